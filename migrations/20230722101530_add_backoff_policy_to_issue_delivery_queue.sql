@@ -0,0 +1,9 @@
+ALTER TABLE issue_delivery_queue ADD COLUMN n_attempts smallint NOT NULL DEFAULT 0;
+ALTER TABLE issue_delivery_queue ADD COLUMN retry_base_delay_in_secs integer NOT NULL DEFAULT 30;
+ALTER TABLE issue_delivery_queue ADD COLUMN retry_max_delay_in_secs integer NOT NULL DEFAULT 3600;
+
+UPDATE issue_delivery_queue
+  SET retry_base_delay_in_secs = execute_after_in_secs
+  WHERE execute_after_in_secs IS NOT NULL;
+
+ALTER TABLE issue_delivery_queue DROP COLUMN execute_after_in_secs;
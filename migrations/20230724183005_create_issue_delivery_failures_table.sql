@@ -0,0 +1,13 @@
+ALTER TABLE issue_delivery_queue ADD COLUMN enqueued_at timestamptz NOT NULL DEFAULT now();
+
+CREATE TABLE issue_delivery_failures (
+  newsletter_issue_id uuid NOT NULL REFERENCES newsletter_issues (id),
+  subscriber_email TEXT NOT NULL,
+  last_error TEXT NOT NULL,
+  n_attempts smallint NOT NULL,
+  retry_base_delay_in_secs integer NOT NULL,
+  retry_max_delay_in_secs integer NOT NULL,
+  enqueued_at timestamptz NOT NULL,
+  failed_at timestamptz NOT NULL,
+  PRIMARY KEY(newsletter_issue_id, subscriber_email)
+);
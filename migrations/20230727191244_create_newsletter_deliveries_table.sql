@@ -0,0 +1,9 @@
+CREATE TABLE newsletter_deliveries (
+  newsletter_issue_id uuid NOT NULL REFERENCES newsletter_issues (id),
+  subscriber_email TEXT NOT NULL,
+  status TEXT NOT NULL CHECK (status IN ('sent', 'failed', 'skipped')),
+  provider_message_id TEXT,
+  n_attempts smallint NOT NULL,
+  delivered_at timestamptz NOT NULL,
+  PRIMARY KEY(newsletter_issue_id, subscriber_email)
+);
@@ -0,0 +1,25 @@
+BEGIN;
+  ALTER TABLE newsletter_issues ADD COLUMN status TEXT NULL;
+  ALTER TABLE newsletter_issues ADD COLUMN created_at timestamptz NULL;
+
+  UPDATE newsletter_issues
+    SET status = CASE
+          WHEN EXISTS (
+            SELECT 1 FROM issue_delivery_queue
+            WHERE issue_delivery_queue.newsletter_issue_id = newsletter_issues.id
+          ) THEN 'sending'
+          ELSE 'sent'
+        END,
+      created_at = published_at::timestamptz;
+
+  ALTER TABLE newsletter_issues ALTER COLUMN status SET NOT NULL;
+  ALTER TABLE newsletter_issues
+    ADD CONSTRAINT newsletter_issues_status_check
+    CHECK (status IN ('draft', 'scheduled', 'sending', 'sent'));
+
+  ALTER TABLE newsletter_issues ALTER COLUMN created_at SET NOT NULL;
+  ALTER TABLE newsletter_issues ALTER COLUMN created_at SET DEFAULT now();
+
+  -- Drafts have not been published yet.
+  ALTER TABLE newsletter_issues ALTER COLUMN published_at DROP NOT NULL;
+COMMIT;
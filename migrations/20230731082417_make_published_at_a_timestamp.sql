@@ -0,0 +1,4 @@
+-- `published_at` is the moment the issue goes out, which can be in the future
+-- for scheduled issues, so it has to be comparable with `now()`.
+ALTER TABLE newsletter_issues
+  ALTER COLUMN published_at TYPE timestamptz USING published_at::timestamptz;
@@ -0,0 +1,10 @@
+BEGIN;
+  ALTER TABLE newsletter_issues DROP CONSTRAINT newsletter_issues_status_check;
+  ALTER TABLE newsletter_issues
+    ADD CONSTRAINT newsletter_issues_status_check
+    CHECK (status IN ('draft', 'scheduled', 'sending', 'paused', 'cancelled', 'sent'));
+
+  -- How many recipients had been sent the issue when it was cancelled.
+  ALTER TABLE newsletter_issues ADD COLUMN n_sent_before_cancel INTEGER NULL;
+  ALTER TABLE newsletter_issues ADD COLUMN cancelled_at timestamptz NULL;
+COMMIT;
@@ -0,0 +1,2 @@
+-- Source of `text_content` and `html_content` for issues written in Markdown.
+ALTER TABLE newsletter_issues ADD COLUMN markdown_content TEXT NULL;
@@ -0,0 +1,10 @@
+-- `slug` is set when an issue is published and never changes afterwards,
+-- it's the address of the issue in the public archive.
+ALTER TABLE newsletter_issues ADD COLUMN slug TEXT NULL UNIQUE;
+ALTER TABLE newsletter_issues ADD COLUMN hidden_from_archive BOOLEAN NOT NULL DEFAULT false;
+
+-- Issues published before the archive existed.
+UPDATE newsletter_issues
+SET slug = COALESCE(NULLIF(trim(both '-' from lower(regexp_replace(title, '[^a-zA-Z0-9]+', '-', 'g'))), ''), 'issue')
+    || '-' || left(id::text, 8)
+WHERE status <> 'draft';
@@ -0,0 +1,3 @@
+-- Hiding or showing an issue changes the feeds without publishing anything,
+-- `Last-Modified` has to account for it.
+ALTER TABLE newsletter_issues ADD COLUMN archive_visibility_changed_at timestamptz NULL;
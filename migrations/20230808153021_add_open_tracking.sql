@@ -0,0 +1,14 @@
+-- Open tracking is opt-in per issue, and subscribers can opt out of it.
+ALTER TABLE newsletter_issues ADD COLUMN track_opens BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE subscriptions ADD COLUMN do_not_track BOOLEAN NOT NULL DEFAULT false;
+
+CREATE TABLE newsletter_opens (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (id) ON DELETE CASCADE,
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id) ON DELETE CASCADE,
+    n_opens INTEGER NOT NULL,
+    first_opened_at timestamptz NOT NULL,
+    last_opened_at timestamptz NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, subscriber_id)
+);
@@ -0,0 +1,21 @@
+-- The links of an issue, registered when it's published. Click tracking tokens
+-- point at a position in here rather than carrying a URL.
+CREATE TABLE newsletter_links (
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    url TEXT NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, position)
+);
+
+-- Every click, not only the first one of each subscriber.
+CREATE TABLE newsletter_clicks (
+    newsletter_issue_id uuid NOT NULL,
+    position INTEGER NOT NULL,
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id) ON DELETE CASCADE,
+    clicked_at timestamptz NOT NULL,
+    FOREIGN KEY (newsletter_issue_id, position)
+        REFERENCES newsletter_links (newsletter_issue_id, position) ON DELETE CASCADE
+);
+CREATE INDEX newsletter_clicks_newsletter_issue_id_idx ON newsletter_clicks (newsletter_issue_id, position);
@@ -0,0 +1,3 @@
+-- When the subscriber last unsubscribed, to attribute unsubscribes to the issue
+-- they were last sent. Unknown for the ones who unsubscribed before this column.
+ALTER TABLE subscriptions ADD COLUMN unsubscribed_at timestamptz NULL;
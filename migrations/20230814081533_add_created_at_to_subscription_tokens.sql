@@ -0,0 +1,4 @@
+-- Confirmation links expire. The tokens issued before this column start their
+-- time to live now.
+ALTER TABLE subscription_tokens ADD COLUMN created_at timestamptz NOT NULL DEFAULT now();
+CREATE INDEX subscription_tokens_subscriber_id_idx ON subscription_tokens (subscriber_id);
@@ -0,0 +1,5 @@
+-- Scheduled issues only pick their recipients once they are due, the retry settings
+-- chosen on publication wait on the issue until then.
+ALTER TABLE newsletter_issues ADD COLUMN n_retries smallint NOT NULL DEFAULT 20;
+ALTER TABLE newsletter_issues ADD COLUMN retry_base_delay_in_secs integer NOT NULL DEFAULT 30;
+ALTER TABLE newsletter_issues ADD COLUMN retry_max_delay_in_secs integer NOT NULL DEFAULT 3600;
@@ -13,6 +13,8 @@ pub struct Credentials {
     pub password: Secret<String>,
 }
 
+type StoredCredentials = (Option<Uuid>, Secret<String>);
+
 pub async fn validate_credentials(
     credentials: Credentials,
     pool: &PgPool,
@@ -81,7 +83,7 @@ pub fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>,
 async fn get_stored_credentials(
     username: &str,
     pool: &PgPool,
-) -> Result<Option<(Option<Uuid>, Secret<String>)>, anyhow::Error> {
+) -> Result<Option<StoredCredentials>, anyhow::Error> {
     let row = sqlx::query!(
         r#"SELECT id, password_hash FROM users WHERE username = $1"#,
         username,
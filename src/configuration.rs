@@ -1,15 +1,29 @@
 use crate::domain::SubscriberEmail;
-use crate::email_client::EmailClient;
-
+use crate::markdown::NewsletterLayout;
+use crate::email_client::{
+    EmailTransport,
+    FileTransport,
+    MailtrapTransport,
+    SmtpSecurity,
+    SmtpTransport,
+    StdoutTransport,
+};
+
+use anyhow::Context;
 use secrecy::{ExposeSecret, Secret};
 use serde_aux::field_attributes::deserialize_number_from_string;
 use sqlx::{postgres::{PgConnectOptions, PgSslMode}, ConnectOptions};
+use std::collections::BTreeMap;
+use std::sync::Arc;
 
 #[derive(serde::Deserialize, Clone)]
 pub struct Settings {
     pub database: DatabaseSettings,
     pub application: ApplicationSettings,
     pub email_client: EmailClientSettings,
+    pub delivery_worker: DeliveryWorkerSettings,
+    pub newsletter_layout: NewsletterLayoutSettings,
+    pub subscriptions: SubscriptionSettings,
     pub redis_uri: Secret<String>,
 }
 
@@ -22,6 +36,49 @@ pub struct ApplicationSettings {
     pub hmac_secret: Secret<String>,
 }
 
+#[derive(serde::Deserialize, Clone)]
+pub struct DeliveryWorkerSettings {
+    /// Number of workers pulling tasks from `issue_delivery_queue`.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub n_workers: usize,
+    /// Number of emails each worker sends concurrently.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_in_flight: usize,
+    /// Size of the delivery workers' connection pool. Every email in flight holds a
+    /// connection, so it must be larger than `n_workers * max_in_flight`: see
+    /// `run_worker_until_stopped`.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_connections: u32,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SubscriptionSettings {
+    /// How long a confirmation link stays valid.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub confirmation_link_ttl_in_hours: u32,
+    /// Minimum time between two confirmation emails resent to the same address.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub resend_cooldown_in_secs: u32,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct NewsletterLayoutSettings {
+    /// HTML page that issues written in Markdown are wrapped in, at `{{ content }}`.
+    pub template_path: String,
+    /// Inline styles for the rendered elements, by tag name.
+    #[serde(default)]
+    pub styles: BTreeMap<String, String>,
+}
+
+impl NewsletterLayoutSettings {
+    pub fn layout(self) -> Result<NewsletterLayout, anyhow::Error> {
+        let template = std::fs::read_to_string(&self.template_path)
+            .with_context(|| format!("Failed to read the newsletter layout at {}", self.template_path))?;
+
+        NewsletterLayout::new(template, self.styles).map_err(anyhow::Error::msg)
+    }
+}
+
 #[derive(serde::Deserialize, Clone)]
 pub struct DatabaseSettings {
     pub username: String,
@@ -35,11 +92,36 @@ pub struct DatabaseSettings {
 
 #[derive(serde::Deserialize, Clone)]
 pub struct EmailClientSettings {
+    #[serde(default)]
+    pub transport: EmailTransportKind,
     pub base_url: String,
     pub sender_email: String,
     pub authorization_token: Secret<String>,
     pub inbox_id: Secret<String>,
     pub timeout_milliseconds: u64,
+    pub smtp: Option<SmtpSettings>,
+    pub file_directory: Option<String>,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailTransportKind {
+    #[default]
+    Mailtrap,
+    Smtp,
+    File,
+    Stdout,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    pub username: Option<String>,
+    pub password: Option<Secret<String>>,
 }
 
 impl EmailClientSettings {
@@ -51,17 +133,37 @@ impl EmailClientSettings {
         std::time::Duration::from_millis(self.timeout_milliseconds)
     }
 
-    pub fn client(self) -> EmailClient {
+    pub fn client(self) -> Arc<dyn EmailTransport> {
         let sender_email = self.sender().expect("Invalid sender email address.");
-
         let timeout = self.timeout();
-        EmailClient::new(
-            self.base_url,
-            sender_email,
-            self.authorization_token,
-            self.inbox_id,
-            timeout,
-        )
+
+        match self.transport {
+            EmailTransportKind::Mailtrap => Arc::new(MailtrapTransport::new(
+                self.base_url,
+                sender_email,
+                self.authorization_token,
+                self.inbox_id,
+                timeout,
+            )),
+            EmailTransportKind::Smtp => {
+                let smtp = self.smtp.expect("Missing `email_client.smtp` settings.");
+                let credentials = smtp.username.zip(smtp.password);
+
+                Arc::new(
+                    SmtpTransport::new(sender_email, &smtp.host, smtp.port, smtp.security, credentials, timeout)
+                        .expect("Failed to build the SMTP transport."),
+                )
+            },
+            EmailTransportKind::File => {
+                let directory = self.file_directory.expect("Missing `email_client.file_directory` setting.");
+
+                Arc::new(
+                    FileTransport::new(sender_email, directory)
+                        .expect("Failed to build the file transport."),
+                )
+            },
+            EmailTransportKind::Stdout => Arc::new(StdoutTransport::new(sender_email)),
+        }
     }
 }
 
@@ -126,7 +228,7 @@ impl DatabaseSettings {
         PgConnectOptions::new()
             .host(&self.host)
             .username(&self.username)
-            .password(&self.password.expose_secret())
+            .password(self.password.expose_secret())
             .port(self.port)
             .ssl_mode(ssl_mode)
     }
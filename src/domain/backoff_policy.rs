@@ -0,0 +1,126 @@
+use rand::Rng;
+use std::time::Duration;
+
+const DEFAULT_BASE_DELAY_IN_SECS: u32 = 30;
+const DEFAULT_MAX_DELAY_IN_SECS: u32 = 60 * 60;
+/// Delays are stored as Postgres `integer`s; a week is well within range and longer
+/// than any sensible wait between two attempts.
+const MAX_RETRY_DELAY_IN_SECS: u32 = 7 * 24 * 60 * 60;
+
+/// Exponential backoff with a cap and "equal jitter": the delay before the n-th
+/// retry is `min(max_delay, base_delay * 2^(n - 1))`, of which the upper half is
+/// randomized so that retries of the same issue don't hit the provider in lockstep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackoffPolicy {
+    base_delay_in_secs: u32,
+    max_delay_in_secs: u32,
+}
+
+impl BackoffPolicy {
+    pub fn parse(base_delay_in_secs: u32, max_delay_in_secs: u32) -> Result<Self, String> {
+        if max_delay_in_secs > MAX_RETRY_DELAY_IN_SECS {
+            return Err(format!(
+                "The retry delays must not be longer than {}s.",
+                MAX_RETRY_DELAY_IN_SECS,
+            ));
+        }
+        if max_delay_in_secs < base_delay_in_secs {
+            return Err(format!(
+                "The maximum retry delay ({}s) must not be shorter than the initial one ({}s).",
+                max_delay_in_secs,
+                base_delay_in_secs,
+            ));
+        }
+
+        Ok(Self { base_delay_in_secs, max_delay_in_secs })
+    }
+
+    pub fn base_delay_in_secs(&self) -> u32 {
+        self.base_delay_in_secs
+    }
+
+    pub fn max_delay_in_secs(&self) -> u32 {
+        self.max_delay_in_secs
+    }
+
+    /// Delay before the given retry attempt, counting from 1.
+    pub fn delay(&self, attempt: u16) -> Duration {
+        let exponent = u32::from(attempt.max(1) - 1).min(31);
+        let capped_delay = u64::from(self.base_delay_in_secs)
+            .saturating_mul(1 << exponent)
+            .min(u64::from(self.max_delay_in_secs));
+
+        let fixed_part = capped_delay / 2;
+        let jitter = rand::thread_rng().gen_range(0..=capped_delay - fixed_part);
+
+        Duration::from_secs(fixed_part + jitter)
+    }
+}
+
+impl Default for BackoffPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay_in_secs: DEFAULT_BASE_DELAY_IN_SECS,
+            max_delay_in_secs: DEFAULT_MAX_DELAY_IN_SECS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BackoffPolicy, MAX_RETRY_DELAY_IN_SECS};
+    use claim::{assert_err, assert_ok};
+    use std::time::Duration;
+
+    #[test]
+    fn max_delay_shorter_than_base_delay_is_rejected() {
+        assert_err!(BackoffPolicy::parse(60, 30));
+    }
+
+    #[test]
+    fn delays_longer_than_the_limit_are_rejected() {
+        assert_err!(BackoffPolicy::parse(30, MAX_RETRY_DELAY_IN_SECS + 1));
+        assert_err!(BackoffPolicy::parse(u32::MAX, u32::MAX));
+    }
+
+    #[test]
+    fn equal_base_and_max_delays_are_accepted() {
+        assert_ok!(BackoffPolicy::parse(30, 30));
+    }
+
+    #[test]
+    fn zero_base_delay_retries_immediately() {
+        let policy = BackoffPolicy::parse(0, 0).unwrap();
+
+        assert_eq!(policy.delay(1), Duration::ZERO);
+        assert_eq!(policy.delay(10), Duration::ZERO);
+    }
+
+    #[test]
+    fn first_retry_waits_between_half_and_the_whole_base_delay() {
+        let policy = BackoffPolicy::parse(30, 3600).unwrap();
+
+        for _ in 0..100 {
+            let delay = policy.delay(1);
+            assert!(delay >= Duration::from_secs(15) && delay <= Duration::from_secs(30));
+        }
+    }
+
+    #[test]
+    fn delay_grows_exponentially() {
+        let policy = BackoffPolicy::parse(10, 3600).unwrap();
+
+        let delay = policy.delay(4);
+
+        assert!(delay >= Duration::from_secs(40) && delay <= Duration::from_secs(80));
+    }
+
+    #[quickcheck_macros::quickcheck]
+    fn delay_never_exceeds_the_cap(base: u32, max: u32, attempt: u16) -> bool {
+        let (base, max) = (base % (MAX_RETRY_DELAY_IN_SECS + 1), max % (MAX_RETRY_DELAY_IN_SECS + 1));
+        let (base, max) = if base <= max { (base, max) } else { (max, base) };
+        let policy = BackoffPolicy::parse(base, max).unwrap();
+
+        policy.delay(attempt) <= Duration::from_secs(u64::from(max))
+    }
+}
@@ -0,0 +1,84 @@
+use secrecy::Secret;
+use uuid::Uuid;
+
+use crate::domain::signed_token;
+
+const PAYLOAD_LENGTH: usize = 36;
+const DOMAIN: &[u8] = b"click-tracking";
+
+/// Which link of which issue a subscriber clicked.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TrackedClick {
+    pub newsletter_issue_id: Uuid,
+    pub subscriber_id: Uuid,
+    /// The link's position in `newsletter_links`.
+    pub position: u32,
+}
+
+/// A `TrackedClick`, signed. The token only points at a link registered when the issue
+/// was published, never at a URL: nobody can make us redirect to a URL of their choosing.
+#[derive(Debug)]
+pub struct ClickTrackingToken(String);
+
+impl ClickTrackingToken {
+    pub fn generate(click: &TrackedClick, hmac_secret: &Secret<String>) -> Self {
+        let mut payload = click.newsletter_issue_id.as_bytes().to_vec();
+        payload.extend_from_slice(click.subscriber_id.as_bytes());
+        payload.extend_from_slice(&click.position.to_be_bytes());
+
+        Self(signed_token::sign(DOMAIN, &payload, hmac_secret))
+    }
+
+    pub fn parse(token: &str, hmac_secret: &Secret<String>) -> Result<TrackedClick, String> {
+        let invalid_token = || "The click tracking token is not valid.".to_string();
+
+        let click = signed_token::verify(DOMAIN, token, PAYLOAD_LENGTH, hmac_secret)
+            .ok_or_else(invalid_token)?;
+
+        let newsletter_issue_id = Uuid::from_slice(&click[..16]).map_err(|_| invalid_token())?;
+        let subscriber_id = Uuid::from_slice(&click[16..32]).map_err(|_| invalid_token())?;
+        let position = u32::from_be_bytes(click[32..].try_into().map_err(|_| invalid_token())?);
+
+        Ok(TrackedClick { newsletter_issue_id, subscriber_id, position })
+    }
+}
+
+impl AsRef<str> for ClickTrackingToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClickTrackingToken, TrackedClick};
+    use claim::{assert_err, assert_ok_eq};
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    fn click() -> TrackedClick {
+        TrackedClick { newsletter_issue_id: Uuid::new_v4(), subscriber_id: Uuid::new_v4(), position: 3 }
+    }
+
+    #[test]
+    fn a_generated_token_is_parsed_back_to_the_click() {
+        let secret = Secret::new(Uuid::new_v4().to_string());
+        let click = click();
+
+        let token = ClickTrackingToken::generate(&click, &secret);
+
+        assert_ok_eq!(ClickTrackingToken::parse(token.as_ref(), &secret), click);
+    }
+
+    #[test]
+    fn a_token_pointing_at_another_link_is_rejected() {
+        let secret = Secret::new(Uuid::new_v4().to_string());
+        let token = ClickTrackingToken::generate(&click(), &secret);
+
+        let mut payload = base64::decode_config(token.as_ref(), base64::URL_SAFE_NO_PAD).unwrap();
+        payload[35] ^= 1;
+        let tampered = base64::encode_config(payload, base64::URL_SAFE_NO_PAD);
+
+        assert_err!(ClickTrackingToken::parse(&tampered, &secret));
+    }
+}
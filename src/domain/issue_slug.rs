@@ -0,0 +1,72 @@
+/// Used when nothing is left of the title, e.g. a title in another script.
+const FALLBACK_SLUG: &str = "issue";
+const MAX_LENGTH: usize = 80;
+
+/// The address of a published issue in the archive, e.g. `big-news-for-july`.
+#[derive(Debug)]
+pub struct IssueSlug(String);
+
+impl IssueSlug {
+    /// Lowercase ASCII letters and digits, separated by single dashes.
+    pub fn from_title(title: &str) -> IssueSlug {
+        let mut slug = String::new();
+        for word in title.split(|c: char| !c.is_ascii_alphanumeric()).filter(|w| !w.is_empty()) {
+            if slug.is_empty() {
+                // Only a single word this long gets cut.
+                slug.push_str(&word[..word.len().min(MAX_LENGTH)]);
+            } else if slug.len() + word.len() < MAX_LENGTH {
+                slug.push('-');
+                slug.push_str(word);
+            } else {
+                break;
+            }
+        }
+
+        if slug.is_empty() {
+            slug.push_str(FALLBACK_SLUG);
+        }
+        Self(slug.to_ascii_lowercase())
+    }
+}
+
+impl AsRef<str> for IssueSlug {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::issue_slug::IssueSlug;
+
+    #[test]
+    fn titles_become_lowercase_words_joined_by_dashes() {
+        assert_eq!(IssueSlug::from_title("  Big News: July's Edition! ").as_ref(), "big-news-july-s-edition");
+    }
+
+    #[test]
+    fn titles_without_ascii_letters_get_a_fallback() {
+        assert_eq!(IssueSlug::from_title("Новини").as_ref(), "issue");
+        assert_eq!(IssueSlug::from_title("!!!").as_ref(), "issue");
+    }
+
+    #[test]
+    fn long_titles_are_cut_at_a_word_boundary() {
+        let slug = IssueSlug::from_title(&"word ".repeat(50));
+
+        assert!(slug.as_ref().len() <= 80);
+        assert!(slug.as_ref().ends_with("word"));
+    }
+
+    #[quickcheck_macros::quickcheck]
+    fn slugs_are_always_url_safe(title: String) -> bool {
+        let slug = IssueSlug::from_title(&title);
+        let slug = slug.as_ref();
+
+        !slug.is_empty()
+            && !slug.starts_with('-')
+            && !slug.ends_with('-')
+            && !slug.contains("--")
+            && slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    }
+}
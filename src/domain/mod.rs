@@ -1,13 +1,28 @@
+mod backoff_policy;
+mod click_tracking_token;
 mod current_password;
+mod issue_slug;
 mod new_subscriber;
 mod new_password;
+mod newsletter_html;
+mod newsletter_template;
+mod open_tracking_token;
 mod reset_password;
+mod signed_token;
 mod subscriber_email;
 mod subscriber_name;
+mod unsubscribe_token;
 
+pub use backoff_policy::BackoffPolicy;
+pub use click_tracking_token::{ClickTrackingToken, TrackedClick};
 pub use current_password::CurrentPassword;
+pub use issue_slug::IssueSlug;
 pub use new_subscriber::NewSubscriber;
 pub use new_password::NewPassword;
+pub use newsletter_html::NewsletterHtml;
+pub use newsletter_template::{NewsletterTemplate, TemplateValues};
+pub use open_tracking_token::OpenTrackingToken;
 pub use reset_password::ResetPassword;
 pub use subscriber_email::SubscriberEmail;
 pub use subscriber_name::SubscriberName;
+pub use unsubscribe_token::UnsubscribeToken;
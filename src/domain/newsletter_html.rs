@@ -0,0 +1,214 @@
+use std::collections::{BTreeMap, HashSet};
+
+/// Attributes that emails rely on for their layout, on top of ammonia's defaults.
+const LAYOUT_ATTRIBUTES: [&str; 10] = [
+    "style", "align", "valign", "width", "height", "bgcolor", "border", "cellpadding", "cellspacing", "role",
+];
+/// Elements whose content goes away with them.
+const CONTENT_TAGS: [&str; 2] = ["script", "style"];
+
+/// Hand-written HTML content of an issue, cleaned with an allow-list of elements,
+/// attributes and link schemes. Scripts, forms, iframes, event handlers and
+/// `javascript:` links don't make it to subscribers.
+#[derive(Debug)]
+pub struct NewsletterHtml(String);
+
+impl NewsletterHtml {
+    /// Unsafe markup is removed rather than rejected: returns the clean HTML along
+    /// with a description of what was removed, e.g. "the script element".
+    pub fn sanitize(html: &str) -> (NewsletterHtml, Vec<String>) {
+        let clean = ammonia::Builder::default()
+            .add_generic_attributes(&LAYOUT_ATTRIBUTES)
+            .url_schemes(HashSet::from(["http", "https", "mailto"]))
+            .link_rel(None)
+            .clean(html)
+            .to_string();
+
+        let before = inventory(html);
+        let after = inventory(&clean);
+        let removed = before
+            .into_iter()
+            .filter(|(markup, count)| after.get(markup).unwrap_or(&0) < count)
+            .map(|(markup, _)| markup)
+            .collect();
+
+        (Self(clean), removed)
+    }
+}
+
+impl AsRef<str> for NewsletterHtml {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Counts the elements and attributes in `html`, e.g. "the a element" or "the onclick
+/// attribute on a". Only meant to tell editors what the sanitizer dropped, so it
+/// doesn't try hard to be a real HTML tokenizer.
+fn inventory(html: &str) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+
+        let tag: String = take_name(&mut rest);
+        if tag.is_empty() {
+            continue;
+        }
+        *counts.entry(format!("the {} element", tag)).or_insert(0) += 1;
+
+        loop {
+            rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+            if rest.is_empty() || rest.starts_with('>') {
+                break;
+            }
+
+            let attribute = take_name(&mut rest);
+            if attribute.is_empty() {
+                // Not something we understand, skip a character and look again.
+                let mut chars = rest.chars();
+                chars.next();
+                rest = chars.as_str();
+                continue;
+            }
+            *counts.entry(format!("the {} attribute on {}", attribute, tag)).or_insert(0) += 1;
+
+            rest = rest.trim_start();
+            if let Some(value) = rest.strip_prefix('=') {
+                rest = skip_attribute_value(value.trim_start());
+            }
+        }
+
+        if CONTENT_TAGS.contains(&tag.as_str()) {
+            if let Some(end) = rest.to_ascii_lowercase().find(&format!("</{}", tag)) {
+                rest = &rest[end..];
+            }
+        }
+    }
+
+    counts
+}
+
+fn take_name(rest: &mut &str) -> String {
+    let end = rest
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == ':'))
+        .unwrap_or(rest.len());
+    let (name, tail) = rest.split_at(end);
+    *rest = tail;
+
+    // Names start with a letter: `a < b` or `</p>` are not tags.
+    if name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name.to_ascii_lowercase()
+    } else {
+        String::new()
+    }
+}
+
+fn skip_attribute_value(value: &str) -> &str {
+    match value.chars().next() {
+        Some(quote @ ('"' | '\'')) => match value[1..].find(quote) {
+            Some(end) => &value[end + 2..],
+            None => "",
+        },
+        _ => {
+            let end = value.find(|c: char| c.is_whitespace() || c == '>').unwrap_or(value.len());
+            &value[end..]
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NewsletterHtml;
+    use quickcheck::Arbitrary;
+
+    const SAFE_SNIPPETS: [&str; 8] = [
+        "<p>Hello</p>",
+        "<h1>Big news</h1>",
+        r#"<a href="https://example.com">link</a>"#,
+        r#"<a href="{{ unsubscribe_url }}">Unsubscribe</a>"#,
+        r#"<p style="margin: 0;">Styled</p>"#,
+        "<ul><li>one</li><li>two</li></ul>",
+        r#"<img src="https://example.com/a.png" alt="A picture">"#,
+        "Hi {{ name }}",
+    ];
+    const UNSAFE_SNIPPETS: [&str; 7] = [
+        "<script>alert(1)</script>",
+        r#"<iframe src="https://tracker.example.com"></iframe>"#,
+        r#"<form action="https://example.com"><input name="password"></form>"#,
+        r#"<a href="javascript:alert(1)">click</a>"#,
+        r#"<img src="x" onerror="alert(1)">"#,
+        r#"<p onclick="alert(1)">Click me</p>"#,
+        "<style>body { display: none; }</style>",
+    ];
+
+    /// Random mix of safe and unsafe snippets.
+    #[derive(Debug, Clone)]
+    struct HtmlFixture(String);
+
+    impl Arbitrary for HtmlFixture {
+        fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+            let n_snippets = g.size() % 10 + 1;
+            let html = (0..n_snippets)
+                .map(|_| {
+                    let snippets = if bool::arbitrary(g) { &SAFE_SNIPPETS[..] } else { &UNSAFE_SNIPPETS[..] };
+                    snippets[usize::arbitrary(g) % snippets.len()]
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            Self(html)
+        }
+    }
+
+    /// Only safe snippets.
+    #[derive(Debug, Clone)]
+    struct SafeHtmlFixture(String);
+
+    impl Arbitrary for SafeHtmlFixture {
+        fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+            let n_snippets = g.size() % 10 + 1;
+            let html = (0..n_snippets)
+                .map(|_| SAFE_SNIPPETS[usize::arbitrary(g) % SAFE_SNIPPETS.len()])
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            Self(html)
+        }
+    }
+
+    #[quickcheck_macros::quickcheck]
+    fn scripts_and_event_handlers_never_survive(html: HtmlFixture) -> bool {
+        let (clean, _) = NewsletterHtml::sanitize(&html.0);
+        let clean = clean.as_ref();
+
+        ["<script", "<iframe", "<form", "<input", "<style", "javascript:", "onerror", "onclick"]
+            .iter()
+            .all(|unsafe_markup| !clean.contains(unsafe_markup))
+    }
+
+    #[quickcheck_macros::quickcheck]
+    fn sanitizing_twice_changes_nothing(html: HtmlFixture) -> bool {
+        let (once, _) = NewsletterHtml::sanitize(&html.0);
+        let (twice, removed) = NewsletterHtml::sanitize(once.as_ref());
+
+        once.as_ref() == twice.as_ref() && removed.is_empty()
+    }
+
+    #[quickcheck_macros::quickcheck]
+    fn safe_html_is_left_alone(html: SafeHtmlFixture) -> bool {
+        let (clean, removed) = NewsletterHtml::sanitize(&html.0);
+
+        clean.as_ref() == html.0 && removed.is_empty()
+    }
+
+    #[test]
+    fn what_was_removed_is_reported() {
+        let (_, removed) = NewsletterHtml::sanitize(
+            r#"<p onclick="alert(1)">Hi</p><script>if (a<b) {}</script><a href="https://example.com">ok</a>"#,
+        );
+
+        assert_eq!(removed, ["the onclick attribute on p", "the script element"]);
+    }
+}
@@ -0,0 +1,137 @@
+use crate::utils::escape_html;
+
+const PLACEHOLDERS: [&str; 3] = ["name", "email", "unsubscribe_url"];
+
+/// An issue body with `{{ name }}`, `{{ email }}` and `{{ unsubscribe_url }}`
+/// placeholders, filled in for every subscriber at send time.
+#[derive(Debug)]
+pub struct NewsletterTemplate(String);
+
+/// What the placeholders are replaced with.
+pub struct TemplateValues<'a> {
+    pub name: &'a str,
+    pub email: &'a str,
+    pub unsubscribe_url: &'a str,
+}
+
+enum Segment<'a> {
+    Text(&'a str),
+    Placeholder(&'a str),
+}
+
+impl NewsletterTemplate {
+    pub fn parse(template: String) -> Result<NewsletterTemplate, String> {
+        for segment in segments(&template) {
+            if let Segment::Placeholder(name) = segment {
+                if !PLACEHOLDERS.contains(&name) {
+                    return Err(format!(
+                        "{{{{ {} }}}} is not a known placeholder, use {{{{ name }}}}, {{{{ email }}}} or {{{{ unsubscribe_url }}}}.",
+                        name,
+                    ));
+                }
+            }
+        }
+
+        Ok(Self(template))
+    }
+
+    pub fn render_text(&self, values: &TemplateValues) -> String {
+        self.render(values, str::to_owned)
+    }
+
+    /// Values are escaped, a subscriber's name can't inject markup.
+    pub fn render_html(&self, values: &TemplateValues) -> String {
+        self.render(values, escape_html)
+    }
+
+    fn render(&self, values: &TemplateValues, escape: impl Fn(&str) -> String) -> String {
+        let mut rendered = String::with_capacity(self.0.len());
+
+        for segment in segments(&self.0) {
+            match segment {
+                Segment::Text(text) => rendered.push_str(text),
+                Segment::Placeholder("name") => rendered.push_str(&escape(values.name)),
+                Segment::Placeholder("email") => rendered.push_str(&escape(values.email)),
+                Segment::Placeholder("unsubscribe_url") => rendered.push_str(&escape(values.unsubscribe_url)),
+                Segment::Placeholder(_) => unreachable!("placeholders are checked by `NewsletterTemplate::parse`"),
+            }
+        }
+
+        rendered
+    }
+}
+
+impl AsRef<str> for NewsletterTemplate {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Splits a template around its `{{ ... }}` placeholders.
+/// A `{{` without a matching `}}` is plain text.
+fn segments(template: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start + 2..].find("}}") {
+            Some(end) => start + 2 + end,
+            None => break,
+        };
+
+        segments.push(Segment::Text(&rest[..start]));
+        segments.push(Segment::Placeholder(rest[start + 2..end].trim()));
+        rest = &rest[end + 2..];
+    }
+
+    segments.push(Segment::Text(rest));
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::newsletter_template::{NewsletterTemplate, TemplateValues};
+    use claim::{assert_err, assert_ok};
+
+    fn values() -> TemplateValues<'static> {
+        TemplateValues {
+            name: "Ursula & co",
+            email: "ursula@example.com",
+            unsubscribe_url: "https://example.com/subscriptions/unsubscribe?unsubscribe_token=abc",
+        }
+    }
+
+    #[test]
+    fn placeholders_are_replaced_with_the_subscriber_details() {
+        let template = NewsletterTemplate::parse(
+            "Hi {{ name }} ({{email}}), leave at {{  unsubscribe_url }}".into(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            template.render_text(&values()),
+            "Hi Ursula & co (ursula@example.com), leave at https://example.com/subscriptions/unsubscribe?unsubscribe_token=abc",
+        );
+    }
+
+    #[test]
+    fn values_are_escaped_in_html() {
+        let template = NewsletterTemplate::parse("<p>Hi {{ name }}</p>".into()).unwrap();
+
+        assert_eq!(template.render_html(&values()), "<p>Hi Ursula &amp; co</p>");
+    }
+
+    #[test]
+    fn unknown_placeholders_are_rejected() {
+        assert_err!(NewsletterTemplate::parse("Hi {{ first_name }}".into()));
+        assert_err!(NewsletterTemplate::parse("Hi {{}}".into()));
+    }
+
+    #[test]
+    fn text_without_placeholders_is_left_alone() {
+        let template = NewsletterTemplate::parse("a { b } {{ c".into());
+
+        assert_ok!(&template);
+        assert_eq!(template.unwrap().render_text(&values()), "a { b } {{ c");
+    }
+}
@@ -0,0 +1,59 @@
+use secrecy::Secret;
+use uuid::Uuid;
+
+use crate::domain::signed_token;
+
+const IDS_LENGTH: usize = 32;
+const DOMAIN: &[u8] = b"open-tracking";
+
+/// The issue id and the subscriber id, signed. Nobody can record opens on behalf
+/// of another subscriber without the application `hmac_secret`.
+#[derive(Debug)]
+pub struct OpenTrackingToken(String);
+
+impl OpenTrackingToken {
+    pub fn generate(newsletter_issue_id: Uuid, subscriber_id: Uuid, hmac_secret: &Secret<String>) -> Self {
+        let mut payload = newsletter_issue_id.as_bytes().to_vec();
+        payload.extend_from_slice(subscriber_id.as_bytes());
+
+        Self(signed_token::sign(DOMAIN, &payload, hmac_secret))
+    }
+
+    /// Returns the ids of the issue and of the subscriber the token was issued for.
+    pub fn parse(token: &str, hmac_secret: &Secret<String>) -> Result<(Uuid, Uuid), String> {
+        let invalid_token = || "The open tracking token is not valid.".to_string();
+
+        let ids = signed_token::verify(DOMAIN, token, IDS_LENGTH, hmac_secret)
+            .ok_or_else(invalid_token)?;
+
+        let (newsletter_issue_id, subscriber_id) = ids.split_at(IDS_LENGTH / 2);
+        let newsletter_issue_id = Uuid::from_slice(newsletter_issue_id).map_err(|_| invalid_token())?;
+        let subscriber_id = Uuid::from_slice(subscriber_id).map_err(|_| invalid_token())?;
+
+        Ok((newsletter_issue_id, subscriber_id))
+    }
+}
+
+impl AsRef<str> for OpenTrackingToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OpenTrackingToken;
+    use claim::assert_ok_eq;
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    #[test]
+    fn a_generated_token_is_parsed_back_to_the_issue_and_subscriber_ids() {
+        let secret = Secret::new(Uuid::new_v4().to_string());
+        let (newsletter_issue_id, subscriber_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let token = OpenTrackingToken::generate(newsletter_issue_id, subscriber_id, &secret);
+
+        assert_ok_eq!(OpenTrackingToken::parse(token.as_ref(), &secret), (newsletter_issue_id, subscriber_id));
+    }
+}
@@ -0,0 +1,105 @@
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// The scheme behind the tokens we put in emails: a fixed-length payload followed by
+/// an HMAC-SHA256 tag of it, base64url-encoded. They can't be forged without the
+/// application `hmac_secret`.
+///
+/// Each kind of token signs with its own `domain`, so that a token of one kind is never
+/// accepted as another. Unsubscribe tokens predate that and use an empty domain.
+pub fn sign(domain: &[u8], payload: &[u8], hmac_secret: &Secret<String>) -> String {
+    let tag = mac(domain, hmac_secret).chain_update(payload).finalize().into_bytes();
+    let mut token = payload.to_vec();
+    token.extend_from_slice(&tag);
+
+    base64::encode_config(token, base64::URL_SAFE_NO_PAD)
+}
+
+/// Returns the payload if the token is well-formed and its tag checks out.
+pub fn verify(domain: &[u8], token: &str, payload_length: usize, hmac_secret: &Secret<String>) -> Option<Vec<u8>> {
+    let mut payload = base64::decode_config(token, base64::URL_SAFE_NO_PAD).ok()?;
+
+    if payload.len() <= payload_length {
+        return None;
+    }
+
+    let tag = payload.split_off(payload_length);
+    mac(domain, hmac_secret)
+        .chain_update(&payload)
+        .verify_slice(&tag)
+        .ok()?;
+
+    Some(payload)
+}
+
+fn mac(domain: &[u8], hmac_secret: &Secret<String>) -> HmacSha256 {
+    HmacSha256::new_from_slice(hmac_secret.expose_secret().as_bytes())
+        .expect("HMAC can take a key of any size")
+        .chain_update(domain)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sign, verify};
+    use claim::{assert_none, assert_some_eq};
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    const DOMAIN: &[u8] = b"test";
+    const PAYLOAD: &[u8] = b"sixteen bytes!!!";
+
+    fn secret() -> Secret<String> {
+        Secret::new(Uuid::new_v4().to_string())
+    }
+
+    #[test]
+    fn a_signed_token_is_verified_back_to_its_payload() {
+        let secret = secret();
+
+        let token = sign(DOMAIN, PAYLOAD, &secret);
+
+        assert_some_eq!(verify(DOMAIN, &token, PAYLOAD.len(), &secret), PAYLOAD.to_vec());
+    }
+
+    #[test]
+    fn a_token_signed_with_another_secret_is_rejected() {
+        let token = sign(DOMAIN, PAYLOAD, &secret());
+
+        assert_none!(verify(DOMAIN, &token, PAYLOAD.len(), &secret()));
+    }
+
+    #[test]
+    fn a_token_signed_for_another_domain_is_rejected() {
+        let secret = secret();
+        let token = sign(b"other", PAYLOAD, &secret);
+
+        assert_none!(verify(DOMAIN, &token, PAYLOAD.len(), &secret));
+    }
+
+    #[test]
+    fn a_tampered_token_is_rejected() {
+        let secret = secret();
+        let token = sign(DOMAIN, PAYLOAD, &secret);
+
+        let mut bytes = base64::decode_config(&token, base64::URL_SAFE_NO_PAD).unwrap();
+        bytes[0] ^= 1;
+        let tampered = base64::encode_config(bytes, base64::URL_SAFE_NO_PAD);
+
+        assert_none!(verify(DOMAIN, &tampered, PAYLOAD.len(), &secret));
+    }
+
+    #[test]
+    fn a_bare_payload_is_rejected() {
+        let token = base64::encode_config(PAYLOAD, base64::URL_SAFE_NO_PAD);
+
+        assert_none!(verify(DOMAIN, &token, PAYLOAD.len(), &secret()));
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        assert_none!(verify(DOMAIN, "not a token!", PAYLOAD.len(), &secret()));
+    }
+}
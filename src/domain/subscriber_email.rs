@@ -7,7 +7,11 @@ impl SubscriberEmail {
     pub fn parse(email: String) -> Result<SubscriberEmail, (String, String)> {
         if validate_email(&email) { return Ok(Self(email)) }
 
-        Err(("email must not be empty.".into(), email))
+        if email.trim().is_empty() {
+            return Err(("The email address must not be empty.".into(), email));
+        }
+
+        Err((format!("{} is not a valid email address.", email), email))
     }
 }
 
@@ -10,7 +10,9 @@ impl SubscriberName {
 
         let forbidden_characters = ['/', '(', ')', '"', '<', '>', '\\', '{', '}'];
         let contains_forbidden_characters = name.chars().any(|g| forbidden_characters.contains(&g));
-        if is_empty_or_whitespace || is_too_long || contains_forbidden_characters {
+        if is_empty_or_whitespace {
+            Err("The name must not be empty.".to_string())
+        } else if is_too_long || contains_forbidden_characters {
             Err(format!("{} is not a valid subscriber name.", name))
         } else {
             Ok(Self(name))
@@ -0,0 +1,52 @@
+use secrecy::Secret;
+use uuid::Uuid;
+
+use crate::domain::signed_token;
+
+const SUBSCRIBER_ID_LENGTH: usize = 16;
+/// Empty: unsubscribe links that went out before tokens had domains keep working.
+const DOMAIN: &[u8] = b"";
+
+/// The subscriber id, signed. It is safe to put it in every email we send to the subscriber.
+#[derive(Debug)]
+pub struct UnsubscribeToken(String);
+
+impl UnsubscribeToken {
+    pub fn generate(subscriber_id: Uuid, hmac_secret: &Secret<String>) -> Self {
+        Self(signed_token::sign(DOMAIN, subscriber_id.as_bytes(), hmac_secret))
+    }
+
+    /// Returns the id of the subscriber the token was issued for.
+    pub fn parse(token: &str, hmac_secret: &Secret<String>) -> Result<Uuid, String> {
+        let invalid_token = || "The unsubscribe token is not valid.".to_string();
+
+        let subscriber_id = signed_token::verify(DOMAIN, token, SUBSCRIBER_ID_LENGTH, hmac_secret)
+            .ok_or_else(invalid_token)?;
+
+        Uuid::from_slice(&subscriber_id).map_err(|_| invalid_token())
+    }
+}
+
+impl AsRef<str> for UnsubscribeToken {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UnsubscribeToken;
+    use claim::assert_ok_eq;
+    use secrecy::Secret;
+    use uuid::Uuid;
+
+    #[test]
+    fn a_generated_token_is_parsed_back_to_the_subscriber_id() {
+        let secret = Secret::new(Uuid::new_v4().to_string());
+        let subscriber_id = Uuid::new_v4();
+
+        let token = UnsubscribeToken::generate(subscriber_id, &secret);
+
+        assert_ok_eq!(UnsubscribeToken::parse(token.as_ref(), &secret), subscriber_id);
+    }
+}
@@ -0,0 +1,134 @@
+use crate::domain::SubscriberEmail;
+use crate::email_client::{raw_message, EmailTransport, SendEmailError};
+
+use anyhow::Context;
+use lettre::{AsyncFileTransport, AsyncTransport, Tokio1Executor};
+use std::io::Write;
+use std::path::Path;
+
+/// Writes every email to `<directory>/<uuid>.eml` instead of sending it.
+/// Meant for local development: the files open in any mail client.
+pub struct FileTransport {
+    sender: SubscriberEmail,
+    mailer: AsyncFileTransport<Tokio1Executor>,
+}
+
+impl FileTransport {
+    pub fn new(sender: SubscriberEmail, directory: impl AsRef<Path>) -> Result<Self, anyhow::Error> {
+        std::fs::create_dir_all(directory.as_ref())
+            .with_context(|| format!("Failed to create {}", directory.as_ref().display()))?;
+
+        Ok(Self { sender, mailer: AsyncFileTransport::new(directory) })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for FileTransport {
+    fn sender(&self) -> &SubscriberEmail {
+        &self.sender
+    }
+
+    async fn send_email(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<Option<String>, SendEmailError> {
+        let (envelope, message) = raw_message(
+            &self.sender,
+            recipient,
+            subject,
+            html_content,
+            text_content,
+            headers,
+        )?;
+
+        let id = self
+            .mailer
+            .send_raw(&envelope, &message)
+            .await
+            .context("Failed to write the email to disk")?;
+        tracing::info!(email_id = %id, "Email written to disk");
+
+        Ok(Some(id))
+    }
+}
+
+/// Prints every email to stdout instead of sending it.
+pub struct StdoutTransport {
+    sender: SubscriberEmail,
+}
+
+impl StdoutTransport {
+    pub fn new(sender: SubscriberEmail) -> Self {
+        Self { sender }
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for StdoutTransport {
+    fn sender(&self) -> &SubscriberEmail {
+        &self.sender
+    }
+
+    async fn send_email(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<Option<String>, SendEmailError> {
+        let (_, message) = raw_message(
+            &self.sender,
+            recipient,
+            subject,
+            html_content,
+            text_content,
+            headers,
+        )?;
+
+        let mut stdout = std::io::stdout().lock();
+        stdout
+            .write_all(&message)
+            .and_then(|_| stdout.write_all(b"\n"))
+            .context("Failed to write the email to stdout")?;
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::SubscriberEmail;
+    use crate::email_client::{EmailTransport, FileTransport};
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn file_transport_writes_an_eml_file_per_email() {
+        let directory = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        let sender = SubscriberEmail::parse("sender@example.com".into()).unwrap();
+        let recipient = SubscriberEmail::parse("recipient@example.com".into()).unwrap();
+        let transport = FileTransport::new(sender, &directory).unwrap();
+
+        transport
+            .send_email(&recipient, "Subject", "<p>HTML body</p>", "Text body", &[])
+            .await
+            .unwrap();
+
+        let files: Vec<_> = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].extension().unwrap(), "eml");
+
+        let content = std::fs::read_to_string(&files[0]).unwrap();
+        assert!(content.contains("To: recipient@example.com"));
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
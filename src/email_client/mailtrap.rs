@@ -0,0 +1,378 @@
+use crate::domain::SubscriberEmail;
+use crate::email_client::{EmailTransport, SendEmailError};
+
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, Response, StatusCode};
+use secrecy::{Secret, ExposeSecret};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Sends emails through Mailtrap's HTTP API.
+pub struct MailtrapTransport {
+    base_url: String,
+    http_client: Client,
+    sender: SubscriberEmail,
+    authorization_token: Secret<String>,
+    inbox_id: Secret<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct SendEmailRequest<'a> {
+    from: Address<'a>,
+    to: Vec<Address<'a>>,
+    subject: &'a str,
+    html: &'a str,
+    text: &'a str,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    headers: HashMap<&'a str, &'a str>,
+}
+
+#[derive(serde::Serialize)]
+pub struct Address<'a> {
+    pub email: &'a str,
+}
+
+#[derive(serde::Deserialize)]
+struct SendEmailResponse {
+    #[serde(default)]
+    message_ids: Vec<String>,
+}
+
+impl MailtrapTransport {
+    pub fn new(
+        base_url: String,
+        sender: SubscriberEmail,
+        authorization_token: Secret<String>,
+        inbox_id: Secret<String>,
+        timeout: std::time::Duration,
+    ) -> Self {
+        let http_client = Client::builder()
+            .timeout(timeout)
+            .build()
+            .unwrap();
+
+        Self {
+            base_url,
+            http_client,
+            sender,
+            authorization_token,
+            inbox_id,
+        }
+    }
+
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for MailtrapTransport {
+    fn sender(&self) -> &SubscriberEmail {
+        &self.sender
+    }
+
+    async fn send_email(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<Option<String>, SendEmailError> {
+        let url = format!("{}/api/send/{}", self.base_url, self.inbox_id.expose_secret());
+        let request_body = SendEmailRequest {
+            from: Address { email: self.sender.as_ref() },
+            to: vec![Address { email: recipient.as_ref() }],
+            subject,
+            html: html_content,
+            text: text_content,
+            headers: headers.iter().copied().collect(),
+        };
+
+        let response = self
+            .http_client
+            .post(&url)
+            .header(
+                "Api-Token",
+                self.authorization_token.expose_secret()
+            )
+            .json(&request_body)
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_timeout() {
+                    SendEmailError::Timeout(e.into())
+                } else {
+                    SendEmailError::UnexpectedError(e.into())
+                }
+            })?;
+
+        if !response.status().is_success() {
+            return Err(classify_error_response(response).await);
+        }
+
+        // The email has been accepted at this point, a body we can't make sense of
+        // must not turn it into a failure.
+        let message_id = response
+            .json::<SendEmailResponse>()
+            .await
+            .ok()
+            .and_then(|body| body.message_ids.into_iter().next());
+
+        Ok(message_id)
+    }
+}
+
+async fn classify_error_response(response: Response) -> SendEmailError {
+    let status = response.status();
+
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+
+        return SendEmailError::RateLimited { retry_after };
+    }
+
+    let message = match response.text().await {
+        Ok(body) if !body.trim().is_empty() => body,
+        _ => status.canonical_reason().unwrap_or_default().to_string(),
+    };
+
+    if status.is_server_error() {
+        SendEmailError::ServerError { status: status.as_u16(), message }
+    } else if status.is_client_error() {
+        SendEmailError::Rejected { status: status.as_u16(), message }
+    } else {
+        SendEmailError::UnexpectedError(anyhow::anyhow!("Unexpected response status {}: {}", status, message))
+    }
+}
+
+/// `Retry-After` is either a number of seconds or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let retry_at = chrono::DateTime::parse_from_rfc2822(value.trim()).ok()?;
+    let delay = retry_at.with_timezone(&chrono::Utc) - chrono::Utc::now();
+
+    Some(delay.to_std().unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod tests {
+    use claim::{assert_err, assert_ok};
+    use crate::domain::SubscriberEmail;
+    use crate::email_client::{EmailTransport, MailtrapTransport, SendEmailError};
+    use super::parse_retry_after;
+    use fake::faker::internet::en::SafeEmail;
+    use fake::faker::lorem::en::{Sentence, Paragraph};
+    use fake::{Fake, Faker};
+    use secrecy::Secret;
+    use wiremock::matchers::{any, header_exists, header, path, method};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    struct SendEmailBodyMatcher;
+
+    impl wiremock::Match for SendEmailBodyMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            let result: Result<serde_json::Value, _> = 
+                serde_json::from_slice(&request.body);
+
+            match result {
+                Ok(body) => { 
+                    body.get("from").unwrap().get("email").is_some()
+                        && body.get("to").unwrap()[0].get("email").is_some()
+                        && body.get("subject").is_some()
+                        && body.get("html").is_some()
+                        && body.get("text").is_some()
+                },
+                Err(_) => false,
+            }
+        }
+    }
+
+    fn subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    fn content() -> String {
+        Paragraph(1..10).fake()
+    }
+
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).unwrap()
+    }
+
+    fn email_client(base_url: String, inbox_id: Secret<String>) -> MailtrapTransport {
+        MailtrapTransport::new(
+            base_url,
+            email(),
+            Secret::new(Faker.fake()),
+            inbox_id,
+            std::time::Duration::from_millis(200),
+        )
+    }
+
+    #[tokio::test]
+    async fn send_email_fires_a_request_to_base_url() {
+        let mock_server = MockServer::start().await;
+        let inbox_id: String = Faker.fake();
+
+        let email_client = email_client(mock_server.uri(), Secret::new(inbox_id.clone()));
+
+        Mock::given(header_exists("Api-Token"))
+            .and(header("Content-Type", "application/json"))
+            .and(path(format!("/api/send/{}", &inbox_id)))
+            .and(method("POST"))
+            .and(SendEmailBodyMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content(), &[])
+            .await;
+
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_returns_the_message_id_assigned_by_the_provider() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri(), Secret::new(Faker.fake()));
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "success": true,
+                "message_ids": ["0c7fd939-02cf-11ed-88c2-0a58a9feac02"],
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let message_id = email_client
+            .send_email(&email(), &subject(), &content(), &content(), &[])
+            .await
+            .unwrap();
+
+        assert_eq!(message_id.as_deref(), Some("0c7fd939-02cf-11ed-88c2-0a58a9feac02"));
+    }
+
+    #[tokio::test]
+    async fn send_email_passes_custom_headers_to_the_provider() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri(), Secret::new(Faker.fake()));
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content(), &[("X-Custom", "value")])
+            .await;
+
+        assert_ok!(outcome);
+
+        let request = &mock_server.received_requests().await.unwrap()[0];
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+
+        assert_eq!(body["headers"]["X-Custom"], "value");
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri(), Secret::new(Faker.fake()));
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content(), &[])
+            .await;
+
+        assert_err!(&outcome);
+        let error = outcome.unwrap_err();
+        assert!(matches!(error, SendEmailError::ServerError { status: 500, .. }));
+        assert!(error.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn send_email_is_rejected_permanently_if_the_server_returns_400() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri(), Secret::new(Faker.fake()));
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(400).set_body_string("Invalid recipient"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let error = email_client
+            .send_email(&email(), &subject(), &content(), &content(), &[])
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            &error,
+            SendEmailError::Rejected { status: 400, message } if message == "Invalid recipient"
+        ));
+        assert!(!error.is_retryable());
+    }
+
+    #[tokio::test]
+    async fn send_email_reports_the_retry_hint_if_the_server_returns_429() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri(), Secret::new(Faker.fake()));
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "120"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let error = email_client
+            .send_email(&email(), &subject(), &content(), &content(), &[])
+            .await
+            .unwrap_err();
+
+        assert!(error.is_retryable());
+        assert_eq!(error.retry_after(), Some(std::time::Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn retry_after_accepts_an_http_date() {
+        let retry_at = chrono::Utc::now() + chrono::Duration::seconds(60);
+        let retry_after = parse_retry_after(&retry_at.to_rfc2822()).unwrap();
+
+        assert!(retry_after <= std::time::Duration::from_secs(60));
+        assert!(retry_after >= std::time::Duration::from_secs(55));
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_the_server_takes_too_long() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri(), Secret::new(Faker.fake()));
+
+        let response = ResponseTemplate::new(200)
+            .set_delay(std::time::Duration::from_secs(180));
+        Mock::given(any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content(), &[])
+            .await;
+
+        assert!(matches!(outcome, Err(SendEmailError::Timeout(_))));
+    }
+}
@@ -0,0 +1,167 @@
+mod file;
+mod mailtrap;
+mod smtp;
+
+pub use file::{FileTransport, StdoutTransport};
+pub use mailtrap::MailtrapTransport;
+pub use smtp::{SmtpSecurity, SmtpTransport};
+
+use crate::domain::SubscriberEmail;
+use crate::utils::error_chain_fmt;
+
+use anyhow::Context;
+use lettre::address::Envelope;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::Message;
+use std::time::Duration;
+
+#[async_trait::async_trait]
+pub trait EmailTransport: Send + Sync {
+    fn sender(&self) -> &SubscriberEmail;
+
+    /// Returns the id the provider assigned to the email, when it reports one.
+    async fn send_email(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<Option<String>, SendEmailError>;
+}
+
+#[derive(thiserror::Error)]
+pub enum SendEmailError {
+    #[error("The email provider did not answer in time.")]
+    Timeout(#[source] anyhow::Error),
+    #[error("The email provider is rate limiting us.")]
+    RateLimited { retry_after: Option<Duration> },
+    #[error("The email provider failed to process the email ({status}): {message}")]
+    ServerError { status: u16, message: String },
+    #[error("The email provider rejected the email ({status}): {message}")]
+    Rejected { status: u16, message: String },
+    #[error("The email could not be built.")]
+    InvalidEmail(#[source] anyhow::Error),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl SendEmailError {
+    /// Whether sending the same email again later has a chance to succeed.
+    pub fn is_retryable(&self) -> bool {
+        !matches!(self, Self::Rejected { .. } | Self::InvalidEmail(_))
+    }
+
+    /// How long the provider asked us to wait before trying again.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RateLimited { retry_after } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Debug for SendEmailError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+/// Builds a multipart/alternative message for the transports that deal with raw
+/// RFC 5322 messages (SMTP, .eml files).
+///
+/// lettre only knows how to set headers with a name known at compile time, so
+/// custom headers are prepended to the formatted message instead.
+fn raw_message(
+    sender: &SubscriberEmail,
+    recipient: &SubscriberEmail,
+    subject: &str,
+    html_content: &str,
+    text_content: &str,
+    headers: &[(&str, &str)],
+) -> Result<(Envelope, Vec<u8>), SendEmailError> {
+    build_raw_message(sender, recipient, subject, html_content, text_content, headers)
+        .map_err(SendEmailError::InvalidEmail)
+}
+
+fn build_raw_message(
+    sender: &SubscriberEmail,
+    recipient: &SubscriberEmail,
+    subject: &str,
+    html_content: &str,
+    text_content: &str,
+    headers: &[(&str, &str)],
+) -> Result<(Envelope, Vec<u8>), anyhow::Error> {
+    let message = Message::builder()
+        .from(sender.as_ref().parse::<Mailbox>().context("Invalid sender address")?)
+        .to(recipient.as_ref().parse::<Mailbox>().context("Invalid recipient address")?)
+        .subject(subject)
+        .multipart(MultiPart::alternative_plain_html(
+            text_content.to_owned(),
+            html_content.to_owned(),
+        ))
+        .context("Failed to build email message")?;
+
+    let mut raw = Vec::new();
+
+    for (name, value) in headers {
+        let is_valid_name = !name.is_empty()
+            && name.chars().all(|c| c.is_ascii_graphic() && c != ':');
+        let is_valid_value = value.chars().all(|c| c.is_ascii() && c != '\r' && c != '\n');
+
+        if !is_valid_name || !is_valid_value {
+            anyhow::bail!("Invalid email header {}", name);
+        }
+
+        raw.extend_from_slice(format!("{}: {}\r\n", name, value).as_bytes());
+    }
+
+    raw.extend_from_slice(&message.formatted());
+
+    Ok((message.envelope().clone(), raw))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::raw_message;
+    use crate::domain::SubscriberEmail;
+    use claim::assert_err;
+
+    fn email(address: &str) -> SubscriberEmail {
+        SubscriberEmail::parse(address.into()).unwrap()
+    }
+
+    #[test]
+    fn raw_message_contains_custom_headers_and_both_bodies() {
+        let (envelope, raw) = raw_message(
+            &email("sender@example.com"),
+            &email("recipient@example.com"),
+            "Subject",
+            "<p>HTML body</p>",
+            "Text body",
+            &[("List-Unsubscribe-Post", "List-Unsubscribe=One-Click")],
+        )
+        .unwrap();
+
+        let raw = String::from_utf8(raw).unwrap();
+
+        assert!(raw.starts_with("List-Unsubscribe-Post: List-Unsubscribe=One-Click\r\n"));
+        assert!(raw.contains("<p>HTML body</p>"));
+        assert!(raw.contains("Text body"));
+        assert_eq!(envelope.to()[0].to_string(), "recipient@example.com");
+    }
+
+    #[test]
+    fn header_injection_is_rejected() {
+        let outcome = raw_message(
+            &email("sender@example.com"),
+            &email("recipient@example.com"),
+            "Subject",
+            "<p>HTML body</p>",
+            "Text body",
+            &[("X-Custom", "value\r\nBcc: someone@example.com")],
+        );
+
+        assert_err!(outcome);
+    }
+}
@@ -0,0 +1,102 @@
+use crate::domain::SubscriberEmail;
+use crate::email_client::{raw_message, EmailTransport, SendEmailError};
+
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use secrecy::{ExposeSecret, Secret};
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Implicit TLS, usually on port 465.
+    Tls,
+    /// Plain connection upgraded with STARTTLS, usually on port 587.
+    #[default]
+    StartTls,
+    /// No encryption at all. Only meant for local catch-all servers like MailHog.
+    None,
+}
+
+/// Sends emails to an SMTP relay.
+pub struct SmtpTransport {
+    sender: SubscriberEmail,
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpTransport {
+    pub fn new(
+        sender: SubscriberEmail,
+        host: &str,
+        port: u16,
+        security: SmtpSecurity,
+        credentials: Option<(String, Secret<String>)>,
+        timeout: std::time::Duration,
+    ) -> Result<Self, anyhow::Error> {
+        let builder = match security {
+            SmtpSecurity::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(host)?,
+            SmtpSecurity::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?,
+            SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+        };
+
+        let builder = builder.port(port).timeout(Some(timeout));
+        let builder = match credentials {
+            Some((username, password)) => {
+                builder.credentials(Credentials::new(username, password.expose_secret().clone()))
+            },
+            None => builder,
+        };
+
+        Ok(Self { sender, mailer: builder.build() })
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailTransport for SmtpTransport {
+    fn sender(&self) -> &SubscriberEmail {
+        &self.sender
+    }
+
+    async fn send_email(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+        headers: &[(&str, &str)],
+    ) -> Result<Option<String>, SendEmailError> {
+        let (envelope, message) = raw_message(
+            &self.sender,
+            recipient,
+            subject,
+            html_content,
+            text_content,
+            headers,
+        )?;
+
+        let response = self.mailer
+            .send_raw(&envelope, &message)
+            .await
+            .map_err(classify_smtp_error)?;
+
+        // Relays usually mention their queue id in the reply, e.g. "2.0.0 Ok: queued as 4F2Xq".
+        Ok(response.first_line().map(str::to_owned))
+    }
+}
+
+/// SMTP replies in the 4xx range are transient, the ones in the 5xx range are permanent.
+fn classify_smtp_error(e: lettre::transport::smtp::Error) -> SendEmailError {
+    let status = e
+        .status()
+        .and_then(|code| code.to_string().parse::<u16>().ok())
+        .unwrap_or_default();
+
+    if e.is_timeout() {
+        SendEmailError::Timeout(e.into())
+    } else if e.is_permanent() {
+        SendEmailError::Rejected { status, message: e.to_string() }
+    } else if e.is_transient() {
+        SendEmailError::ServerError { status, message: e.to_string() }
+    } else {
+        SendEmailError::UnexpectedError(e.into())
+    }
+}
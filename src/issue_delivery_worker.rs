@@ -1,14 +1,27 @@
 use crate::{
-    configuration::Settings,
-    domain::SubscriberEmail,
-    email_client::EmailClient,
-    startup::get_connection_pool,
+    configuration::{DeliveryWorkerSettings, Settings},
+    domain::{BackoffPolicy, ClickTrackingToken, NewsletterTemplate, OpenTrackingToken, SubscriberEmail, TemplateValues, TrackedClick, UnsubscribeToken},
+    email_client::EmailTransport,
+    tracked_links::rewrite_links,
 };
 
-use sqlx::{postgres::PgArguments, PgPool, Postgres, Transaction};
+use chrono::{DateTime, Utc};
+use secrecy::Secret;
+use sqlx::postgres::{PgArguments, PgListener, PgPoolOptions};
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::watch;
 use uuid::Uuid;
 
+/// Postgres channel notified whenever new tasks are committed to `issue_delivery_queue`
+/// or an issue is scheduled.
+const NEW_TASKS_CHANNEL: &str = "issue_delivery_queue";
+/// Idle workers still look at the queue from time to time to pick up the tasks whose
+/// `execute_after` has passed, nobody sends a notification for those.
+/// The scheduler looks at the scheduled issues at least as often.
+const FALLBACK_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
 pub enum ExecutionOutcome {
     TaskCompleted,
     EmptyQueue,
@@ -26,13 +39,12 @@ impl ErrorType {
         error: anyhow::Error,
         newsletter_issue_id: Uuid,
         subscriber_email: String,
-        n_retries: i16,
-        execute_after_in_secs: Option<i32>,
+        retry_conf: RetryConf,
     ) -> Self {
         ErrorType::SoftError(
             JobErrorWithRetryConf {
                 job_error: JobError::new(error, newsletter_issue_id, subscriber_email),
-                retry_conf: RetryConf { n_retries, execute_after_in_secs }
+                retry_conf,
             }
         )
     }
@@ -84,79 +96,422 @@ struct IssueJobInfo {
 #[derive(Debug)]
 struct RetryConf {
     n_retries: i16,
-    execute_after_in_secs: Option<i32>,
+    n_attempts: i16,
+    backoff_policy: BackoffPolicy,
+    /// Delay requested by the email provider, takes precedence over the backoff policy
+    /// up to its maximum delay.
+    retry_after: Option<Duration>,
+}
+
+/// Outcome recorded in `newsletter_deliveries` once a task leaves the queue.
+/// Failed deliveries are recorded by `move_task_to_failures`.
+#[derive(Debug, Clone, Copy)]
+enum DeliveryStatus {
+    Sent,
+    /// The subscriber is not confirmed anymore, e.g. they unsubscribed after the
+    /// issue was published.
+    Skipped,
+}
+
+impl DeliveryStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Sent => "sent",
+            DeliveryStatus::Skipped => "skipped",
+        }
+    }
+}
+
+struct Task {
+    newsletter_issue_id: Uuid,
+    subscriber_email: String,
+    /// `None` if the subscriber was deleted after the issue was published.
+    subscriber: Option<Subscriber>,
+    retry_conf: RetryConf,
 }
 
 type PgTransaction = Transaction<'static, Postgres>;
 
+/// Runs `n_workers` delivery workers until `shutdown` flips to `true`.
+/// Sends that are in flight at that point are completed before returning.
 pub async fn run_worker_until_stopped(
-    configuration: Settings
+    configuration: Settings,
+    shutdown: watch::Receiver<bool>,
 ) -> Result<(), anyhow::Error> {
-    let connection_pool = get_connection_pool(&configuration.database);
+    let DeliveryWorkerSettings { n_workers, max_in_flight, max_connections } = configuration.delivery_worker;
+    anyhow::ensure!(n_workers > 0, "At least one delivery worker is required.");
+    anyhow::ensure!(max_in_flight > 0, "Delivery workers must be allowed at least one email in flight.");
+    // Every in-flight send holds a connection for its transaction and briefly needs
+    // another one to look up the issue. The listener holds one more.
+    let min_connections = n_workers.saturating_mul(max_in_flight).saturating_add(2);
+    anyhow::ensure!(
+        max_connections as usize >= min_connections,
+        "{} delivery workers with {} emails in flight each need at least {} database connections, {} are allowed.",
+        n_workers,
+        max_in_flight,
+        min_connections,
+        max_connections,
+    );
+
+    let connection_pool = PgPoolOptions::new()
+        .acquire_timeout(Duration::from_secs(5))
+        .max_connections(max_connections)
+        .connect_lazy_with(configuration.database.with_db());
     let email_client = configuration.email_client.client();
+    let base_url: Arc<str> = configuration.application.base_url.into();
+
+    let (wake_up_sender, wake_up) = watch::channel(());
+    let listener = tokio::spawn(listen_for_new_tasks(connection_pool.clone(), wake_up_sender));
+    let scheduler = tokio::spawn(scheduler_loop(connection_pool.clone(), wake_up.clone(), shutdown.clone()));
+
+    let workers = (0..n_workers).map(|_| {
+        tokio::spawn(worker_loop(
+            connection_pool.clone(),
+            email_client.clone(),
+            base_url.clone(),
+            configuration.application.hmac_secret.clone(),
+            max_in_flight,
+            wake_up.clone(),
+            shutdown.clone(),
+        ))
+    });
+
+    // Workers log their errors and carry on, only a panic ends one early.
+    let outcome = futures::future::try_join_all(workers).await;
+    let scheduler_outcome = scheduler.await;
+    listener.abort();
+    outcome?;
+    scheduler_outcome?;
+
+    Ok(())
+}
+
+/// Tells the workers to look at the queue on every notification on `NEW_TASKS_CHANNEL`.
+/// Never returns: if the connection drops, the workers are woken up in case a
+/// notification was missed and a new connection is established.
+async fn listen_for_new_tasks(pool: PgPool, wake_up: watch::Sender<()>) {
+    loop {
+        if let Err(e) = forward_notifications(&pool, &wake_up).await {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Lost the connection listening for new delivery tasks",
+            );
+        }
+
+        let _ = wake_up.send(());
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
+}
+
+async fn forward_notifications(pool: &PgPool, wake_up: &watch::Sender<()>) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(NEW_TASKS_CHANNEL).await?;
+
+    // Tasks might have been enqueued while we were not listening.
+    let _ = wake_up.send(());
+
+    loop {
+        // `None` means that the connection was lost and re-established, notifications
+        // sent in the meantime are gone.
+        let _ = listener.try_recv().await?;
+        let _ = wake_up.send(());
+    }
+}
+
+/// Wakes up idle delivery workers and the scheduler once the current transaction commits.
+pub async fn notify_new_tasks<'c>(executor: impl PgExecutor<'c>) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT pg_notify($1, '')")
+        .bind(NEW_TASKS_CHANNEL)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// Enqueues the deliveries of scheduled issues as they come due. Between two looks it
+/// sleeps until the next send time, or until an issue is scheduled or resumed.
+async fn scheduler_loop(
+    pool: PgPool,
+    mut wake_up: watch::Receiver<()>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    while !*shutdown.borrow() {
+        wake_up.borrow_and_update();
+
+        let next_send_at = match enqueue_due_issues(&pool).await {
+            Ok(next_send_at) => next_send_at,
+            Err(e) => {
+                tracing::warn!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to enqueue the deliveries of due issues",
+                );
+                None
+            },
+        };
+        // A send time that passed already belongs to an issue another process is enqueueing.
+        let delay = next_send_at
+            .map(|send_at| (send_at - Utc::now()).to_std().unwrap_or_default().min(FALLBACK_POLL_INTERVAL))
+            .unwrap_or(FALLBACK_POLL_INTERVAL);
+
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {},
+            _ = wake_up.changed() => {},
+            _ = shutdown.changed() => {},
+        }
+    }
+}
+
+/// Scheduled issues pick their recipients once they are due: subscribers who confirmed
+/// after the issue was scheduled get it too. Returns the next send time still ahead.
+pub async fn enqueue_due_issues(pool: &PgPool) -> Result<Option<DateTime<Utc>>, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+
+    // Another worker process might be enqueueing the same issues.
+    let due_issue_ids = sqlx::query_scalar!(
+        r#"
+        SELECT id FROM newsletter_issues
+        WHERE status = 'scheduled' AND published_at <= now()
+        FOR UPDATE SKIP LOCKED
+        "#,
+    )
+    .fetch_all(&mut transaction)
+    .await?;
+
+    for newsletter_issue_id in &due_issue_ids {
+        enqueue_delivery_tasks(&mut transaction, *newsletter_issue_id).await?;
+    }
+    if !due_issue_ids.is_empty() {
+        notify_new_tasks(&mut transaction).await?;
+    }
+    transaction.commit().await?;
 
-    worker_loop(connection_pool, email_client).await
+    let next_send_at = sqlx::query_scalar!(
+        r#"SELECT MIN(published_at) FROM newsletter_issues WHERE status = 'scheduled'"#,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(next_send_at)
 }
 
+/// Picks the recipients of an issue, every subscriber confirmed right now, with the
+/// retry settings chosen on publication. The issue is `sending` from then on, or
+/// `sent` already if there is nobody to send it to.
+#[tracing::instrument(skip(transaction))]
+pub async fn enqueue_delivery_tasks(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    // Issues scheduled before recipients were picked at send time have tasks already.
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (
+            newsletter_issue_id,
+            subscriber_email,
+            n_retries,
+            retry_base_delay_in_secs,
+            retry_max_delay_in_secs
+        )
+        SELECT newsletter_issues.id,
+            subscriptions.email,
+            newsletter_issues.n_retries,
+            newsletter_issues.retry_base_delay_in_secs,
+            newsletter_issues.retry_max_delay_in_secs
+        FROM newsletter_issues, subscriptions
+        WHERE newsletter_issues.id = $1 AND subscriptions.status = 'confirmed'
+        ON CONFLICT DO NOTHING
+        "#,
+        newsletter_issue_id,
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = CASE
+                WHEN EXISTS (
+                    SELECT 1 FROM issue_delivery_queue WHERE newsletter_issue_id = $1
+                ) THEN 'sending'
+                ELSE 'sent'
+            END
+        WHERE id = $1
+        "#,
+        newsletter_issue_id,
+    )
+    .execute(transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// A worker keeps up to `max_in_flight` deliveries going at the same time.
+/// Workers don't coordinate: `FOR UPDATE SKIP LOCKED` in `dequeue_task` makes sure
+/// that a task is picked up by a single one of them.
 async fn worker_loop(
     pool: PgPool,
-    email_client: EmailClient,
-) -> Result<(), anyhow::Error> {
-    loop {
-        match try_execute_task(&pool, &email_client).await {
-            Ok(ExecutionOutcome::EmptyQueue) => tokio::time::sleep(Duration::from_secs(10)).await,
+    email_client: Arc<dyn EmailTransport>,
+    base_url: Arc<str>,
+    hmac_secret: Secret<String>,
+    max_in_flight: usize,
+    wake_up: watch::Receiver<()>,
+    shutdown: watch::Receiver<bool>,
+) {
+    let deliveries = (0..max_in_flight).map(|_| {
+        delivery_loop(&pool, email_client.as_ref(), &base_url, &hmac_secret, wake_up.clone(), shutdown.clone())
+    });
+
+    futures::future::join_all(deliveries).await;
+}
+
+async fn delivery_loop(
+    pool: &PgPool,
+    email_client: &dyn EmailTransport,
+    base_url: &str,
+    hmac_secret: &Secret<String>,
+    mut wake_up: watch::Receiver<()>,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    while !*shutdown.borrow() {
+        // The queue is about to be looked at, so pending notifications are stale.
+        wake_up.borrow_and_update();
+
+        match try_execute_task(pool, email_client, base_url, hmac_secret).await {
+            Ok(ExecutionOutcome::EmptyQueue) => {
+                tokio::select! {
+                    _ = tokio::time::sleep(FALLBACK_POLL_INTERVAL) => {},
+                    _ = wake_up.changed() => {},
+                    _ = shutdown.changed() => {},
+                }
+            },
             Ok(ExecutionOutcome::TaskCompleted) => {},
-            Err(error_type) => handle_worker_error(&pool, error_type).await?,
+            Err(error_type) => handle_worker_error(error_type).await,
         }
     }
 }
 
-#[tracing::instrument(skip(pool))]
-pub async fn handle_worker_error(pool: &PgPool, error_type: ErrorType) -> Result<(), anyhow::Error> {
+/// Failed tasks have been rescheduled or dead-lettered by `try_execute_task` already,
+/// this only keeps a worker from hammering the provider or the database.
+pub async fn handle_worker_error(error_type: ErrorType) {
     match error_type {
-        ErrorType::SoftError(job_error_with_retry) => {
-            let IssueJobInfo { newsletter_issue_id, subscriber_email } = job_error_with_retry.job_error.issue_job_info;
-            let n_retries = job_error_with_retry.retry_conf.n_retries;
-
-            if n_retries <= 1 {
-                delete_task_query(newsletter_issue_id, &subscriber_email)
-                    .execute(pool)
-                    .await?;
-            } else {
-                let execute_after_in_secs = job_error_with_retry.retry_conf.execute_after_in_secs;
-                let execute_after_in_secs: i64 = execute_after_in_secs.unwrap_or(30) as i64;
-
-                sqlx::query!(
-                    r#"
-                    UPDATE issue_delivery_queue
-                    SET n_retries = $1,
-                        execute_after = $2
-                    WHERE newsletter_issue_id = $3 AND subscriber_email = $4;
-                    "#,
-                    n_retries - 1,
-                    chrono::Utc::now() + chrono::Duration::seconds(execute_after_in_secs),
-                    newsletter_issue_id,
-                    subscriber_email,
-                )
-                .execute(pool)
-                .await?;
+        ErrorType::SoftError(_) | ErrorType::UnexpectedError(_) => tokio::time::sleep(Duration::from_secs(1)).await,
+        ErrorType::HardError(_) => {},
+    }
+}
 
-                tokio::time::sleep(Duration::from_secs(1)).await;
-            }
+/// Reschedules a task that failed with a retryable error, dead-letters it once it is
+/// out of retries or if the error is permanent.
+async fn record_failure(
+    transaction: &mut PgTransaction,
+    error_type: &ErrorType,
+) -> Result<(), anyhow::Error> {
+    match error_type {
+        ErrorType::SoftError(JobErrorWithRetryConf { job_error, retry_conf }) if retry_conf.n_retries > 1 => {
+            let IssueJobInfo { newsletter_issue_id, subscriber_email } = &job_error.issue_job_info;
+            let RetryConf { n_retries, n_attempts, backoff_policy, retry_after } = retry_conf;
+            let n_attempts = n_attempts + 1;
+            // The provider's delay is capped like ours: a far-off date would park the task for good.
+            let max_delay = Duration::from_secs(u64::from(backoff_policy.max_delay_in_secs()));
+            let delay = retry_after
+                .map(|retry_after| retry_after.min(max_delay))
+                .unwrap_or_else(|| backoff_policy.delay(n_attempts as u16));
+
+            sqlx::query!(
+                r#"
+                UPDATE issue_delivery_queue
+                SET n_retries = $1,
+                    n_attempts = $2,
+                    execute_after = $3
+                WHERE newsletter_issue_id = $4 AND subscriber_email = $5;
+                "#,
+                n_retries - 1,
+                n_attempts,
+                chrono::Utc::now() + chrono::Duration::from_std(delay)?,
+                newsletter_issue_id,
+                subscriber_email,
+            )
+            .execute(transaction)
+            .await?;
         },
-        ErrorType::HardError(job_error) => {
-            let IssueJobInfo { newsletter_issue_id, subscriber_email } = job_error.issue_job_info;
+        ErrorType::SoftError(JobErrorWithRetryConf { job_error, .. }) | ErrorType::HardError(job_error) => {
+            let IssueJobInfo { newsletter_issue_id, subscriber_email } = &job_error.issue_job_info;
 
-            delete_task_query(newsletter_issue_id, &subscriber_email)
-                .execute(pool)
-                .await?;
+            move_task_to_failures(transaction, *newsletter_issue_id, subscriber_email, &job_error.error).await?;
         },
-        ErrorType::UnexpectedError(_) => tokio::time::sleep(Duration::from_secs(1)).await,
+        ErrorType::UnexpectedError(_) => {},
     };
 
     Ok(())
 }
 
+/// Dead-letters a task that won't be retried anymore, so that it can be inspected
+/// and requeued from the admin area.
+#[tracing::instrument(skip(transaction, error))]
+async fn move_task_to_failures(
+    transaction: &mut PgTransaction,
+    newsletter_issue_id: Uuid,
+    subscriber_email: &str,
+    error: &anyhow::Error,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        WITH failed_task AS (
+            DELETE FROM issue_delivery_queue
+            WHERE newsletter_issue_id = $1 AND subscriber_email = $2
+            RETURNING *
+        ), failed_delivery AS (
+            INSERT INTO newsletter_deliveries (
+                newsletter_issue_id,
+                subscriber_email,
+                status,
+                n_attempts,
+                delivered_at
+            )
+            SELECT newsletter_issue_id, subscriber_email, 'failed', n_attempts + 1, now()
+            FROM failed_task
+            ON CONFLICT (newsletter_issue_id, subscriber_email) DO UPDATE
+            SET status = EXCLUDED.status,
+                provider_message_id = NULL,
+                n_attempts = EXCLUDED.n_attempts,
+                delivered_at = EXCLUDED.delivered_at
+        )
+        INSERT INTO issue_delivery_failures (
+            newsletter_issue_id,
+            subscriber_email,
+            last_error,
+            n_attempts,
+            retry_base_delay_in_secs,
+            retry_max_delay_in_secs,
+            enqueued_at,
+            failed_at
+        )
+        SELECT newsletter_issue_id,
+            subscriber_email,
+            $3,
+            n_attempts + 1,
+            retry_base_delay_in_secs,
+            retry_max_delay_in_secs,
+            enqueued_at,
+            now()
+        FROM failed_task
+        ON CONFLICT (newsletter_issue_id, subscriber_email) DO UPDATE
+        SET last_error = EXCLUDED.last_error,
+            n_attempts = EXCLUDED.n_attempts,
+            enqueued_at = EXCLUDED.enqueued_at,
+            failed_at = EXCLUDED.failed_at
+        "#,
+        newsletter_issue_id,
+        subscriber_email,
+        format!("{:#}", error),
+    )
+    .execute(transaction)
+    .await?;
+
+    Ok(())
+}
+
 #[tracing::instrument(
     skip_all,
     fields(
@@ -167,49 +522,160 @@ pub async fn handle_worker_error(pool: &PgPool, error_type: ErrorType) -> Result
 )]
 pub async fn try_execute_task(
     pool: &PgPool,
-    email_client: &EmailClient,
+    email_client: &dyn EmailTransport,
+    base_url: &str,
+    hmac_secret: &Secret<String>,
 ) -> Result<ExecutionOutcome, ErrorType> {
-    let task = dequeue_task(pool).await.map_err(|e| ErrorType::UnexpectedError(e))?;
-    let (transaction, newsletter_issue_id, email, n_retries, execute_after_in_secs) = match task {
+    let task = dequeue_task(pool).await.map_err(ErrorType::UnexpectedError)?;
+    let (mut transaction, task) = match task {
         Some(res) => res,
         None => return Ok(ExecutionOutcome::EmptyQueue),
     };
+    let newsletter_issue_id = task.newsletter_issue_id;
+
+    // The task stays locked until its outcome is recorded: other workers would pick
+    // up a failed task again before it is rescheduled otherwise.
+    let outcome = execute_task(pool, &mut transaction, task, email_client, base_url, hmac_secret).await;
+    match &outcome {
+        Ok(()) => {},
+        // Rolled back, the task is retried as it is.
+        Err(ErrorType::UnexpectedError(_)) => return outcome.map(|_| ExecutionOutcome::TaskCompleted),
+        Err(error_type) => record_failure(&mut transaction, error_type)
+            .await
+            .map_err(ErrorType::UnexpectedError)?,
+    }
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| ErrorType::UnexpectedError(e.into()))?;
+    mark_issue_as_sent_if_delivered(pool, newsletter_issue_id)
+        .await
+        .map_err(ErrorType::UnexpectedError)?;
+
+    outcome.map(|_| ExecutionOutcome::TaskCompleted)
+}
 
-    let NewsletterIssue { title, text_content, html_content } = get_newsletter_issue(&pool, newsletter_issue_id)
-        .await.map_err(|e| ErrorType::UnexpectedError(e))?; 
+/// Sends the email of a dequeued task. Its outcome is written to `transaction`,
+/// which is committed by the caller.
+async fn execute_task(
+    pool: &PgPool,
+    transaction: &mut PgTransaction,
+    task: Task,
+    email_client: &dyn EmailTransport,
+    base_url: &str,
+    hmac_secret: &Secret<String>,
+) -> Result<(), ErrorType> {
+    let Task { newsletter_issue_id, subscriber_email: email, subscriber, retry_conf } = task;
+
+    let NewsletterIssue { title, text_content, mut html_content, track_opens } = get_newsletter_issue(pool, newsletter_issue_id)
+        .await.map_err(ErrorType::UnexpectedError)?; 
     let email = SubscriberEmail::parse(email).map_err(|e| { 
         ErrorType::create_hard_error(anyhow::anyhow!(e.0), newsletter_issue_id, e.1)
     })?;
 
-    email_client
-        .send_email(&email, &title, &html_content, &text_content)
-        .await
-        .map_err(|_| { 
-            ErrorType::create_soft_error(
-                anyhow::anyhow!(format!("Failed to send newsletter to a confitmed subscriber {:?}. Skipping.", &email)),
+    let subscriber = subscriber
+        .ok_or_else(|| {
+            ErrorType::create_hard_error(
+                anyhow::anyhow!("The subscriber does not exist anymore."),
                 newsletter_issue_id,
                 email.as_ref().into(),
-                n_retries,
-                execute_after_in_secs,
             )
         })?;
 
-    delete_task(transaction, newsletter_issue_id, email.as_ref()).await.map_err(|e| ErrorType::UnexpectedError(e))?;
+    if subscriber.status != "confirmed" {
+        return complete_task(transaction, newsletter_issue_id, email.as_ref(), DeliveryStatus::Skipped, None, retry_conf.n_attempts)
+            .await
+            .map_err(ErrorType::UnexpectedError);
+    }
+
+    let unsubscribe_token = UnsubscribeToken::generate(subscriber.id, hmac_secret);
+    let unsubscribe_url = unsubscribe_url(base_url, &unsubscribe_token);
+    let headers = list_unsubscribe_headers(&unsubscribe_url);
+    let headers: Vec<(&str, &str)> = headers.iter().map(|(name, value)| (*name, value.as_str())).collect();
+
+    // Links are rewritten before the placeholders are filled in: their positions are
+    // those of the stored content, which is what publishing registered.
+    if !subscriber.do_not_track {
+        let links = get_newsletter_links(pool, newsletter_issue_id)
+            .await
+            .map_err(ErrorType::UnexpectedError)?;
+        html_content = rewrite_links(&html_content, &links, |position| {
+            let click = TrackedClick { newsletter_issue_id, subscriber_id: subscriber.id, position: position as u32 };
+            click_tracking_url(base_url, &ClickTrackingToken::generate(&click, hmac_secret))
+        });
+    }
 
-    Ok(ExecutionOutcome::TaskCompleted)
+    let values = TemplateValues { name: &subscriber.name, email: email.as_ref(), unsubscribe_url: &unsubscribe_url };
+    let (text_content, mut html_content) = render_contents(text_content, html_content, &values)
+        .map_err(|e| ErrorType::create_hard_error(e, newsletter_issue_id, email.as_ref().into()))?;
+    if track_opens && !subscriber.do_not_track {
+        let token = OpenTrackingToken::generate(newsletter_issue_id, subscriber.id, hmac_secret);
+        html_content = with_open_tracking_pixel(html_content, &open_tracking_url(base_url, &token));
+    }
+
+    let n_attempts = retry_conf.n_attempts + 1;
+    let provider_message_id = email_client
+        .send_email(&email, &title, &html_content, &text_content, &headers)
+        .await
+        .map_err(|e| {
+            let is_retryable = e.is_retryable();
+            let retry_after = e.retry_after();
+            let error = anyhow::Error::from(e)
+                .context(format!("Failed to send newsletter to a confirmed subscriber {}.", &email));
+
+            if is_retryable {
+                ErrorType::create_soft_error(
+                    error,
+                    newsletter_issue_id,
+                    email.as_ref().into(),
+                    RetryConf { retry_after, ..retry_conf },
+                )
+            } else {
+                ErrorType::create_hard_error(error, newsletter_issue_id, email.as_ref().into())
+            }
+        })?;
+
+    complete_task(
+        transaction,
+        newsletter_issue_id,
+        email.as_ref(),
+        DeliveryStatus::Sent,
+        provider_message_id.as_deref(),
+        n_attempts,
+    )
+    .await
+    .map_err(ErrorType::UnexpectedError)
 }
 
+/// Tasks of paused issues stay in the queue until the issue is resumed, those of
+/// scheduled issues until `enqueue_due_issues` gets to them.
 async fn dequeue_task(
     pool: &PgPool,
-) -> Result<Option<(PgTransaction, Uuid, String, i16, Option<i32>)>, anyhow::Error> {
+) -> Result<Option<(PgTransaction, Task)>, anyhow::Error> {
     let mut transaction = pool.begin().await?;
 
+    // Only the task is locked, not the subscriber: `FOR UPDATE OF`.
     let record = sqlx::query!(
         r#"
-        SELECT newsletter_issue_id, subscriber_email, n_retries, execute_after_in_secs
+        SELECT issue_delivery_queue.newsletter_issue_id,
+            issue_delivery_queue.subscriber_email,
+            issue_delivery_queue.n_retries,
+            issue_delivery_queue.n_attempts,
+            issue_delivery_queue.retry_base_delay_in_secs,
+            issue_delivery_queue.retry_max_delay_in_secs,
+            subscriptions.id as "subscriber_id?",
+            subscriptions.name as "subscriber_name?",
+            subscriptions.status as "subscriber_status?",
+            subscriptions.do_not_track as "subscriber_do_not_track?"
         FROM issue_delivery_queue
-        WHERE execute_after < now() OR execute_after IS NULL
-        FOR UPDATE
+            LEFT JOIN subscriptions
+                ON subscriptions.email = issue_delivery_queue.subscriber_email
+        WHERE (issue_delivery_queue.execute_after < now() OR issue_delivery_queue.execute_after IS NULL)
+            AND issue_delivery_queue.newsletter_issue_id NOT IN (
+                SELECT id FROM newsletter_issues WHERE status IN ('paused', 'scheduled')
+            )
+        FOR UPDATE OF issue_delivery_queue
         SKIP LOCKED
         LIMIT 1
         "#,
@@ -218,12 +684,33 @@ async fn dequeue_task(
     .await?;
 
     match record {
-        Some(rec) => { 
-            Ok(Some((transaction,
-                     rec.newsletter_issue_id,
-                     rec.subscriber_email,
-                     rec.n_retries,
-                     rec.execute_after_in_secs)))
+        Some(rec) => {
+            // Publishing validates the policy before `enqueue_delivery_tasks` copies it here,
+            // so fall back to the default one if the stored values are off.
+            let backoff_policy = BackoffPolicy::parse(
+                rec.retry_base_delay_in_secs.try_into().unwrap_or_default(),
+                rec.retry_max_delay_in_secs.try_into().unwrap_or_default(),
+            )
+            .unwrap_or_default();
+
+            let subscriber = match (rec.subscriber_id, rec.subscriber_name, rec.subscriber_status, rec.subscriber_do_not_track) {
+                (Some(id), Some(name), Some(status), Some(do_not_track)) => Some(Subscriber { id, name, status, do_not_track }),
+                _ => None,
+            };
+
+            let task = Task {
+                newsletter_issue_id: rec.newsletter_issue_id,
+                subscriber_email: rec.subscriber_email,
+                subscriber,
+                retry_conf: RetryConf {
+                    n_retries: rec.n_retries,
+                    n_attempts: rec.n_attempts,
+                    backoff_policy,
+                    retry_after: None,
+                },
+            };
+
+            Ok(Some((transaction, task)))
         },
         None => Ok(None),
     }
@@ -244,24 +731,140 @@ fn delete_task_query(
     )
 }
 
+/// Removes the task from the queue and records how it went.
 #[tracing::instrument(skip_all)]
-async fn delete_task(
-    mut transaction: PgTransaction,
+async fn complete_task(
+    transaction: &mut PgTransaction,
     issue_id: Uuid,
     email: &str,
+    status: DeliveryStatus,
+    provider_message_id: Option<&str>,
+    n_attempts: i16,
 ) -> Result<(), anyhow::Error> {
     delete_task_query(issue_id, email)
-        .execute(&mut transaction)
+        .execute(&mut *transaction)
         .await?;
 
-    transaction.commit().await?;
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_deliveries (
+            newsletter_issue_id,
+            subscriber_email,
+            status,
+            provider_message_id,
+            n_attempts,
+            delivered_at
+        )
+        VALUES ($1, $2, $3, $4, $5, now())
+        ON CONFLICT (newsletter_issue_id, subscriber_email) DO UPDATE
+        SET status = EXCLUDED.status,
+            provider_message_id = EXCLUDED.provider_message_id,
+            n_attempts = EXCLUDED.n_attempts,
+            delivered_at = EXCLUDED.delivered_at
+        "#,
+        issue_id,
+        email,
+        status.as_str(),
+        provider_message_id,
+        n_attempts,
+    )
+    .execute(transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// Flips a `sending` issue to `sent` once its last task has left the queue.
+/// It must run after the task's transaction has committed: when two workers complete
+/// the last two tasks at the same time, the one checking last sees both deletions.
+#[tracing::instrument(skip(pool))]
+async fn mark_issue_as_sent_if_delivered(pool: &PgPool, newsletter_issue_id: Uuid) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = 'sent'
+        WHERE id = $1
+            AND status = 'sending'
+            AND NOT EXISTS (
+                SELECT 1 FROM issue_delivery_queue WHERE newsletter_issue_id = $1
+            )
+        "#,
+        newsletter_issue_id,
+    )
+    .execute(pool)
+    .await?;
+
     Ok(())
 }
 
+/// Fills in the placeholders of both contents for a single subscriber.
+/// Contents are checked when the issue is saved, an error here means that
+/// the issue was saved before placeholders were a thing.
+fn render_contents(
+    text_content: String,
+    html_content: String,
+    values: &TemplateValues,
+) -> Result<(String, String), anyhow::Error> {
+    let text_content = NewsletterTemplate::parse(text_content).map_err(anyhow::Error::msg)?;
+    let html_content = NewsletterTemplate::parse(html_content).map_err(anyhow::Error::msg)?;
+
+    Ok((text_content.render_text(values), html_content.render_html(values)))
+}
+
+fn click_tracking_url(base_url: &str, token: &ClickTrackingToken) -> String {
+    format!("{}/t/c/{}", base_url, token.as_ref())
+}
+
+fn open_tracking_url(base_url: &str, token: &OpenTrackingToken) -> String {
+    format!("{}/t/o/{}.gif", base_url, token.as_ref())
+}
+
+/// The pixel goes last in the body, a full document keeps its `</body>`.
+fn with_open_tracking_pixel(mut html_content: String, pixel_url: &str) -> String {
+    let pixel = format!(
+        r#"<img src="{}" width="1" height="1" alt="" style="display: block; border: 0;">"#,
+        pixel_url,
+    );
+
+    match html_content.to_ascii_lowercase().rfind("</body>") {
+        Some(end_of_body) => html_content.insert_str(end_of_body, &pixel),
+        None => html_content.push_str(&pixel),
+    }
+
+    html_content
+}
+
+fn unsubscribe_url(base_url: &str, unsubscribe_token: &UnsubscribeToken) -> String {
+    format!(
+        "{}/subscriptions/unsubscribe?unsubscribe_token={}",
+        base_url,
+        unsubscribe_token.as_ref(),
+    )
+}
+
+/// RFC 8058 one-click unsubscribe headers. Mail providers POST
+/// `List-Unsubscribe=One-Click` to the https link on the subscriber's behalf.
+/// There is no `mailto:` alternative: nothing reads replies to the sender address.
+fn list_unsubscribe_headers(unsubscribe_link: &str) -> [(&'static str, String); 2] {
+    [
+        ("List-Unsubscribe", format!("<{}>", unsubscribe_link)),
+        ("List-Unsubscribe-Post", "List-Unsubscribe=One-Click".into()),
+    ]
+}
+
+struct Subscriber {
+    id: Uuid,
+    name: String,
+    status: String,
+    /// Opted out of open and click tracking.
+    do_not_track: bool,
+}
+
 struct NewsletterIssue {
     title: String,
     text_content: String,
     html_content: String,
+    track_opens: bool,
 }
 
 async fn get_newsletter_issue(pool: &PgPool, newsletter_issue_id: Uuid) -> Result<NewsletterIssue, anyhow::Error> {
@@ -270,7 +873,8 @@ async fn get_newsletter_issue(pool: &PgPool, newsletter_issue_id: Uuid) -> Resul
         r#"
         SELECT title,
             text_content,
-            html_content
+            html_content,
+            track_opens
         FROM newsletter_issues
         WHERE id = $1
         "#,
@@ -282,3 +886,15 @@ async fn get_newsletter_issue(pool: &PgPool, newsletter_issue_id: Uuid) -> Resul
     Ok(record)
 }
 
+
+/// The links registered when the issue was published, by position.
+async fn get_newsletter_links(pool: &PgPool, newsletter_issue_id: Uuid) -> Result<Vec<String>, anyhow::Error> {
+    let links = sqlx::query_scalar!(
+        r#"SELECT url FROM newsletter_links WHERE newsletter_issue_id = $1 ORDER BY position"#,
+        newsletter_issue_id,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(links)
+}
@@ -5,8 +5,11 @@ pub mod email_client;
 pub mod idempotency;
 pub mod idempotency_key_worker;
 pub mod issue_delivery_worker;
+pub mod markdown;
 pub mod routes;
 pub mod session_state;
 pub mod startup;
+pub mod subscription_token_worker;
 pub mod telemetry;
+pub mod tracked_links;
 pub mod utils;
@@ -2,9 +2,11 @@ use zero2prod::configuration::get_configuration;
 use zero2prod::issue_delivery_worker::run_worker_until_stopped;
 use zero2prod::idempotency_key_worker;
 use zero2prod::startup::Application;
+use zero2prod::subscription_token_worker;
 use zero2prod::telemetry::{get_subscriber, init_subscriber};
 
 use std::fmt::{Debug, Display};
+use tokio::sync::watch;
 use tokio::task::JoinError;
 
 #[tokio::main]
@@ -15,14 +17,24 @@ async fn main() -> anyhow::Result<()> {
     let configuration = get_configuration().expect("Failed to read configuration.");
     let application = Application::build(configuration.clone()).await?;
 
+    let (shutdown_sender, shutdown) = watch::channel(false);
+
     let application_task = tokio::spawn(application.run_until_stopped());
-    let issue_delivery_worker = tokio::spawn(run_worker_until_stopped(configuration.clone()));
-    let idempotency_key_worker = tokio::spawn(idempotency_key_worker::run_worker_until_stopped(configuration));
+    let mut issue_delivery_worker = tokio::spawn(run_worker_until_stopped(configuration.clone(), shutdown));
+    let idempotency_key_worker = tokio::spawn(idempotency_key_worker::run_worker_until_stopped(configuration.clone()));
+    let subscription_token_worker = tokio::spawn(subscription_token_worker::run_worker_until_stopped(configuration));
 
     tokio::select! {
-        o = application_task => report_exit("API", o),
-        o = issue_delivery_worker => report_exit("Background worker (Issue delivery)", o),
+        o = application_task => {
+            report_exit("API", o);
+            // The API stops on SIGTERM/SIGINT: let the delivery workers finish the
+            // emails they are sending before the runtime goes away.
+            let _ = shutdown_sender.send(true);
+            report_exit("Background worker (Issue delivery)", (&mut issue_delivery_worker).await);
+        },
+        o = &mut issue_delivery_worker => report_exit("Background worker (Issue delivery)", o),
         o = idempotency_key_worker => report_exit("Background worker (Expire idempotency key)", o),
+        o = subscription_token_worker => report_exit("Background worker (Expire subscription token)", o),
     }
     Ok(())
 }
@@ -0,0 +1,203 @@
+use crate::utils::escape_html;
+
+use pulldown_cmark::{html, CowStr, Event, Options, Parser, Tag};
+use std::collections::BTreeMap;
+
+/// Where the rendered Markdown goes in the layout.
+const CONTENT_MARKER: &str = "{{ content }}";
+/// Link schemes that can end up in an email. Relative links and placeholders
+/// such as `{{ unsubscribe_url }}` don't have one.
+const ALLOWED_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+/// The HTML page every Markdown issue is wrapped in.
+pub struct NewsletterLayout {
+    template: String,
+    /// Inline styles by tag name: many email clients drop `<style>` blocks.
+    styles: BTreeMap<String, String>,
+}
+
+/// Both versions of an issue, generated from its Markdown source.
+pub struct RenderedMarkdown {
+    pub html_content: String,
+    pub text_content: String,
+}
+
+impl NewsletterLayout {
+    pub fn new(template: String, styles: BTreeMap<String, String>) -> Result<Self, String> {
+        if !template.contains(CONTENT_MARKER) {
+            return Err(format!("The newsletter layout must contain {}.", CONTENT_MARKER));
+        }
+
+        Ok(Self { template, styles })
+    }
+
+    /// Raw HTML in the source is shown as text rather than interpreted, and links
+    /// with a scheme other than `ALLOWED_SCHEMES` point nowhere.
+    pub fn render(&self, markdown: &str) -> RenderedMarkdown {
+        let events: Vec<Event> = Parser::new_ext(markdown, Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH)
+            .map(|event| match event {
+                Event::Html(html) => Event::Text(html),
+                Event::Start(Tag::Link(link_type, url, title)) => Event::Start(Tag::Link(link_type, safe_url(url), title)),
+                Event::Start(Tag::Image(link_type, url, title)) => Event::Start(Tag::Image(link_type, safe_url(url), title)),
+                event => event,
+            })
+            .collect();
+
+        let mut body = String::new();
+        html::push_html(&mut body, events.iter().cloned());
+
+        RenderedMarkdown {
+            html_content: self.template.replace(CONTENT_MARKER, &self.inline_styles(restore_placeholders(body))),
+            text_content: plain_text(&events),
+        }
+    }
+
+    /// Raw HTML from the source is escaped by now, every tag comes from the renderer.
+    fn inline_styles(&self, mut html: String) -> String {
+        for (tag, style) in &self.styles {
+            let style = escape_html(style);
+            html = html
+                .replace(&format!("<{} ", tag), &format!(r#"<{} style="{}" "#, tag, style))
+                .replace(&format!("<{}>", tag), &format!(r#"<{} style="{}">"#, tag, style));
+        }
+
+        html
+    }
+}
+
+fn safe_url(url: CowStr) -> CowStr {
+    let scheme = url.split_once(':').map(|(scheme, _)| scheme.to_ascii_lowercase());
+    let is_safe = match scheme {
+        // A colon after a slash or a `?` is part of a relative link.
+        Some(scheme) if !scheme.contains(['/', '?', '#']) => ALLOWED_SCHEMES.contains(&scheme.as_str()),
+        _ => true,
+    };
+
+    if is_safe { url } else { "#".into() }
+}
+
+/// Links are percent-encoded by the renderer, which mangles `[Unsubscribe]({{unsubscribe_url}})`.
+fn restore_placeholders(html: String) -> String {
+    ["name", "email", "unsubscribe_url"]
+        .iter()
+        .fold(html, |html, name| {
+            html.replace(&format!("%7B%7B%20{}%20%7D%7D", name), &format!("{{{{ {} }}}}", name))
+                .replace(&format!("%7B%7B{}%7D%7D", name), &format!("{{{{ {} }}}}", name))
+        })
+}
+
+/// Markdown is readable as is, this mostly drops the markup and spells links out.
+fn plain_text(events: &[Event]) -> String {
+    let mut text = String::new();
+    let mut list_numbers: Vec<Option<u64>> = Vec::new();
+
+    for event in events {
+        match event {
+            Event::Text(t) | Event::Code(t) => text.push_str(t),
+            Event::SoftBreak | Event::HardBreak => text.push('\n'),
+            Event::Rule => text.push_str("----\n\n"),
+            Event::Start(Tag::List(first_number)) => list_numbers.push(*first_number),
+            Event::End(Tag::List(_)) => {
+                list_numbers.pop();
+                if list_numbers.is_empty() {
+                    text.push('\n');
+                }
+            },
+            Event::Start(Tag::Item) => {
+                text.push_str(&"  ".repeat(list_numbers.len().saturating_sub(1)));
+                match list_numbers.last_mut() {
+                    Some(Some(number)) => {
+                        text.push_str(&format!("{}. ", number));
+                        *number += 1;
+                    },
+                    _ => text.push_str("- "),
+                }
+            },
+            Event::End(Tag::Item) if !text.ends_with('\n') => text.push('\n'),
+            Event::End(Tag::Link(_, url, _)) if !text.ends_with(url.as_ref()) => {
+                text.push_str(&format!(" ({})", url));
+            },
+            Event::End(Tag::Paragraph | Tag::Heading(..) | Tag::CodeBlock(_) | Tag::Table(_)) => {
+                if list_numbers.is_empty() {
+                    text.push_str("\n\n");
+                } else if !text.ends_with('\n') {
+                    text.push('\n');
+                }
+            },
+            Event::End(Tag::TableCell) => text.push('\t'),
+            Event::End(Tag::TableHead | Tag::TableRow) => text.push('\n'),
+            _ => {},
+        }
+    }
+
+    text.trim_end().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::markdown::NewsletterLayout;
+    use std::collections::BTreeMap;
+
+    fn layout() -> NewsletterLayout {
+        NewsletterLayout::new("<body>{{ content }}</body>".into(), BTreeMap::new()).unwrap()
+    }
+
+    #[test]
+    fn markdown_is_rendered_into_the_layout() {
+        let rendered = layout().render("# Hello\n\nSome *news*.");
+
+        assert_eq!(rendered.html_content, "<body><h1>Hello</h1>\n<p>Some <em>news</em>.</p>\n</body>");
+        assert_eq!(rendered.text_content, "Hello\n\nSome news.");
+    }
+
+    #[test]
+    fn a_layout_without_a_content_marker_is_rejected() {
+        assert!(NewsletterLayout::new("<body></body>".into(), BTreeMap::new()).is_err());
+    }
+
+    #[test]
+    fn raw_html_is_escaped() {
+        let rendered = layout().render("<script>alert(1)</script>\n\nHi <b>there</b>");
+
+        assert!(!rendered.html_content.contains("<script>"));
+        assert!(!rendered.html_content.contains("<b>"));
+        assert!(rendered.html_content.contains("&lt;script&gt;"));
+    }
+
+    #[test]
+    fn links_with_unsafe_schemes_are_dropped() {
+        let rendered = layout().render("[click](javascript:alert(1)) [ok](https://example.com) [rel](/a:b)");
+
+        assert!(rendered.html_content.contains(r##"<a href="#">click</a>"##));
+        assert!(rendered.html_content.contains(r#"<a href="https://example.com">ok</a>"#));
+        assert!(rendered.html_content.contains(r#"<a href="/a:b">rel</a>"#));
+    }
+
+    #[test]
+    fn placeholders_survive_in_links() {
+        let rendered = layout().render("[Unsubscribe]({{unsubscribe_url}})");
+
+        assert!(rendered.html_content.contains(r#"<a href="{{ unsubscribe_url }}">Unsubscribe</a>"#));
+        assert_eq!(rendered.text_content, "Unsubscribe ({{unsubscribe_url}})");
+    }
+
+    #[test]
+    fn lists_are_spelled_out_in_plain_text() {
+        let rendered = layout().render("1. one\n2. two\n\n- a\n- b");
+
+        assert_eq!(rendered.text_content, "1. one\n2. two\n\n- a\n- b");
+    }
+
+    #[test]
+    fn styles_are_inlined() {
+        let styles = BTreeMap::from([("p".to_string(), "margin: 0".to_string()), ("a".to_string(), "color: red".to_string())]);
+        let layout = NewsletterLayout::new("{{ content }}".into(), styles).unwrap();
+
+        let rendered = layout.render("[link](https://example.com)");
+
+        assert_eq!(
+            rendered.html_content,
+            r#"<p style="margin: 0"><a style="color: red" href="https://example.com">link</a></p>"#.to_string() + "\n",
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! The subscriber growth chart, drawn as inline SVG so that the page needs nothing
+//! but the HTML: one bar per day for the confirmations, and a line for the running
+//! total of confirmed subscribers, each on its own scale.
+
+use chrono::NaiveDate;
+use std::fmt::Write;
+
+const WIDTH: f64 = 720.0;
+const HEIGHT: f64 = 240.0;
+const LEFT: f64 = 48.0;
+const RIGHT: f64 = WIDTH - 48.0;
+const TOP: f64 = 16.0;
+const BOTTOM: f64 = HEIGHT - 24.0;
+
+pub struct DailyConfirmations {
+    pub day: NaiveDate,
+    pub n_confirmations: i64,
+}
+
+/// `days` are consecutive, `n_confirmed_before` is where the running total starts.
+pub fn growth_chart(days: &[DailyConfirmations], n_confirmed_before: i64) -> String {
+    let (first_day, last_day) = match (days.first(), days.last()) {
+        (Some(first), Some(last)) => (first.day, last.day),
+        _ => return "<p>No data for this period.</p>".to_string(),
+    };
+
+    let max_daily = days.iter().map(|d| d.n_confirmations).max().unwrap_or(0).max(1);
+    let max_total = n_confirmed_before + days.iter().map(|d| d.n_confirmations).sum::<i64>();
+    let slot_width = (RIGHT - LEFT) / days.len() as f64;
+    let y = |value: i64, max: i64| BOTTOM - (BOTTOM - TOP) * value as f64 / max.max(1) as f64;
+
+    let mut bars = String::new();
+    let mut total_points = Vec::with_capacity(days.len());
+    let mut total = n_confirmed_before;
+    for (i, day) in days.iter().enumerate() {
+        let x = LEFT + slot_width * i as f64;
+        let top = y(day.n_confirmations, max_daily);
+        write!(
+            bars,
+            r##"
+    <rect x="{x:.1}" y="{top:.1}" width="{width:.1}" height="{height:.1}" fill="#4a7fb5"><title>{day}: {n} confirmations</title></rect>"##,
+            width = slot_width * 0.8,
+            height = BOTTOM - top,
+            day = day.day,
+            n = day.n_confirmations,
+        )
+        .unwrap();
+
+        total += day.n_confirmations;
+        total_points.push(format!("{:.1},{:.1}", x + slot_width * 0.4, y(total, max_total)));
+    }
+
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {WIDTH} {HEIGHT}" width="{WIDTH}" height="{HEIGHT}" role="img" aria-labelledby="growth-chart-title">
+    <title id="growth-chart-title">Confirmations per day from {first_day} to {last_day}</title>
+    <line x1="{LEFT}" y1="{BOTTOM}" x2="{RIGHT}" y2="{BOTTOM}" stroke="#888"/>{bars}
+    <polyline points="{total_points}" fill="none" stroke="#c0392b" stroke-width="2"><title>Confirmed subscribers</title></polyline>
+    <text x="{left_label}" y="{top_label}" text-anchor="end" font-size="12">{max_daily}</text>
+    <text x="{left_label}" y="{BOTTOM}" text-anchor="end" font-size="12">0</text>
+    <text x="{right_label}" y="{top_label}" font-size="12" fill="#c0392b">{max_total}</text>
+    <text x="{LEFT}" y="{date_label}" font-size="12">{first_day}</text>
+    <text x="{RIGHT}" y="{date_label}" text-anchor="end" font-size="12">{last_day}</text>
+</svg>"##,
+        total_points = total_points.join(" "),
+        left_label = LEFT - 6.0,
+        right_label = RIGHT + 6.0,
+        top_label = TOP + 4.0,
+        date_label = HEIGHT - 6.0,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{growth_chart, DailyConfirmations, BOTTOM, TOP};
+    use chrono::{Duration, NaiveDate};
+
+    fn days(confirmations: &[i64]) -> Vec<DailyConfirmations> {
+        let start = NaiveDate::from_ymd_opt(2023, 8, 1).unwrap();
+
+        confirmations
+            .iter()
+            .enumerate()
+            .map(|(i, &n_confirmations)| DailyConfirmations { day: start + Duration::days(i as i64), n_confirmations })
+            .collect()
+    }
+
+    #[test]
+    fn every_day_gets_a_bar() {
+        let chart = growth_chart(&days(&[1, 0, 3]), 0);
+
+        assert_eq!(chart.matches("<rect ").count(), 3);
+        assert!(chart.contains("<title>2023-08-02: 0 confirmations</title>"));
+        assert!(chart.contains("from 2023-08-01 to 2023-08-03"));
+    }
+
+    #[test]
+    fn the_busiest_day_and_the_final_total_reach_the_top() {
+        let chart = growth_chart(&days(&[1, 4, 2]), 10);
+
+        let busiest_bar = format!(r#"y="{:.1}" width"#, TOP);
+        assert_eq!(chart.matches(&busiest_bar).count(), 1);
+        assert!(chart.contains(&format!(r#",{:.1}" fill="none""#, TOP)));
+        assert!(chart.contains(">17</text>"));
+    }
+
+    #[test]
+    fn a_period_without_confirmations_stays_flat() {
+        let chart = growth_chart(&days(&[0, 0]), 0);
+
+        assert_eq!(chart.matches(&format!(r#"y="{:.1}" width"#, BOTTOM)).count(), 2);
+        assert!(!chart.contains("NaN"));
+    }
+}
@@ -0,0 +1,260 @@
+use crate::routes::admin::analytics::chart::{growth_chart, DailyConfirmations};
+use crate::routes::helpers::ApiError;
+use crate::utils::escape_html;
+
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+const ISSUES_PER_PAGE: i64 = 20;
+const DAYS_CHARTED: i32 = 90;
+
+#[derive(serde::Deserialize)]
+pub struct AnalyticsParameters {
+    page: Option<u32>,
+}
+
+struct IssueStats {
+    id: Uuid,
+    title: String,
+    published_at: Option<DateTime<Utc>>,
+    track_opens: bool,
+    n_recipients: i64,
+    n_sent: i64,
+    n_failed: i64,
+    n_readers: i64,
+    n_clickers: i64,
+    n_clicks: i64,
+    n_unsubscribes: i64,
+}
+
+#[tracing::instrument(name = "Show the newsletter analytics", skip(query, pool))]
+pub async fn analytics(
+    query: web::Query<AnalyticsParameters>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let page = query.page.unwrap_or(1).max(1);
+
+    // One extra row tells us whether there is a next page.
+    let mut issues = get_issue_stats(&pool, page)
+        .await
+        .context("Failed to retrieve the newsletter issue statistics")?;
+    let has_next_page = issues.len() as i64 > ISSUES_PER_PAGE;
+    issues.truncate(ISSUES_PER_PAGE as usize);
+
+    let days = get_daily_confirmations(&pool)
+        .await
+        .context("Failed to retrieve the daily confirmations")?;
+    let n_confirmed_before = get_n_confirmed_before(&pool)
+        .await
+        .context("Failed to retrieve the confirmations before the charted period")?;
+    let chart = growth_chart(&days, n_confirmed_before);
+
+    let mut rows = String::new();
+    for issue in &issues {
+        let opens = if issue.track_opens {
+            with_rate(issue.n_readers, issue.n_sent)
+        } else {
+            "Not tracked".to_string()
+        };
+        writeln!(
+            rows,
+            r#"
+                    <tr>
+                        <td><a href="/admin/newsletters/{id}">{title}</a></td>
+                        <td>{published_at}</td>
+                        <td>{n_recipients}</td>
+                        <td>{n_sent}</td>
+                        <td>{n_failed}</td>
+                        <td>{opens}</td>
+                        <td>{clickers}, {n_clicks} clicks</td>
+                        <td>{unsubscribes}</td>
+                    </tr>"#,
+            id = issue.id,
+            title = escape_html(&issue.title),
+            published_at = issue.published_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            n_recipients = issue.n_recipients,
+            n_sent = issue.n_sent,
+            n_failed = issue.n_failed,
+            clickers = with_rate(issue.n_clickers, issue.n_sent),
+            n_clicks = issue.n_clicks,
+            unsubscribes = with_rate(issue.n_unsubscribes, issue.n_sent),
+        )
+        .unwrap();
+    }
+
+    let mut pagination = String::new();
+    if page > 1 {
+        write!(pagination, r#"<a href="/admin/analytics?page={}">Newer</a> "#, page - 1).unwrap();
+    }
+    if has_next_page {
+        write!(pagination, r#"<a href="/admin/analytics?page={}">Older</a>"#, page + 1).unwrap();
+    }
+
+    let empty_note = if issues.is_empty() {
+        "<p>No newsletter issues have been published yet.</p>"
+    } else {
+        ""
+    };
+
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Analytics</title>
+        </head>
+        <body>
+            <h1>Analytics</h1>
+
+            <h2>Subscriber growth</h2>
+            <p>Confirmed subscriptions over the last {DAYS_CHARTED} days, by the day they signed up.</p>
+            {chart}
+
+            <h2>Issues</h2>
+            <p>Unsubscribes are attributed to the last issue sent to the subscriber before they left.</p>
+            <table>
+                <thead>
+                    <tr>
+                        <th>Title</th>
+                        <th>Published at</th>
+                        <th>Recipients</th>
+                        <th>Sent</th>
+                        <th>Failed</th>
+                        <th>Opened by</th>
+                        <th>Clicked by</th>
+                        <th>Unsubscribes</th>
+                    </tr>
+                </thead>
+                <tbody>{rows}
+                </tbody>
+            </table>
+            {empty_note}
+            <p>{pagination}</p>
+
+            <a href="/admin/dashboard">&lt;- Back</a>
+        </body>
+        </html>
+        "#
+    );
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(body)
+    )
+}
+
+/// `3 (60%)`, the rate is left out when nothing was sent.
+fn with_rate(n: i64, n_sent: i64) -> String {
+    if n_sent == 0 {
+        n.to_string()
+    } else {
+        format!("{} ({:.0}%)", n, 100.0 * n as f64 / n_sent as f64)
+    }
+}
+
+/// Published issues only, drafts have nothing to show yet.
+#[tracing::instrument(skip(pool))]
+async fn get_issue_stats(pool: &PgPool, page: u32) -> Result<Vec<IssueStats>, sqlx::Error> {
+    sqlx::query_as!(
+        IssueStats,
+        r#"
+        WITH unsubscribes AS (
+            SELECT last_delivery.newsletter_issue_id
+            FROM subscriptions
+            CROSS JOIN LATERAL (
+                SELECT newsletter_issue_id FROM newsletter_deliveries
+                WHERE subscriber_email = subscriptions.email
+                    AND status = 'sent'
+                    AND delivered_at <= subscriptions.unsubscribed_at
+                ORDER BY delivered_at DESC
+                LIMIT 1
+            ) AS last_delivery
+            WHERE subscriptions.status = 'unsubscribed'
+        )
+        SELECT newsletter_issues.id,
+            newsletter_issues.title,
+            newsletter_issues.published_at,
+            newsletter_issues.track_opens,
+            (
+                SELECT COUNT(*) FROM issue_delivery_queue
+                WHERE newsletter_issue_id = newsletter_issues.id
+            ) + (
+                SELECT COUNT(*) FROM newsletter_deliveries
+                WHERE newsletter_issue_id = newsletter_issues.id
+            ) as "n_recipients!",
+            (
+                SELECT COUNT(*) FROM newsletter_deliveries
+                WHERE newsletter_issue_id = newsletter_issues.id AND status = 'sent'
+            ) as "n_sent!",
+            (
+                SELECT COUNT(*) FROM newsletter_deliveries
+                WHERE newsletter_issue_id = newsletter_issues.id AND status = 'failed'
+            ) as "n_failed!",
+            (
+                SELECT COUNT(*) FROM newsletter_opens
+                WHERE newsletter_issue_id = newsletter_issues.id
+            ) as "n_readers!",
+            (
+                SELECT COUNT(DISTINCT subscriber_id) FROM newsletter_clicks
+                WHERE newsletter_issue_id = newsletter_issues.id
+            ) as "n_clickers!",
+            (
+                SELECT COUNT(*) FROM newsletter_clicks
+                WHERE newsletter_issue_id = newsletter_issues.id
+            ) as "n_clicks!",
+            (
+                SELECT COUNT(*) FROM unsubscribes
+                WHERE newsletter_issue_id = newsletter_issues.id
+            ) as "n_unsubscribes!"
+        FROM newsletter_issues
+        WHERE newsletter_issues.published_at IS NOT NULL
+        ORDER BY newsletter_issues.published_at DESC, newsletter_issues.id
+        LIMIT $1 OFFSET $2
+        "#,
+        ISSUES_PER_PAGE + 1,
+        (i64::from(page) - 1) * ISSUES_PER_PAGE,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Every day of the period, the ones without confirmations included. Subscribers who
+/// unsubscribed since still count: they did confirm.
+#[tracing::instrument(skip(pool))]
+async fn get_daily_confirmations(pool: &PgPool) -> Result<Vec<DailyConfirmations>, sqlx::Error> {
+    sqlx::query_as!(
+        DailyConfirmations,
+        r#"
+        SELECT days.day::date as "day!", COUNT(subscriptions.id) as "n_confirmations!"
+        FROM generate_series(current_date - ($1::integer - 1), current_date, interval '1 day') AS days (day)
+        LEFT JOIN subscriptions
+            ON subscriptions.subscribed_at::date = days.day::date
+            AND subscriptions.status <> 'pending_confirmation'
+        GROUP BY days.day
+        ORDER BY days.day
+        "#,
+        DAYS_CHARTED,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_n_confirmed_before(pool: &PgPool) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) as "count!" FROM subscriptions
+        WHERE subscribed_at::date <= current_date - $1::integer
+            AND status <> 'pending_confirmation'
+        "#,
+        DAYS_CHARTED,
+    )
+    .fetch_one(pool)
+    .await
+}
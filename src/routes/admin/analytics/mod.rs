@@ -0,0 +1,4 @@
+mod chart;
+mod get;
+
+pub use get::analytics;
@@ -30,6 +30,9 @@ pub async fn admin_dashboard(
                         <p>Available actions:</p>
                         <ol>
                             <li><a href="/admin/newsletters">Send a newsletter issue</a></li>
+                            <li><a href="/admin/newsletters/history">Past newsletter issues</a></li>
+                            <li><a href="/admin/newsletters/failures">Failed deliveries</a></li>
+                            <li><a href="/admin/analytics">Analytics</a></li>
                             <li><a href="/admin/password">Change password</a></li>
                             <li>
                                 <form action="/admin/logout" method="post" name="logoutForm">
@@ -1,8 +1,10 @@
+mod analytics;
 mod dashboard;
 mod logout;
 mod newsletters;
 mod password;
 
+pub use analytics::analytics;
 pub use dashboard::{admin_dashboard, get_username};
 pub use logout::log_out;
 pub use newsletters::*;
@@ -0,0 +1,50 @@
+use crate::routes::helpers::ApiError;
+use crate::utils::see_other;
+
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(serde::Deserialize, Debug)]
+pub struct FormData {
+    hidden_from_archive: bool,
+}
+
+/// Works for drafts too: they are kept out of the archive once published.
+#[tracing::instrument(name = "Change the archive visibility of a newsletter issue", skip(pool))]
+pub async fn set_archive_visibility(
+    newsletter_issue_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+
+    let n_updated = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET hidden_from_archive = $2,
+            archive_visibility_changed_at = now()
+        WHERE id = $1
+        "#,
+        newsletter_issue_id,
+        form.hidden_from_archive,
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to change the archive visibility")?
+    .rows_affected();
+
+    if n_updated == 0 {
+        return Err(ApiError::NotFoundError);
+    }
+
+    if form.hidden_from_archive {
+        FlashMessage::info("The newsletter issue is hidden from the archive.").send();
+    } else {
+        FlashMessage::info("The newsletter issue is shown in the archive.").send();
+    }
+
+    Ok(see_other(&format!("/admin/newsletters/{}", newsletter_issue_id)))
+}
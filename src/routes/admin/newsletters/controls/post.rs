@@ -0,0 +1,188 @@
+use crate::issue_delivery_worker::notify_new_tasks;
+use crate::routes::admin::newsletters::helpers::issue_exists;
+use crate::routes::helpers::ApiError;
+use crate::utils::see_other;
+
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+/// Paused issues keep their tasks in `issue_delivery_queue`, `dequeue_task` skips them.
+/// Emails that were already in flight still go out.
+#[tracing::instrument(name = "Pause a newsletter issue", skip(pool))]
+pub async fn pause_newsletter(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+
+    let n_paused = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = 'paused'
+        WHERE id = $1 AND status IN ('scheduled', 'sending')
+        "#,
+        newsletter_issue_id,
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to pause the newsletter issue")?
+    .rows_affected();
+
+    if n_paused == 0 {
+        let issue_exists = issue_exists(&pool, newsletter_issue_id)
+            .await
+            .context("Failed to retrieve the newsletter issue")?;
+        if !issue_exists {
+            return Err(ApiError::NotFoundError);
+        }
+
+        FlashMessage::error("Only issues that are scheduled or being sent can be paused.").send();
+    } else {
+        FlashMessage::info("The newsletter issue has been paused.").send();
+    }
+
+    Ok(see_other(&format!("/admin/newsletters/{}", newsletter_issue_id)))
+}
+
+#[tracing::instrument(name = "Resume a newsletter issue", skip(pool))]
+pub async fn resume_newsletter(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+
+    // Issues paused before they were due have no recipients yet, `enqueue_due_issues`
+    // picks them once they are scheduled again. The last in-flight emails of the others
+    // might have gone out while the issue was paused.
+    let n_resumed = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = CASE
+                WHEN NOT EXISTS (
+                    SELECT 1 FROM issue_delivery_queue WHERE newsletter_issue_id = $1
+                ) AND NOT EXISTS (
+                    SELECT 1 FROM newsletter_deliveries WHERE newsletter_issue_id = $1
+                ) THEN 'scheduled'
+                WHEN NOT EXISTS (
+                    SELECT 1 FROM issue_delivery_queue WHERE newsletter_issue_id = $1
+                ) THEN 'sent'
+                ELSE 'sending'
+            END
+        WHERE id = $1 AND status = 'paused'
+        "#,
+        newsletter_issue_id,
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to resume the newsletter issue")?
+    .rows_affected();
+
+    if n_resumed == 0 {
+        let issue_exists = issue_exists(&pool, newsletter_issue_id)
+            .await
+            .context("Failed to retrieve the newsletter issue")?;
+        if !issue_exists {
+            return Err(ApiError::NotFoundError);
+        }
+
+        FlashMessage::error("Only paused issues can be resumed.").send();
+    } else {
+        notify_new_tasks(pool.get_ref())
+            .await
+            .context("Failed to notify the delivery workers")?;
+        FlashMessage::info("The newsletter issue has been resumed.").send();
+    }
+
+    Ok(see_other(&format!("/admin/newsletters/{}", newsletter_issue_id)))
+}
+
+/// Drops the remaining deliveries of the issue, including the failed ones waiting
+/// to be requeued. Emails that were already in flight still go out.
+#[tracing::instrument(name = "Cancel a newsletter issue", skip(pool))]
+pub async fn cancel_newsletter(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    let status = sqlx::query!(
+        r#"SELECT status FROM newsletter_issues WHERE id = $1 FOR UPDATE"#,
+        newsletter_issue_id,
+    )
+    .fetch_optional(&mut transaction)
+    .await
+    .context("Failed to retrieve the newsletter issue")?
+    .ok_or(ApiError::NotFoundError)?
+    .status;
+
+    if !["scheduled", "sending", "paused"].contains(&status.as_str()) {
+        FlashMessage::error("Only issues that are scheduled or being sent can be cancelled.").send();
+        return Ok(see_other(&format!("/admin/newsletters/{}", newsletter_issue_id)));
+    }
+
+    let n_sent = cancel_deliveries(&mut transaction, newsletter_issue_id)
+        .await
+        .context("Failed to cancel the newsletter issue")?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to cancel the newsletter issue")?;
+
+    FlashMessage::info(format!(
+        "The newsletter issue has been cancelled, {} recipients had already been sent it.",
+        n_sent,
+    ))
+    .send();
+
+    Ok(see_other(&format!("/admin/newsletters/{}", newsletter_issue_id)))
+}
+
+/// Returns how many recipients had been sent the issue.
+/// Deleting a task that a worker is sending blocks until the worker is done with it,
+/// so the count includes the emails that were in flight.
+async fn cancel_deliveries(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_queue WHERE newsletter_issue_id = $1"#,
+        newsletter_issue_id,
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    sqlx::query!(
+        r#"DELETE FROM issue_delivery_failures WHERE newsletter_issue_id = $1"#,
+        newsletter_issue_id,
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    let record = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = 'cancelled',
+            cancelled_at = now(),
+            n_sent_before_cancel = (
+                SELECT COUNT(*) FROM newsletter_deliveries
+                WHERE newsletter_issue_id = $1 AND status = 'sent'
+            )
+        WHERE id = $1
+        RETURNING n_sent_before_cancel as "n_sent_before_cancel!"
+        "#,
+        newsletter_issue_id,
+    )
+    .fetch_one(&mut *transaction)
+    .await?;
+
+    Ok(record.n_sent_before_cancel.into())
+}
@@ -0,0 +1,69 @@
+use crate::routes::admin::newsletters::helpers::{get_newsletter_issue, newsletter_content_fields, tracking_fields};
+use crate::routes::helpers::ApiError;
+use crate::utils::see_other;
+
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use anyhow::Context;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+#[tracing::instrument(name = "Show the newsletter draft editor", skip(pool, flash_messages))]
+pub async fn edit_newsletter_form(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, ApiError> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let issue = get_newsletter_issue(&pool, newsletter_issue_id)
+        .await
+        .context("Failed to retrieve the newsletter issue")?
+        .ok_or(ApiError::NotFoundError)?;
+
+    if !issue.is_draft() {
+        FlashMessage::error("Only drafts can be edited.").send();
+        return Ok(see_other(&format!("/admin/newsletters/{}", newsletter_issue_id)));
+    }
+
+    let mut flash_msg = String::new();
+    for message in flash_messages.iter() {
+        writeln!(flash_msg, "<p><i>{}</i></p>", message.content()).unwrap()
+    }
+
+    let content_fields = newsletter_content_fields(
+        &issue.title,
+        issue.markdown_content.as_deref().unwrap_or(""),
+        &issue.text_content,
+        &issue.html_content,
+    );
+    let tracking_fields = tracking_fields(issue.track_opens);
+
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Edit newsletter issue</title>
+        </head>
+        <body>
+            {flash_msg}
+
+            <form action="/admin/newsletters/{newsletter_issue_id}/edit" method="post">{content_fields}{tracking_fields}
+
+                <button type="submit">Save draft</button>
+            </form>
+
+            <a href="/admin/newsletters/{newsletter_issue_id}/preview">&lt;- Back to the preview</a>
+        </body>
+        </html>
+        "#
+    );
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(body)
+    )
+}
@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::edit_newsletter_form;
+pub use post::edit_newsletter;
@@ -0,0 +1,93 @@
+use crate::markdown::NewsletterLayout;
+use crate::routes::admin::newsletters::helpers::{issue_exists, parse_newsletter_content, send_removed_html_flash, NewsletterContent};
+use crate::routes::helpers::ApiError;
+use crate::utils::see_other;
+
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(serde::Deserialize, Debug)]
+pub struct FormData {
+    title: String,
+    #[serde(default)]
+    markdown_content: String,
+    #[serde(default)]
+    text_content: String,
+    #[serde(default)]
+    html_content: String,
+    #[serde(default)]
+    track_opens: bool,
+}
+
+#[tracing::instrument(name = "Edit a newsletter draft", skip(form, pool, layout))]
+pub async fn edit_newsletter(
+    newsletter_issue_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    layout: web::Data<NewsletterLayout>,
+) -> Result<HttpResponse, ApiError> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let FormData { title, markdown_content, text_content, html_content, track_opens } = form.0;
+
+    if title.trim().is_empty() {
+        return Err(ApiError::ValidationError("The title cannot be empty.".into()));
+    }
+    let content = parse_newsletter_content(markdown_content, text_content, html_content, &layout)
+        .map_err(ApiError::ValidationError)?;
+
+    let n_updated = update_newsletter_draft(&pool, newsletter_issue_id, &title, &content, track_opens)
+        .await
+        .context("Failed to update the newsletter draft")?;
+
+    if n_updated == 0 {
+        let issue_exists = issue_exists(&pool, newsletter_issue_id)
+            .await
+            .context("Failed to retrieve the newsletter issue")?;
+        if !issue_exists {
+            return Err(ApiError::NotFoundError);
+        }
+
+        FlashMessage::error("Only drafts can be edited.").send();
+        return Ok(see_other(&format!("/admin/newsletters/{}", newsletter_issue_id)));
+    }
+
+    FlashMessage::info("The draft has been saved.").send();
+    send_removed_html_flash(&content.removed_html);
+
+    Ok(see_other(&format!("/admin/newsletters/{}/preview", newsletter_issue_id)))
+}
+
+#[tracing::instrument(skip_all)]
+async fn update_newsletter_draft(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+    title: &str,
+    content: &NewsletterContent,
+    track_opens: bool,
+) -> Result<u64, sqlx::Error> {
+    let n_updated = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET title = $2,
+            text_content = $3,
+            html_content = $4,
+            markdown_content = $5,
+            track_opens = $6
+        WHERE id = $1 AND status = 'draft'
+        "#,
+        newsletter_issue_id,
+        title,
+        content.text_content.as_ref(),
+        content.html_content.as_ref(),
+        content.markdown_content,
+        track_opens,
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(n_updated)
+}
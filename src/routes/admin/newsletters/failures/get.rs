@@ -0,0 +1,137 @@
+use crate::routes::helpers::ApiError;
+use crate::utils::escape_html;
+
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+struct DeliveryFailure {
+    newsletter_issue_id: Uuid,
+    title: String,
+    subscriber_email: String,
+    last_error: String,
+    n_attempts: i16,
+    enqueued_at: DateTime<Utc>,
+    failed_at: DateTime<Utc>,
+}
+
+pub async fn delivery_failures(
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, ApiError> {
+    let mut flash_msg = String::new();
+    for message in flash_messages.iter() {
+        writeln!(flash_msg, "<p><i>{}</i></p>", message.content()).unwrap()
+    }
+
+    let failures = get_delivery_failures(&pool)
+        .await
+        .context("Failed to retrieve delivery failures")?;
+
+    let mut rows = String::new();
+    for failure in &failures {
+        writeln!(
+            rows,
+            r#"
+                    <tr>
+                        <td><a href="/admin/newsletters/{newsletter_issue_id}">{title}</a></td>
+                        <td>{subscriber_email}</td>
+                        <td>{n_attempts}</td>
+                        <td>{last_error}</td>
+                        <td>{enqueued_at}</td>
+                        <td>{failed_at}</td>
+                        <td>
+                            <form action="/admin/newsletters/failures/requeue" method="post">
+                                <input hidden type="text" name="newsletter_issue_id" value="{newsletter_issue_id}">
+                                <input hidden type="text" name="subscriber_email" value="{subscriber_email}">
+                                <button type="submit">Requeue</button>
+                            </form>
+                        </td>
+                    </tr>"#,
+            title = escape_html(&failure.title),
+            subscriber_email = escape_html(&failure.subscriber_email),
+            n_attempts = failure.n_attempts,
+            last_error = escape_html(&failure.last_error),
+            enqueued_at = failure.enqueued_at.to_rfc3339(),
+            failed_at = failure.failed_at.to_rfc3339(),
+            newsletter_issue_id = failure.newsletter_issue_id,
+        )
+        .unwrap();
+    }
+
+    let requeue_all = if failures.is_empty() {
+        "<p>There are no failed deliveries.</p>".to_string()
+    } else {
+        r#"
+                <form action="/admin/newsletters/failures/requeue_all" method="post">
+                    <button type="submit">Requeue all</button>
+                </form>"#
+            .to_string()
+    };
+
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Failed deliveries</title>
+        </head>
+        <body>
+            {flash_msg}
+
+            <table>
+                <thead>
+                    <tr>
+                        <th>Issue</th>
+                        <th>Subscriber</th>
+                        <th>Attempts</th>
+                        <th>Last error</th>
+                        <th>Enqueued at</th>
+                        <th>Failed at</th>
+                        <th></th>
+                    </tr>
+                </thead>
+                <tbody>{rows}
+                </tbody>
+            </table>
+            {requeue_all}
+
+            <a href="/admin/dashboard">&lt;- Back</a>
+        </body>
+        </html>
+        "#
+    );
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(body)
+    )
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_delivery_failures(pool: &PgPool) -> Result<Vec<DeliveryFailure>, sqlx::Error> {
+    sqlx::query_as!(
+        DeliveryFailure,
+        r#"
+        SELECT issue_delivery_failures.newsletter_issue_id,
+            newsletter_issues.title,
+            issue_delivery_failures.subscriber_email,
+            issue_delivery_failures.last_error,
+            issue_delivery_failures.n_attempts,
+            issue_delivery_failures.enqueued_at,
+            issue_delivery_failures.failed_at
+        FROM issue_delivery_failures
+            INNER JOIN newsletter_issues
+                ON issue_delivery_failures.newsletter_issue_id = newsletter_issues.id
+        ORDER BY issue_delivery_failures.failed_at DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
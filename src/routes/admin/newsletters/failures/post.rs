@@ -0,0 +1,112 @@
+use crate::issue_delivery_worker::notify_new_tasks;
+use crate::routes::helpers::ApiError;
+use crate::utils::see_other;
+
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    newsletter_issue_id: Uuid,
+    subscriber_email: String,
+}
+
+#[tracing::instrument(name = "Requeue a failed delivery", skip(form, pool))]
+pub async fn requeue_delivery_failure(
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let n_requeued = requeue_failures(&pool, Some((form.newsletter_issue_id, &form.subscriber_email)))
+        .await
+        .context("Failed to requeue a failed delivery")?;
+
+    if n_requeued == 0 {
+        FlashMessage::error("The delivery could not be requeued.").send();
+    } else {
+        FlashMessage::info("The delivery has been requeued.").send();
+    }
+
+    Ok(see_other("/admin/newsletters/failures"))
+}
+
+#[tracing::instrument(name = "Requeue all failed deliveries", skip(pool))]
+pub async fn requeue_all_delivery_failures(
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let n_requeued = requeue_failures(&pool, None)
+        .await
+        .context("Failed to requeue failed deliveries")?;
+
+    FlashMessage::info(format!("{} deliveries have been requeued.", n_requeued)).send();
+
+    Ok(see_other("/admin/newsletters/failures"))
+}
+
+/// Moves failed deliveries back to `issue_delivery_queue` with as many retries as
+/// they had attempts, at least one: an exhausted delivery gets its budget again, a
+/// permanently rejected one a single retry. Subscribers that are not confirmed anymore are left out.
+/// Their `failed` status in `newsletter_deliveries` goes away until the new outcome is known,
+/// and their issue goes back to `sending`. A failure whose task is still queued keeps its record.
+async fn requeue_failures(
+    pool: &PgPool,
+    task: Option<(Uuid, &str)>,
+) -> Result<u64, sqlx::Error> {
+    let (newsletter_issue_id, subscriber_email) = task.unzip();
+
+    let n_requeued = sqlx::query!(
+        r#"
+        WITH requeued AS (
+            INSERT INTO issue_delivery_queue (
+                newsletter_issue_id,
+                subscriber_email,
+                n_retries,
+                retry_base_delay_in_secs,
+                retry_max_delay_in_secs
+            )
+            SELECT issue_delivery_failures.newsletter_issue_id,
+                issue_delivery_failures.subscriber_email,
+                GREATEST(issue_delivery_failures.n_attempts, 1),
+                issue_delivery_failures.retry_base_delay_in_secs,
+                issue_delivery_failures.retry_max_delay_in_secs
+            FROM issue_delivery_failures
+                INNER JOIN subscriptions
+                    ON subscriptions.email = issue_delivery_failures.subscriber_email
+            WHERE subscriptions.status = 'confirmed'
+                AND ($1::uuid IS NULL OR issue_delivery_failures.newsletter_issue_id = $1)
+                AND ($2::text IS NULL OR issue_delivery_failures.subscriber_email = $2)
+            ON CONFLICT DO NOTHING
+            RETURNING newsletter_issue_id, subscriber_email
+        ), cleared_failures AS (
+            DELETE FROM issue_delivery_failures
+            USING requeued
+            WHERE issue_delivery_failures.newsletter_issue_id = requeued.newsletter_issue_id
+                AND issue_delivery_failures.subscriber_email = requeued.subscriber_email
+        ), pending_deliveries AS (
+            DELETE FROM newsletter_deliveries
+            USING requeued
+            WHERE newsletter_deliveries.newsletter_issue_id = requeued.newsletter_issue_id
+                AND newsletter_deliveries.subscriber_email = requeued.subscriber_email
+        ), resumed_issues AS (
+            UPDATE newsletter_issues
+            SET status = 'sending'
+            WHERE status = 'sent'
+                AND id IN (SELECT newsletter_issue_id FROM requeued)
+        )
+        SELECT COUNT(*) as "count!" FROM requeued
+        "#,
+        newsletter_issue_id,
+        subscriber_email,
+    )
+    .fetch_one(pool)
+    .await?
+    .count as u64;
+
+    if n_requeued > 0 {
+        notify_new_tasks(pool).await?;
+    }
+
+    Ok(n_requeued)
+}
@@ -1,8 +1,8 @@
 use actix_web::{http::header::ContentType, HttpResponse};
 use actix_web_flash_messages::IncomingFlashMessages;
 use std::fmt::Write;
-use uuid::Uuid;
 
+use crate::routes::admin::newsletters::helpers::{newsletter_content_fields, tracking_fields};
 use crate::routes::helpers::ApiError;
 
 pub async fn submit_newsletter_form(
@@ -13,7 +13,8 @@ pub async fn submit_newsletter_form(
         writeln!(flash_msg, "<p><i>{}</i></p>", message.content()).unwrap()
     }
 
-    let idempotency_key = Uuid::new_v4();
+    let content_fields = newsletter_content_fields("", "", "", "");
+    let tracking_fields = tracking_fields(false);
 
     let body = 
         format!(
@@ -22,41 +23,15 @@ pub async fn submit_newsletter_form(
             <html lang="en">
             <head>
                 <meta http-equiv="content-type" content="text/html; charset=utf-8">
-                <title>Change Password</title>
+                <title>New newsletter issue</title>
             </head>
             <body>
                 {flash_msg}
 
-                <form action="/admin/newsletters" method="post">
-                    <input hidden type="text" name="idempotency_key" value={idempotency_key}>
+                <form action="/admin/newsletters" method="post">{content_fields}{tracking_fields}
 
-                    <lable>
-                        Title
-                        <input type="text" name="title">
-                    </lable>
-
-                    <lable>
-                        Text content
-                        <input type="text" name="text_content">
-                    </lable>
-
-                    <lable>
-                        HTML content
-                        <input type="text" name="html_content">
-                    </lable>
-
-                    <p>Retry settings (Optional)</p>
-                    <label>
-                        N-retries
-                        <input type="number" name="n_retries" placeholder="20">
-                    </label>
-
-                    <label>
-                        Retry after in seconds
-                        <input type="number" name="execute_after_in_secs">
-                    </label>
-
-                    <button type="submit">Send</button>
+                    <p>The issue is saved as a draft: you can preview it before publishing it.</p>
+                    <button type="submit">Save draft</button>
                 </form>
 
                 <a href="/admin/dashboard">&lt;- Back</a>
@@ -0,0 +1,162 @@
+use crate::domain::{NewsletterHtml, NewsletterTemplate};
+use crate::markdown::NewsletterLayout;
+use crate::utils::escape_html;
+
+use actix_web_flash_messages::FlashMessage;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub struct NewsletterIssue {
+    pub title: String,
+    pub text_content: String,
+    pub html_content: String,
+    pub markdown_content: Option<String>,
+    pub status: String,
+    pub published_at: Option<DateTime<Utc>>,
+    pub n_sent_before_cancel: Option<i32>,
+    pub slug: Option<String>,
+    pub hidden_from_archive: bool,
+    pub track_opens: bool,
+}
+
+impl NewsletterIssue {
+    pub fn is_draft(&self) -> bool {
+        self.status == "draft"
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn get_newsletter_issue(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<Option<NewsletterIssue>, sqlx::Error> {
+    sqlx::query_as!(
+        NewsletterIssue,
+        r#"
+        SELECT title, text_content, html_content, markdown_content, status, published_at, n_sent_before_cancel,
+            slug, hidden_from_archive, track_opens
+        FROM newsletter_issues
+        WHERE id = $1
+        "#,
+        newsletter_issue_id,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn issue_exists(pool: &PgPool, newsletter_issue_id: Uuid) -> Result<bool, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"SELECT id FROM newsletter_issues WHERE id = $1"#,
+        newsletter_issue_id,
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(record.is_some())
+}
+
+/// What gets stored for an issue, whether it was written in Markdown or by hand.
+pub struct NewsletterContent {
+    pub markdown_content: Option<String>,
+    pub text_content: NewsletterTemplate,
+    pub html_content: NewsletterTemplate,
+    /// What the sanitizer took out of a hand-written HTML version.
+    pub removed_html: Vec<String>,
+}
+
+/// Markdown, when there is some, takes precedence over the hand-written versions.
+/// Hand-written HTML is sanitized, the Markdown renderer escapes raw HTML already.
+pub fn parse_newsletter_content(
+    markdown_content: String,
+    text_content: String,
+    html_content: String,
+    layout: &NewsletterLayout,
+) -> Result<NewsletterContent, String> {
+    let (markdown_content, text_content, html_content, removed_html) = if markdown_content.trim().is_empty() {
+        if text_content.trim().is_empty() || html_content.trim().is_empty() {
+            return Err("The issue needs either Markdown content or both a plain text and an HTML version.".into());
+        }
+
+        let (html_content, removed_html) = NewsletterHtml::sanitize(&html_content);
+        (None, text_content, html_content.as_ref().to_owned(), removed_html)
+    } else {
+        let rendered = layout.render(&markdown_content);
+        (Some(markdown_content), rendered.text_content, rendered.html_content, Vec::new())
+    };
+
+    Ok(NewsletterContent {
+        markdown_content,
+        text_content: NewsletterTemplate::parse(text_content)?,
+        html_content: NewsletterTemplate::parse(html_content)?,
+        removed_html,
+    })
+}
+
+/// Tells the editor what was taken out of their HTML, if anything.
+pub fn send_removed_html_flash(removed_html: &[String]) {
+    if !removed_html.is_empty() {
+        FlashMessage::info(format!(
+            "Unsafe HTML was removed from the content: {}.",
+            escape_html(&removed_html.join(", ")),
+        ))
+        .send();
+    }
+}
+
+/// Form fields shared by the "new draft" and the "edit draft" pages.
+pub fn newsletter_content_fields(title: &str, markdown_content: &str, text_content: &str, html_content: &str) -> String {
+    // Issues written by hand keep being edited by hand.
+    let open = if markdown_content.is_empty() && !html_content.is_empty() { " open" } else { "" };
+
+    format!(
+        r#"
+                    <label>
+                        Title
+                        <input type="text" name="title" value="{title}">
+                    </label>
+
+                    <label>
+                        Markdown content
+                        <textarea name="markdown_content" rows="20" cols="80">{markdown_content}</textarea>
+                    </label>
+
+                    <p>The HTML and the plain text versions are generated from the Markdown.
+                    Raw HTML is not allowed, links use http, https or mailto.</p>
+
+                    <details{open}>
+                        <summary>Write both versions by hand instead (used when there is no Markdown)</summary>
+
+                        <label>
+                            Text content
+                            <textarea name="text_content" rows="10" cols="80">{text_content}</textarea>
+                        </label>
+
+                        <label>
+                            HTML content
+                            <textarea name="html_content" rows="10" cols="80">{html_content}</textarea>
+                        </label>
+                    </details>
+
+                    <p>The contents can use {{{{ name }}}}, {{{{ email }}}} and {{{{ unsubscribe_url }}}}, they are filled in for every subscriber.
+                    In Markdown links, write them without spaces: [Unsubscribe]({{{{unsubscribe_url}}}}).</p>"#,
+        title = escape_html(title),
+        markdown_content = escape_html(markdown_content),
+        text_content = escape_html(text_content),
+        html_content = escape_html(html_content),
+    )
+}
+
+/// Checked boxes post `track_opens=true`, unchecked ones post nothing.
+pub fn tracking_fields(track_opens: bool) -> String {
+    let checked = if track_opens { " checked" } else { "" };
+
+    format!(
+        r#"
+                    <label>
+                        <input type="checkbox" name="track_opens" value="true"{checked}>
+                        Track opens (subscribers who opted out of tracking are left alone)
+                    </label>"#
+    )
+}
@@ -0,0 +1,148 @@
+use crate::routes::helpers::ApiError;
+use crate::utils::escape_html;
+
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+const ISSUES_PER_PAGE: i64 = 20;
+
+#[derive(serde::Deserialize)]
+pub struct HistoryParameters {
+    page: Option<u32>,
+}
+
+struct IssueSummary {
+    id: Uuid,
+    title: String,
+    status: String,
+    published_at: Option<DateTime<Utc>>,
+    n_recipients: i64,
+    n_remaining: i64,
+}
+
+#[tracing::instrument(name = "List past newsletter issues", skip(query, pool))]
+pub async fn newsletter_history(
+    query: web::Query<HistoryParameters>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let page = query.page.unwrap_or(1).max(1);
+
+    // One extra row tells us whether there is a next page.
+    let mut issues = get_issues(&pool, page)
+        .await
+        .context("Failed to retrieve past newsletter issues")?;
+    let has_next_page = issues.len() as i64 > ISSUES_PER_PAGE;
+    issues.truncate(ISSUES_PER_PAGE as usize);
+
+    let mut rows = String::new();
+    for issue in &issues {
+        writeln!(
+            rows,
+            r#"
+                    <tr>
+                        <td><a href="/admin/newsletters/{id}">{title}</a></td>
+                        <td>{status}</td>
+                        <td>{published_at}</td>
+                        <td>{n_recipients}</td>
+                        <td>{n_remaining}</td>
+                    </tr>"#,
+            id = issue.id,
+            title = escape_html(&issue.title),
+            status = issue.status,
+            published_at = issue.published_at.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            n_recipients = issue.n_recipients,
+            n_remaining = issue.n_remaining,
+        )
+        .unwrap();
+    }
+
+    let mut pagination = String::new();
+    if page > 1 {
+        write!(pagination, r#"<a href="/admin/newsletters/history?page={}">Newer</a> "#, page - 1).unwrap();
+    }
+    if has_next_page {
+        write!(pagination, r#"<a href="/admin/newsletters/history?page={}">Older</a>"#, page + 1).unwrap();
+    }
+
+    let empty_note = if issues.is_empty() {
+        "<p>No newsletter issues have been written yet.</p>"
+    } else {
+        ""
+    };
+
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Past newsletter issues</title>
+        </head>
+        <body>
+            <a href="/admin/newsletters">New issue</a>
+
+            <table>
+                <thead>
+                    <tr>
+                        <th>Title</th>
+                        <th>Status</th>
+                        <th>Published at</th>
+                        <th>Recipients</th>
+                        <th>Remaining</th>
+                    </tr>
+                </thead>
+                <tbody>{rows}
+                </tbody>
+            </table>
+            {empty_note}
+            <p>{pagination}</p>
+
+            <a href="/admin/dashboard">&lt;- Back</a>
+        </body>
+        </html>
+        "#
+    );
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(body)
+    )
+}
+
+/// Recipients are the tasks still in the queue plus the ones with a recorded outcome.
+#[tracing::instrument(skip(pool))]
+async fn get_issues(pool: &PgPool, page: u32) -> Result<Vec<IssueSummary>, sqlx::Error> {
+    sqlx::query_as!(
+        IssueSummary,
+        r#"
+        SELECT newsletter_issues.id,
+            newsletter_issues.title,
+            newsletter_issues.status,
+            newsletter_issues.published_at,
+            (
+                SELECT COUNT(*) FROM issue_delivery_queue
+                WHERE newsletter_issue_id = newsletter_issues.id
+            ) + (
+                SELECT COUNT(*) FROM newsletter_deliveries
+                WHERE newsletter_issue_id = newsletter_issues.id
+            ) as "n_recipients!",
+            (
+                SELECT COUNT(*) FROM issue_delivery_queue
+                WHERE newsletter_issue_id = newsletter_issues.id
+            ) as "n_remaining!"
+        FROM newsletter_issues
+        ORDER BY COALESCE(newsletter_issues.published_at, newsletter_issues.created_at) DESC,
+            newsletter_issues.id
+        LIMIT $1 OFFSET $2
+        "#,
+        ISSUES_PER_PAGE + 1,
+        (i64::from(page) - 1) * ISSUES_PER_PAGE,
+    )
+    .fetch_all(pool)
+    .await
+}
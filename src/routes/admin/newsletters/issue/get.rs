@@ -0,0 +1,388 @@
+use crate::routes::admin::newsletters::helpers::get_newsletter_issue;
+use crate::routes::helpers::ApiError;
+use crate::utils::escape_html;
+
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+const MAX_RECIPIENTS_SHOWN: i64 = 100;
+
+#[derive(serde::Deserialize)]
+pub struct SearchParameters {
+    search: Option<String>,
+}
+
+struct DeliveryProgress {
+    queued: i64,
+    sent: i64,
+    failed: i64,
+    skipped: i64,
+}
+
+struct OpenStats {
+    n_readers: i64,
+    n_opens: i64,
+}
+
+struct LinkClicks {
+    url: String,
+    n_clickers: i64,
+    n_clicks: i64,
+}
+
+struct Recipient {
+    subscriber_email: String,
+    status: String,
+    provider_message_id: Option<String>,
+    n_attempts: i16,
+    updated_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Show the progress of a newsletter issue", skip(query, pool, flash_messages))]
+pub async fn newsletter_issue_progress(
+    newsletter_issue_id: web::Path<Uuid>,
+    query: web::Query<SearchParameters>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, ApiError> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let search = query
+        .0
+        .search
+        .map(|s| s.trim().to_owned())
+        .filter(|s| !s.is_empty());
+
+    let issue = get_newsletter_issue(&pool, newsletter_issue_id)
+        .await
+        .context("Failed to retrieve the newsletter issue")?
+        .ok_or(ApiError::NotFoundError)?;
+    let progress = get_delivery_progress(&pool, newsletter_issue_id)
+        .await
+        .context("Failed to retrieve the delivery progress")?;
+    let opens = if issue.track_opens {
+        let stats = get_open_stats(&pool, newsletter_issue_id)
+            .await
+            .context("Failed to retrieve the opens")?;
+        format!("<li>Opened by: {} subscribers ({} opens)</li>", stats.n_readers, stats.n_opens)
+    } else {
+        "<li>Opens are not tracked.</li>".to_string()
+    };
+    let link_clicks = get_link_clicks(&pool, newsletter_issue_id)
+        .await
+        .context("Failed to retrieve the clicks")?;
+    let recipients = get_recipients(&pool, newsletter_issue_id, search.as_deref())
+        .await
+        .context("Failed to retrieve the recipients")?;
+
+    let mut flash_msg = String::new();
+    for message in flash_messages.iter() {
+        writeln!(flash_msg, "<p><i>{}</i></p>", message.content()).unwrap()
+    }
+
+    let publication = match issue.published_at {
+        Some(published_at) if issue.status == "scheduled" => {
+            format!("<p>Scheduled for {}</p>", published_at.to_rfc3339())
+        },
+        Some(published_at) => format!("<p>Published at {}</p>", published_at.to_rfc3339()),
+        None => format!(
+            r#"<p>Not published yet: <a href="/admin/newsletters/{}/preview">preview and publish</a></p>"#,
+            newsletter_issue_id,
+        ),
+    };
+
+    let cancel_button = format!(
+        r#"
+            <form action="/admin/newsletters/{newsletter_issue_id}/cancel" method="post">
+                <button type="submit">Cancel the remaining deliveries</button>
+            </form>"#
+    );
+    let controls = match issue.status.as_str() {
+        "scheduled" | "sending" => format!(
+            r#"
+            <form action="/admin/newsletters/{newsletter_issue_id}/pause" method="post">
+                <button type="submit">Pause</button>
+            </form>{cancel_button}"#
+        ),
+        "paused" => format!(
+            r#"
+            <form action="/admin/newsletters/{newsletter_issue_id}/resume" method="post">
+                <button type="submit">Resume</button>
+            </form>{cancel_button}"#
+        ),
+        "cancelled" => format!(
+            "<p>Cancelled after {} recipients had been sent the issue.</p>",
+            issue.n_sent_before_cancel.unwrap_or_default(),
+        ),
+        _ => String::new(),
+    };
+
+    let archive_link = match &issue.slug {
+        Some(slug) if !issue.hidden_from_archive => format!(r#"<p>In the archive: <a href="/archive/{slug}">/archive/{slug}</a></p>"#),
+        _ => String::new(),
+    };
+    let archive = if issue.hidden_from_archive {
+        format!(
+            r#"
+            <p>Hidden from the archive.</p>
+            <form action="/admin/newsletters/{newsletter_issue_id}/archive" method="post">
+                <input hidden type="text" name="hidden_from_archive" value="false">
+                <button type="submit">Show in the archive</button>
+            </form>"#
+        )
+    } else {
+        format!(
+            r#"{archive_link}
+            <form action="/admin/newsletters/{newsletter_issue_id}/archive" method="post">
+                <input hidden type="text" name="hidden_from_archive" value="true">
+                <button type="submit">Hide from the archive</button>
+            </form>"#
+        )
+    };
+
+    let mut rows = String::new();
+    for recipient in &recipients {
+        writeln!(
+            rows,
+            r#"
+                    <tr>
+                        <td>{subscriber_email}</td>
+                        <td>{status}</td>
+                        <td>{n_attempts}</td>
+                        <td>{provider_message_id}</td>
+                        <td>{updated_at}</td>
+                    </tr>"#,
+            subscriber_email = escape_html(&recipient.subscriber_email),
+            status = recipient.status,
+            n_attempts = recipient.n_attempts,
+            provider_message_id = escape_html(recipient.provider_message_id.as_deref().unwrap_or("")),
+            updated_at = recipient.updated_at.to_rfc3339(),
+        )
+        .unwrap();
+    }
+
+    let mut clicks = String::new();
+    for link in &link_clicks {
+        writeln!(
+            clicks,
+            r#"
+                    <tr>
+                        <td>{url}</td>
+                        <td>{n_clickers}</td>
+                        <td>{n_clicks}</td>
+                    </tr>"#,
+            url = escape_html(&link.url),
+            n_clickers = link.n_clickers,
+            n_clicks = link.n_clicks,
+        )
+        .unwrap();
+    }
+    let clicks = if link_clicks.is_empty() {
+        "<p>No tracked links.</p>".to_string()
+    } else {
+        format!(
+            r#"
+            <table>
+                <thead>
+                    <tr>
+                        <th>Link</th>
+                        <th>Subscribers</th>
+                        <th>Clicks</th>
+                    </tr>
+                </thead>
+                <tbody>{clicks}
+                </tbody>
+            </table>"#
+        )
+    };
+
+    let recipients_note = if recipients.is_empty() {
+        "<p>No recipients found.</p>".to_string()
+    } else if recipients.len() as i64 == MAX_RECIPIENTS_SHOWN {
+        format!("<p>Showing the first {} recipients, narrow the search to see the others.</p>", MAX_RECIPIENTS_SHOWN)
+    } else {
+        String::new()
+    };
+
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>{title}</title>
+        </head>
+        <body>
+            {flash_msg}
+
+            <h1>{title}</h1>
+            <p>Status: {status}</p>
+            {publication}
+            {controls}
+            {archive}
+
+            <h2>Progress</h2>
+            <ul>
+                <li>Queued: {queued}</li>
+                <li>Sent: {sent}</li>
+                <li>Failed: {failed}</li>
+                <li>Skipped: {skipped}</li>
+                {opens}
+            </ul>
+
+            <h2>Clicks</h2>
+            {clicks}
+
+            <h2>Content</h2>
+            <iframe sandbox srcdoc="{html_content}" title="HTML content"></iframe>
+            <pre>{text_content}</pre>
+
+            <h2>Recipients</h2>
+            <form action="/admin/newsletters/{newsletter_issue_id}" method="get">
+                <input type="search" name="search" value="{search}" placeholder="Email address">
+                <button type="submit">Search</button>
+            </form>
+
+            <table>
+                <thead>
+                    <tr>
+                        <th>Subscriber</th>
+                        <th>Status</th>
+                        <th>Attempts</th>
+                        <th>Provider message id</th>
+                        <th>Updated at</th>
+                    </tr>
+                </thead>
+                <tbody>{rows}
+                </tbody>
+            </table>
+            {recipients_note}
+
+            <a href="/admin/newsletters/history">&lt;- Back</a>
+        </body>
+        </html>
+        "#,
+        title = escape_html(&issue.title),
+        status = issue.status,
+        queued = progress.queued,
+        sent = progress.sent,
+        failed = progress.failed,
+        skipped = progress.skipped,
+        html_content = escape_html(&issue.html_content),
+        text_content = escape_html(&issue.text_content),
+        search = escape_html(search.as_deref().unwrap_or("")),
+    );
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(body)
+    )
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_delivery_progress(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+) -> Result<DeliveryProgress, sqlx::Error> {
+    sqlx::query_as!(
+        DeliveryProgress,
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM issue_delivery_queue WHERE newsletter_issue_id = $1) as "queued!",
+            COUNT(*) FILTER (WHERE status = 'sent') as "sent!",
+            COUNT(*) FILTER (WHERE status = 'failed') as "failed!",
+            COUNT(*) FILTER (WHERE status = 'skipped') as "skipped!"
+        FROM newsletter_deliveries
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_open_stats(pool: &PgPool, newsletter_issue_id: Uuid) -> Result<OpenStats, sqlx::Error> {
+    sqlx::query_as!(
+        OpenStats,
+        r#"
+        SELECT COUNT(*) as "n_readers!", COALESCE(SUM(n_opens), 0) as "n_opens!"
+        FROM newsletter_opens
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Every link registered on publication, clicked or not.
+#[tracing::instrument(skip(pool))]
+async fn get_link_clicks(pool: &PgPool, newsletter_issue_id: Uuid) -> Result<Vec<LinkClicks>, sqlx::Error> {
+    sqlx::query_as!(
+        LinkClicks,
+        r#"
+        SELECT newsletter_links.url,
+            COUNT(DISTINCT newsletter_clicks.subscriber_id) as "n_clickers!",
+            COUNT(newsletter_clicks.subscriber_id) as "n_clicks!"
+        FROM newsletter_links
+        LEFT JOIN newsletter_clicks USING (newsletter_issue_id, position)
+        WHERE newsletter_links.newsletter_issue_id = $1
+        GROUP BY newsletter_links.position, newsletter_links.url
+        ORDER BY newsletter_links.position
+        "#,
+        newsletter_issue_id,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Recipients still in the queue show up as `queued`, the others with the outcome
+/// recorded in `newsletter_deliveries`.
+#[tracing::instrument(skip(pool))]
+async fn get_recipients(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+    search: Option<&str>,
+) -> Result<Vec<Recipient>, sqlx::Error> {
+    sqlx::query_as!(
+        Recipient,
+        r#"
+        SELECT subscriber_email as "subscriber_email!",
+            status as "status!",
+            provider_message_id,
+            n_attempts as "n_attempts!",
+            updated_at as "updated_at!"
+        FROM (
+            SELECT subscriber_email,
+                'queued' as status,
+                NULL::text as provider_message_id,
+                n_attempts,
+                enqueued_at as updated_at
+            FROM issue_delivery_queue
+            WHERE newsletter_issue_id = $1
+            UNION ALL
+            SELECT subscriber_email,
+                status,
+                provider_message_id,
+                n_attempts,
+                delivered_at
+            FROM newsletter_deliveries
+            WHERE newsletter_issue_id = $1
+        ) AS recipients
+        WHERE $2::text IS NULL OR strpos(lower(subscriber_email), lower($2)) > 0
+        ORDER BY subscriber_email
+        LIMIT $3
+        "#,
+        newsletter_issue_id,
+        search,
+        MAX_RECIPIENTS_SHOWN,
+    )
+    .fetch_all(pool)
+    .await
+}
@@ -1,5 +1,24 @@
+mod archive;
+mod controls;
+mod edit;
+mod failures;
 mod get;
+mod helpers;
+mod history;
+mod issue;
 mod post;
+mod preview;
+mod publish;
+mod send_test;
 
+pub use archive::*;
+pub use controls::*;
+pub use edit::*;
+pub use failures::*;
 pub use get::submit_newsletter_form;
-pub use post::publish_newsletter;
+pub use history::*;
+pub use issue::*;
+pub use post::create_newsletter_draft;
+pub use preview::*;
+pub use publish::*;
+pub use send_test::*;
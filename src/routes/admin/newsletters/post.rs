@@ -1,5 +1,5 @@
-use crate::authentication::middleware::CurrentUserId;
-use crate::idempotency::{IdempotencyKey, save_response, try_processing, NextAction};
+use crate::markdown::NewsletterLayout;
+use crate::routes::admin::newsletters::helpers::{parse_newsletter_content, send_removed_html_flash, NewsletterContent};
 use crate::routes::helpers::ApiError;
 use crate::utils::see_other;
 
@@ -7,73 +7,51 @@ use actix_web::{web, HttpResponse};
 use actix_web_flash_messages::FlashMessage;
 use anyhow::Context;
 use sqlx::PgPool;
-use sqlx::{Postgres, Transaction};
 use uuid::Uuid;
 
 #[derive(serde::Deserialize, Debug)]
 pub struct FormData {
     title: String,
+    #[serde(default)]
+    markdown_content: String,
+    #[serde(default)]
     text_content: String,
+    #[serde(default)]
     html_content: String,
-    idempotency_key: String,
-    n_retries: Option<String>,
-    execute_after_in_secs: Option<String>,
+    #[serde(default)]
+    track_opens: bool,
 }
 
-#[tracing::instrument(
-    name = "Publish a newsletter issue"
-    skip(form, pool),
-    fields(username=tracing::field::Empty, user_is=tracing::field::Empty)
-)]
-pub async fn publish_newsletter(
+#[tracing::instrument(name = "Create a newsletter draft", skip(form, pool, layout))]
+pub async fn create_newsletter_draft(
     form: web::Form<FormData>,
     pool: web::Data<PgPool>,
-    user_id: web::ReqData<CurrentUserId>,
+    layout: web::Data<NewsletterLayout>,
 ) -> Result<HttpResponse, ApiError> {
-    // TODO: we can create validation function for incoming data
-    let FormData { title, text_content, html_content, idempotency_key, n_retries, execute_after_in_secs } = form.0;
+    let FormData { title, markdown_content, text_content, html_content, track_opens } = form.0;
 
-    let n_retries = n_retries.and_then(|s| s.parse::<u8>().ok());
-    let execute_after_in_secs = execute_after_in_secs.and_then(|s| s.parse::<u32>().ok());
+    if title.trim().is_empty() {
+        return Err(ApiError::ValidationError("The title cannot be empty.".into()));
+    }
+    let content = parse_newsletter_content(markdown_content, text_content, html_content, &layout)
+        .map_err(ApiError::ValidationError)?;
 
-    let idempotency_key: Result<IdempotencyKey, anyhow::Error> = idempotency_key.try_into();
-    let idempotency_key = idempotency_key.map_err(|e| ApiError::ValidationError(e.to_string()))?;
-
-    let mut transaction = match try_processing(&pool, &idempotency_key, user_id.0)
+    let issue_id = insert_newsletter_draft(&pool, &title, &content, track_opens)
         .await
-        .map_err(|e| ApiError::ValidationError(e.to_string()))? {
-
-        NextAction::StartProcessing(t) => t,
-        NextAction::ReturnSavedResponse(saved_response) => {
-            success_message().send();
-            println!("before returning saved response");
-            return Ok(saved_response);
-        }
-    };
+        .context("Failed to store newsletter issue details")?;
 
-    let issue_id = insert_newsletter_issue(&mut transaction, &title, &text_content, &html_content)
-        .await
-        .context("Failed to store newsletter issue details")
-        .map_err(ApiError::UnexpectedError)?;
-
-    enqueue_delivery_tasks(&mut transaction, issue_id, n_retries, execute_after_in_secs)
-        .await
-        .context("Failed to enqueue delivery tasks")
-        .map_err(ApiError::UnexpectedError)?;
-        
-    success_message().send();
+    FlashMessage::info("The draft has been saved.").send();
+    send_removed_html_flash(&content.removed_html);
 
-    let response = see_other("/admin/newsletters");
-    let response = save_response(transaction, &idempotency_key, user_id.0, response).await?;
-    Ok(response)
+    Ok(see_other(&format!("/admin/newsletters/{}/preview", issue_id)))
 }
 
 #[tracing::instrument(skip_all)]
-async fn insert_newsletter_issue(
-    transaction: &mut Transaction<'_, Postgres>,
+async fn insert_newsletter_draft(
+    pool: &PgPool,
     title: &str,
-    text_content: &str,
-    html_content: &str,
+    content: &NewsletterContent,
+    track_opens: bool,
 ) -> Result<Uuid, sqlx::Error> {
     let id = Uuid::new_v4();
     sqlx::query!(
@@ -83,53 +61,21 @@ async fn insert_newsletter_issue(
             title,
             text_content,
             html_content,
-            published_at
+            markdown_content,
+            track_opens,
+            status
         )
-        VALUES ($1, $2, $3, $4, now())
+        VALUES ($1, $2, $3, $4, $5, $6, 'draft')
         "#,
         id,
         title,
-        text_content,
-        html_content
+        content.text_content.as_ref(),
+        content.html_content.as_ref(),
+        content.markdown_content,
+        track_opens,
     )
-    .execute(transaction)
+    .execute(pool)
     .await?;
 
     Ok(id)
 }
-
-#[tracing::instrument(skip_all)]
-async fn enqueue_delivery_tasks(
-    transaction: &mut Transaction<'_, Postgres>,
-    newsletter_issue_id: Uuid,
-    n_retries: Option<u8>,
-    execute_after_in_secs: Option<u32>
-) -> Result<(), sqlx::Error> {
-    let n_retries: i16 = n_retries.and_then(|num| Some(num as i16)).unwrap_or(20);
-    let execute_after_in_secs: Option<i32> = execute_after_in_secs.and_then(|num| Some(num as i32));
-
-    sqlx::query!(
-        r#"
-        INSERT INTO issue_delivery_queue (
-            newsletter_issue_id,
-            subscriber_email,
-            n_retries,
-            execute_after_in_secs
-        )
-        SELECT $1, email, $2, $3
-        FROM subscriptions
-        WHERE status = 'confirmed'
-        "#,
-        newsletter_issue_id,
-        n_retries,
-        execute_after_in_secs,
-    )
-    .execute(transaction)
-    .await?;
-
-    Ok(())
-}
-
-fn success_message() -> FlashMessage {
-    FlashMessage::info("The newsletter issue has been accepted - emails will go out shortly.")
-}
@@ -0,0 +1,120 @@
+use crate::routes::admin::newsletters::helpers::get_newsletter_issue;
+use crate::routes::helpers::ApiError;
+use crate::utils::escape_html;
+
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use anyhow::Context;
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+#[tracing::instrument(name = "Preview a newsletter issue", skip(pool, flash_messages))]
+pub async fn preview_newsletter(
+    newsletter_issue_id: web::Path<Uuid>,
+    pool: web::Data<PgPool>,
+    flash_messages: IncomingFlashMessages,
+) -> Result<HttpResponse, ApiError> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let issue = get_newsletter_issue(&pool, newsletter_issue_id)
+        .await
+        .context("Failed to retrieve the newsletter issue")?
+        .ok_or(ApiError::NotFoundError)?;
+
+    let mut flash_msg = String::new();
+    for message in flash_messages.iter() {
+        writeln!(flash_msg, "<p><i>{}</i></p>", message.content()).unwrap()
+    }
+
+    let actions = if issue.is_draft() {
+        format!(
+            r#"
+            <a href="/admin/newsletters/{newsletter_issue_id}/edit">Edit</a>
+
+            <form action="/admin/newsletters/{newsletter_issue_id}/publish" method="post">
+                <input hidden type="text" name="idempotency_key" value="{idempotency_key}">
+
+                <p>Retry settings (Optional)</p>
+                <label>
+                    N-retries
+                    <input type="number" name="n_retries" placeholder="20">
+                </label>
+
+                <p>Retries back off exponentially, with jitter, up to the maximum delay.</p>
+                <label>
+                    Initial retry delay in seconds
+                    <input type="number" name="retry_base_delay_in_secs" placeholder="30">
+                </label>
+
+                <label>
+                    Maximum retry delay in seconds
+                    <input type="number" name="retry_max_delay_in_secs" placeholder="3600">
+                </label>
+
+                <p>Schedule (Optional, leave empty to send right away)</p>
+                <label>
+                    Send at
+                    <input type="datetime-local" name="send_at">
+                </label>
+
+                <label>
+                    Time zone
+                    <input type="text" name="timezone" placeholder="Europe/Kyiv" value="UTC">
+                </label>
+
+                <button type="submit">Publish</button>
+            </form>"#,
+            idempotency_key = Uuid::new_v4(),
+        )
+    } else {
+        format!(
+            r#"
+            <p>This issue has already been published.</p>
+            <a href="/admin/newsletters/{newsletter_issue_id}">See the delivery progress</a>"#
+        )
+    };
+
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Preview: {title}</title>
+        </head>
+        <body>
+            {flash_msg}
+
+            <h1>{title}</h1>
+
+            <h2>HTML</h2>
+            <iframe sandbox srcdoc="{html_content}" title="HTML content" width="800" height="600"></iframe>
+
+            <h2>Plain text</h2>
+            <pre>{text_content}</pre>
+
+            <form action="/admin/newsletters/{newsletter_issue_id}/test" method="post">
+                <label>
+                    Send a test email to
+                    <input type="text" name="recipients" placeholder="editor@example.com, other@example.com">
+                </label>
+
+                <button type="submit">Send test</button>
+            </form>
+            {actions}
+
+            <a href="/admin/newsletters/history">&lt;- Back</a>
+        </body>
+        </html>
+        "#,
+        title = escape_html(&issue.title),
+        html_content = escape_html(&issue.html_content),
+        text_content = escape_html(&issue.text_content),
+    );
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(body)
+    )
+}
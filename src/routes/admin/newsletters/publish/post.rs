@@ -0,0 +1,346 @@
+use crate::authentication::middleware::CurrentUserId;
+use crate::domain::{BackoffPolicy, IssueSlug, NewsletterHtml};
+use crate::idempotency::{IdempotencyKey, save_response, try_processing, NextAction};
+use crate::issue_delivery_worker::{enqueue_delivery_tasks, notify_new_tasks};
+use crate::routes::admin::newsletters::helpers::send_removed_html_flash;
+use crate::routes::helpers::ApiError;
+use crate::tracked_links::trackable_links;
+use crate::utils::see_other;
+
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use chrono::{DateTime, LocalResult, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use sqlx::PgPool;
+use sqlx::{Connection, Postgres, Transaction};
+use uuid::Uuid;
+
+/// Send times this close to now are sent right away rather than rejected.
+const SEND_AT_GRACE_PERIOD_IN_MINUTES: i64 = 5;
+
+#[derive(serde::Deserialize, Debug)]
+pub struct FormData {
+    idempotency_key: String,
+    n_retries: Option<String>,
+    retry_base_delay_in_secs: Option<String>,
+    retry_max_delay_in_secs: Option<String>,
+    send_at: Option<String>,
+    timezone: Option<String>,
+}
+
+#[tracing::instrument(
+    name = "Publish a newsletter issue"
+    skip(form, pool),
+    fields(username=tracing::field::Empty, user_is=tracing::field::Empty)
+)]
+pub async fn publish_newsletter(
+    newsletter_issue_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<CurrentUserId>,
+) -> Result<HttpResponse, ApiError> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let FormData {
+        idempotency_key,
+        n_retries,
+        retry_base_delay_in_secs,
+        retry_max_delay_in_secs,
+        send_at,
+        timezone,
+    } = form.0;
+
+    let n_retries = n_retries.and_then(|s| s.parse::<u8>().ok());
+    let backoff_policy = parse_backoff_policy(retry_base_delay_in_secs, retry_max_delay_in_secs)
+        .map_err(ApiError::ValidationError)?;
+    let send_at = parse_send_at(send_at, timezone).map_err(ApiError::ValidationError)?;
+    // Most likely a typo in the date: sending to everyone right away is not what was asked for.
+    if let Some(send_at) = send_at.filter(|send_at| *send_at < Utc::now() - chrono::Duration::minutes(SEND_AT_GRACE_PERIOD_IN_MINUTES)) {
+        FlashMessage::error(format!("The send time {} is in the past.", send_at.to_rfc3339())).send();
+        return Ok(see_other(&format!("/admin/newsletters/{}", newsletter_issue_id)));
+    }
+    let send_at = send_at.filter(|send_at| *send_at > Utc::now());
+
+    let idempotency_key: Result<IdempotencyKey, anyhow::Error> = idempotency_key.try_into();
+    let idempotency_key = idempotency_key.map_err(|e| ApiError::ValidationError(e.to_string()))?;
+
+    let mut transaction = match try_processing(&pool, &idempotency_key, user_id.0)
+        .await
+        .map_err(|e| ApiError::ValidationError(e.to_string()))? {
+
+        NextAction::StartProcessing(t) => t,
+        NextAction::ReturnSavedResponse(saved_response) => {
+            success_message().send();
+            return Ok(saved_response);
+        }
+    };
+
+    let issue = lock_newsletter_issue(&mut transaction, newsletter_issue_id)
+        .await
+        .context("Failed to retrieve the newsletter issue")?
+        .ok_or(ApiError::NotFoundError)?;
+
+    let response = see_other(&format!("/admin/newsletters/{}", newsletter_issue_id));
+
+    if issue.status != "draft" {
+        FlashMessage::error("The newsletter issue has already been published.").send();
+        let response = save_response(transaction, &idempotency_key, user_id.0, response).await?;
+        return Ok(response);
+    }
+
+    // Drafts are sanitized on save, this catches the ones saved before that was the case.
+    // Markdown issues are full documents generated from escaped Markdown.
+    let mut html_content = issue.html_content;
+    if issue.markdown_content.is_none() {
+        let (sanitized_html, removed_html) = NewsletterHtml::sanitize(&html_content);
+        if !removed_html.is_empty() {
+            update_html_content(&mut transaction, newsletter_issue_id, &sanitized_html)
+                .await
+                .context("Failed to update the HTML content")?;
+            send_removed_html_flash(&removed_html);
+            html_content = sanitized_html.as_ref().to_string();
+        }
+    }
+
+    register_links(&mut transaction, newsletter_issue_id, &trackable_links(&html_content))
+        .await
+        .context("Failed to register the links of the newsletter issue")?;
+
+    save_retry_settings(&mut transaction, newsletter_issue_id, n_retries, backoff_policy)
+        .await
+        .context("Failed to save the retry settings")?;
+
+    let status = if send_at.is_some() { "scheduled" } else { "sending" };
+    let slug = IssueSlug::from_title(&issue.title);
+    mark_newsletter_issue_as_published(&mut transaction, newsletter_issue_id, status, send_at, &slug)
+        .await
+        .context("Failed to publish the newsletter issue")?;
+
+    // Scheduled issues pick their recipients when they are due, see `enqueue_due_issues`.
+    match send_at {
+        Some(send_at) => {
+            FlashMessage::info(format!("The newsletter issue has been scheduled for {}.", send_at.to_rfc3339())).send();
+        },
+        None => {
+            enqueue_delivery_tasks(&mut transaction, newsletter_issue_id)
+                .await
+                .context("Failed to enqueue delivery tasks")?;
+            success_message().send();
+        },
+    }
+    notify_new_tasks(&mut transaction)
+        .await
+        .context("Failed to notify the delivery workers")?;
+
+    let response = save_response(transaction, &idempotency_key, user_id.0, response).await?;
+    Ok(response)
+}
+
+struct LockedIssue {
+    title: String,
+    status: String,
+    html_content: String,
+    markdown_content: Option<String>,
+}
+
+/// Locks the issue until the transaction ends, so that concurrent publish requests
+/// with different idempotency keys can't both enqueue the deliveries.
+async fn lock_newsletter_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<Option<LockedIssue>, sqlx::Error> {
+    sqlx::query_as!(
+        LockedIssue,
+        r#"SELECT title, status, html_content, markdown_content FROM newsletter_issues WHERE id = $1 FOR UPDATE"#,
+        newsletter_issue_id,
+    )
+    .fetch_optional(transaction)
+    .await
+}
+
+#[tracing::instrument(skip(transaction, html_content))]
+async fn update_html_content(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    html_content: &NewsletterHtml,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE newsletter_issues SET html_content = $2 WHERE id = $1"#,
+        newsletter_issue_id,
+        html_content.as_ref(),
+    )
+    .execute(transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// The links click tracking redirects to, by their position in the issue.
+#[tracing::instrument(skip(transaction, links))]
+async fn register_links(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    links: &[String],
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_links (newsletter_issue_id, position, url)
+        SELECT $1, position::integer - 1, url
+        FROM unnest($2::text[]) WITH ORDINALITY AS links (url, position)
+        "#,
+        newsletter_issue_id,
+        links,
+    )
+    .execute(transaction)
+    .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(skip(transaction))]
+async fn mark_newsletter_issue_as_published(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    status: &str,
+    send_at: Option<DateTime<Utc>>,
+    slug: &IssueSlug,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = $2,
+            published_at = COALESCE($3, now())
+        WHERE id = $1
+        "#,
+        newsletter_issue_id,
+        status,
+        send_at,
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    // Issues with the same title tell their slugs apart with the start of their id.
+    // Checking for an existing slug first would race with a concurrent publish: the
+    // unique constraint decides, and the savepoint keeps the transaction usable.
+    let mut savepoint = transaction.begin().await?;
+    match set_slug(&mut savepoint, newsletter_issue_id, slug.as_ref()).await {
+        Ok(()) => savepoint.commit().await,
+        Err(e) if is_unique_violation(&e) => {
+            savepoint.rollback().await?;
+            let slug = format!("{}-{}", slug.as_ref(), &newsletter_issue_id.to_string()[..8]);
+            set_slug(transaction, newsletter_issue_id, &slug).await
+        },
+        Err(e) => Err(e),
+    }
+}
+
+async fn set_slug(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    slug: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE newsletter_issues SET slug = $2 WHERE id = $1"#,
+        newsletter_issue_id,
+        slug,
+    )
+    .execute(transaction)
+    .await?;
+
+    Ok(())
+}
+
+fn is_unique_violation(e: &sqlx::Error) -> bool {
+    e.as_database_error().and_then(|e| e.code()).as_deref() == Some("23505")
+}
+
+/// Kept on the issue until its deliveries are enqueued.
+#[tracing::instrument(skip_all)]
+async fn save_retry_settings(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    n_retries: Option<u8>,
+    backoff_policy: BackoffPolicy,
+) -> Result<(), anyhow::Error> {
+    let n_retries: i16 = n_retries.map(|num| num as i16).unwrap_or(20);
+    let retry_base_delay_in_secs = i32::try_from(backoff_policy.base_delay_in_secs())?;
+    let retry_max_delay_in_secs = i32::try_from(backoff_policy.max_delay_in_secs())?;
+
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET n_retries = $2,
+            retry_base_delay_in_secs = $3,
+            retry_max_delay_in_secs = $4
+        WHERE id = $1
+        "#,
+        newsletter_issue_id,
+        n_retries,
+        retry_base_delay_in_secs,
+        retry_max_delay_in_secs,
+    )
+    .execute(transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// Empty fields fall back to the defaults of `BackoffPolicy`.
+fn parse_backoff_policy(
+    base_delay_in_secs: Option<String>,
+    max_delay_in_secs: Option<String>,
+) -> Result<BackoffPolicy, String> {
+    let parse_delay = |field: Option<String>, name: &str| -> Result<Option<u32>, String> {
+        match field.as_deref().map(str::trim) {
+            None | Some("") => Ok(None),
+            Some(value) => value
+                .parse::<u32>()
+                .map(Some)
+                .map_err(|_| format!("{} must be a number of seconds.", name)),
+        }
+    };
+
+    let base_delay_in_secs = parse_delay(base_delay_in_secs, "The initial retry delay")?;
+    let max_delay_in_secs = parse_delay(max_delay_in_secs, "The maximum retry delay")?;
+
+    let default_policy = BackoffPolicy::default();
+    let base_delay_in_secs = base_delay_in_secs.unwrap_or(default_policy.base_delay_in_secs());
+    let max_delay_in_secs = max_delay_in_secs.unwrap_or(default_policy.max_delay_in_secs().max(base_delay_in_secs));
+
+    BackoffPolicy::parse(base_delay_in_secs, max_delay_in_secs)
+}
+
+/// `send_at` comes from a `datetime-local` input, which has no offset: it is read
+/// as a wall-clock time in `timezone` (an IANA name such as `Europe/Kyiv`, UTC if empty).
+/// An empty `send_at` means "right away".
+fn parse_send_at(
+    send_at: Option<String>,
+    timezone: Option<String>,
+) -> Result<Option<DateTime<Utc>>, String> {
+    let send_at = match send_at.as_deref().map(str::trim) {
+        None | Some("") => return Ok(None),
+        Some(send_at) => send_at,
+    };
+
+    let timezone: Tz = match timezone.as_deref().map(str::trim) {
+        None | Some("") => Tz::UTC,
+        Some(timezone) => timezone
+            .parse()
+            .map_err(|_| format!("{} is not a known time zone.", timezone))?,
+    };
+
+    let send_at = NaiveDateTime::parse_from_str(send_at, "%Y-%m-%dT%H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(send_at, "%Y-%m-%dT%H:%M:%S"))
+        .map_err(|_| "The send time must look like 2023-07-31T09:00.".to_string())?;
+
+    match timezone.from_local_datetime(&send_at) {
+        LocalResult::Single(send_at) => Ok(Some(send_at.with_timezone(&Utc))),
+        // The clocks went back: pick the first of the two moments.
+        LocalResult::Ambiguous(send_at, _) => Ok(Some(send_at.with_timezone(&Utc))),
+        LocalResult::None => Err(format!("{} does not exist in {}.", send_at, timezone)),
+    }
+}
+
+fn success_message() -> FlashMessage {
+    FlashMessage::info("The newsletter issue has been accepted - emails will go out shortly.")
+}
@@ -0,0 +1,3 @@
+mod post;
+
+pub use post::send_test_newsletter;
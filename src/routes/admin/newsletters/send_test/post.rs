@@ -0,0 +1,105 @@
+use crate::domain::{NewsletterTemplate, SubscriberEmail, TemplateValues};
+use crate::email_client::EmailTransport;
+use crate::routes::admin::newsletters::helpers::get_newsletter_issue;
+use crate::routes::helpers::ApiError;
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::see_other;
+
+use actix_web::{web, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Test sends go out one by one while the editor waits, keep them few.
+const MAX_TEST_RECIPIENTS: usize = 10;
+/// Test recipients are not subscribers, `{{ name }}` gets a stand-in.
+const TEST_SUBSCRIBER_NAME: &str = "Test Subscriber";
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    recipients: String,
+}
+
+/// Mails the issue to a handful of addresses, e.g. the editor's own inboxes,
+/// to check how it renders. Nothing goes through `issue_delivery_queue` and
+/// nothing is recorded in `newsletter_deliveries`. The unsubscribe link of test
+/// emails doesn't carry a token, it can't unsubscribe anyone.
+#[tracing::instrument(name = "Send a test newsletter issue", skip(form, pool, email_client, base_url))]
+pub async fn send_test_newsletter(
+    newsletter_issue_id: web::Path<Uuid>,
+    form: web::Form<FormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<dyn EmailTransport>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, ApiError> {
+    let newsletter_issue_id = newsletter_issue_id.into_inner();
+    let recipients = parse_recipients(&form.recipients).map_err(ApiError::ValidationError)?;
+
+    let issue = get_newsletter_issue(&pool, newsletter_issue_id)
+        .await
+        .context("Failed to retrieve the newsletter issue")?
+        .ok_or(ApiError::NotFoundError)?;
+
+    let subject = format!("[TEST] {}", issue.title);
+    let text_content = NewsletterTemplate::parse(issue.text_content).map_err(ApiError::ValidationError)?;
+    let html_content = NewsletterTemplate::parse(issue.html_content).map_err(ApiError::ValidationError)?;
+    let unsubscribe_url = format!("{}/subscriptions/unsubscribe", base_url.0);
+
+    let mut failed = Vec::new();
+    for recipient in &recipients {
+        let values = TemplateValues {
+            name: TEST_SUBSCRIBER_NAME,
+            email: recipient.as_ref(),
+            unsubscribe_url: &unsubscribe_url,
+        };
+
+        if let Err(e) = email_client
+            .send_email(
+                recipient,
+                &subject,
+                &html_content.render_html(&values),
+                &text_content.render_text(&values),
+                &[],
+            )
+            .await
+        {
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                recipient = %recipient,
+                "Failed to send a test email",
+            );
+            failed.push(recipient.as_ref());
+        }
+    }
+
+    if failed.is_empty() {
+        FlashMessage::info("The test email has been sent.").send();
+    } else {
+        FlashMessage::error(format!("The test email could not be sent to {}.", failed.join(", "))).send();
+    }
+
+    Ok(see_other(&format!("/admin/newsletters/{}/preview", newsletter_issue_id)))
+}
+
+/// Addresses can be separated by commas, spaces or new lines.
+fn parse_recipients(recipients: &str) -> Result<Vec<SubscriberEmail>, String> {
+    let recipients = recipients
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|recipient| !recipient.is_empty())
+        .map(|recipient| {
+            SubscriberEmail::parse(recipient.to_owned())
+                .map_err(|(_, email)| format!("{} is not a valid email address.", email))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if recipients.is_empty() {
+        return Err("At least one recipient is required.".into());
+    }
+    if recipients.len() > MAX_TEST_RECIPIENTS {
+        return Err(format!("Test emails can go to at most {} recipients.", MAX_TEST_RECIPIENTS));
+    }
+
+    Ok(recipients)
+}
@@ -0,0 +1,230 @@
+use crate::routes::archive::helpers::render_for_reader;
+use crate::routes::helpers::ApiError;
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::escape_html;
+
+use actix_web::http::header::{
+    CacheControl, CacheDirective, ETag, EntityTag, Header, HttpDate, IfModifiedSince, IfNoneMatch, LastModified,
+};
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, TimeZone, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::fmt::Write;
+use std::time::SystemTime;
+
+const FEED_SIZE: i64 = 20;
+const FEED_TITLE: &str = "Newsletter archive";
+const FEED_DESCRIPTION: &str = "Past issues of our newsletter";
+
+struct FeedIssue {
+    slug: String,
+    title: String,
+    html_content: String,
+    published_at: DateTime<Utc>,
+}
+
+struct Feed {
+    issues: Vec<FeedIssue>,
+    last_modified: Option<DateTime<Utc>>,
+}
+
+#[tracing::instrument(name = "Get the RSS feed", skip(request, pool, base_url))]
+pub async fn rss_feed(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, ApiError> {
+    let feed = get_feed(&pool).await?;
+    let base_url = &base_url.0;
+
+    let mut items = String::new();
+    for issue in feed.issues {
+        let link = format!("{}/archive/{}", base_url, issue.slug);
+        writeln!(
+            items,
+            r#"
+    <item>
+      <title>{title}</title>
+      <link>{link}</link>
+      <guid isPermaLink="true">{link}</guid>
+      <pubDate>{published_at}</pubDate>
+      <description>{description}</description>
+    </item>"#,
+            title = escape_html(&issue.title),
+            published_at = issue.published_at.to_rfc2822(),
+            description = escape_html(&render_for_reader(issue.html_content, base_url)
+                .context("Failed to render a newsletter issue of the feed")?),
+        )
+        .unwrap();
+    }
+
+    let last_build_date = feed
+        .last_modified
+        .map(|t| format!("\n    <lastBuildDate>{}</lastBuildDate>", t.to_rfc2822()))
+        .unwrap_or_default();
+
+    let body = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom">
+  <channel>
+    <title>{FEED_TITLE}</title>
+    <link>{base_url}/archive</link>
+    <description>{FEED_DESCRIPTION}</description>
+    <atom:link href="{base_url}/feed.rss" rel="self" type="application/rss+xml"/>{last_build_date}{items}
+  </channel>
+</rss>
+"#
+    );
+
+    Ok(feed_response(&request, "application/rss+xml; charset=utf-8", body, feed.last_modified))
+}
+
+#[tracing::instrument(name = "Get the Atom feed", skip(request, pool, base_url))]
+pub async fn atom_feed(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, ApiError> {
+    let feed = get_feed(&pool).await?;
+    let base_url = &base_url.0;
+
+    let mut entries = String::new();
+    for issue in feed.issues {
+        let link = format!("{}/archive/{}", base_url, issue.slug);
+        writeln!(
+            entries,
+            r#"
+  <entry>
+    <title>{title}</title>
+    <id>{link}</id>
+    <link href="{link}"/>
+    <published>{published_at}</published>
+    <updated>{published_at}</updated>
+    <content type="html">{content}</content>
+  </entry>"#,
+            title = escape_html(&issue.title),
+            published_at = issue.published_at.to_rfc3339(),
+            content = escape_html(&render_for_reader(issue.html_content, base_url)
+                .context("Failed to render a newsletter issue of the feed")?),
+        )
+        .unwrap();
+    }
+
+    // `updated` is required, an empty feed has never been updated.
+    let updated = feed.last_modified.unwrap_or_else(|| Utc.timestamp_opt(0, 0).unwrap());
+
+    let body = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>{FEED_TITLE}</title>
+  <subtitle>{FEED_DESCRIPTION}</subtitle>
+  <id>{base_url}/archive</id>
+  <link href="{base_url}/archive"/>
+  <link href="{base_url}/feed.atom" rel="self" type="application/atom+xml"/>
+  <updated>{updated}</updated>
+  <author>
+    <name>{FEED_TITLE}</name>
+  </author>{entries}
+</feed>
+"#,
+        updated = updated.to_rfc3339(),
+    );
+
+    Ok(feed_response(&request, "application/atom+xml; charset=utf-8", body, feed.last_modified))
+}
+
+async fn get_feed(pool: &PgPool) -> Result<Feed, ApiError> {
+    let issues = get_feed_issues(pool)
+        .await
+        .context("Failed to retrieve the newsletter issues of the feed")?;
+    let last_modified = get_last_modified(pool)
+        .await
+        .context("Failed to retrieve when the feed last changed")?;
+
+    Ok(Feed { issues, last_modified })
+}
+
+/// Answers `304 Not Modified` when the client's copy is current. `If-None-Match`
+/// wins over `If-Modified-Since` when a client sends both.
+fn feed_response(
+    request: &HttpRequest,
+    content_type: &str,
+    body: String,
+    last_modified: Option<DateTime<Utc>>,
+) -> HttpResponse {
+    let etag = EntityTag::new_strong(base64::encode_config(Sha256::digest(body.as_bytes()), base64::URL_SAFE_NO_PAD));
+
+    let is_unchanged = match IfNoneMatch::parse(request) {
+        Ok(IfNoneMatch::Any) => true,
+        // A missing header parses as an empty list.
+        Ok(IfNoneMatch::Items(tags)) if !tags.is_empty() => tags.iter().any(|tag| tag.weak_eq(&etag)),
+        _ => match (IfModifiedSince::parse(request), last_modified) {
+            // HTTP dates have no fractions of a second.
+            (Ok(IfModifiedSince(since)), Some(last_modified)) => {
+                last_modified.timestamp() <= DateTime::<Utc>::from(SystemTime::from(since)).timestamp()
+            },
+            _ => false,
+        },
+    };
+
+    let mut response = if is_unchanged {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::Ok()
+    };
+    // Readers revalidate on every poll, it's cheap when nothing changed.
+    response
+        .insert_header(ETag(etag))
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]));
+    if let Some(last_modified) = last_modified {
+        response.insert_header(LastModified(HttpDate::from(SystemTime::from(last_modified))));
+    }
+
+    if is_unchanged {
+        response.finish()
+    } else {
+        response.content_type(content_type).body(body)
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_feed_issues(pool: &PgPool) -> Result<Vec<FeedIssue>, sqlx::Error> {
+    sqlx::query_as!(
+        FeedIssue,
+        r#"
+        SELECT slug as "slug!", title, html_content, published_at as "published_at!"
+        FROM newsletter_issues
+        WHERE status IN ('sending', 'paused', 'sent')
+            AND slug IS NOT NULL
+            AND published_at <= now()
+            AND NOT hidden_from_archive
+        ORDER BY published_at DESC, id
+        LIMIT $1
+        "#,
+        FEED_SIZE,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// The feeds change when an issue goes out, is cancelled, or is hidden from
+/// or shown in the archive.
+#[tracing::instrument(skip(pool))]
+async fn get_last_modified(pool: &PgPool) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT GREATEST(
+            MAX(published_at) FILTER (WHERE status IN ('sending', 'paused', 'sent') AND published_at <= now()),
+            MAX(cancelled_at),
+            MAX(archive_visibility_changed_at)
+        ) as last_modified
+        FROM newsletter_issues
+        "#,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(record.last_modified)
+}
@@ -0,0 +1,183 @@
+use crate::routes::archive::helpers::render_for_reader;
+use crate::routes::helpers::ApiError;
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::escape_html;
+
+use actix_web::{http::header::ContentType, web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::fmt::Write;
+
+const ISSUES_PER_PAGE: i64 = 20;
+
+#[derive(serde::Deserialize)]
+pub struct ArchiveParameters {
+    page: Option<u32>,
+}
+
+struct ArchivedIssueSummary {
+    slug: String,
+    title: String,
+    published_at: DateTime<Utc>,
+}
+
+struct ArchivedIssue {
+    title: String,
+    html_content: String,
+    published_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "List archived newsletter issues", skip(query, pool))]
+pub async fn archive(
+    query: web::Query<ArchiveParameters>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, ApiError> {
+    let page = query.page.unwrap_or(1).max(1);
+
+    // One extra row tells us whether there is a next page.
+    let mut issues = get_archived_issues(&pool, page)
+        .await
+        .context("Failed to retrieve archived newsletter issues")?;
+    let has_next_page = issues.len() as i64 > ISSUES_PER_PAGE;
+    issues.truncate(ISSUES_PER_PAGE as usize);
+
+    let mut items = String::new();
+    for issue in &issues {
+        writeln!(
+            items,
+            r#"
+                <li><a href="/archive/{slug}">{title}</a> ({published_at})</li>"#,
+            slug = issue.slug,
+            title = escape_html(&issue.title),
+            published_at = issue.published_at.format("%Y-%m-%d"),
+        )
+        .unwrap();
+    }
+
+    let mut pagination = String::new();
+    if page > 1 {
+        write!(pagination, r#"<a href="/archive?page={}">Newer</a> "#, page - 1).unwrap();
+    }
+    if has_next_page {
+        write!(pagination, r#"<a href="/archive?page={}">Older</a>"#, page + 1).unwrap();
+    }
+
+    let empty_note = if issues.is_empty() {
+        "<p>No issues have been published yet.</p>"
+    } else {
+        ""
+    };
+
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Newsletter archive</title>
+        </head>
+        <body>
+            <h1>Newsletter archive</h1>
+            <ul>{items}
+            </ul>
+            {empty_note}
+            <p>{pagination}</p>
+
+            <a href="/">&lt;- Home</a>
+        </body>
+        </html>
+        "#
+    );
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(body)
+    )
+}
+
+#[tracing::instrument(name = "Show an archived newsletter issue", skip(pool, base_url))]
+pub async fn archived_issue(
+    slug: web::Path<String>,
+    pool: web::Data<PgPool>,
+    base_url: web::Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, ApiError> {
+    let issue = get_archived_issue(&pool, &slug)
+        .await
+        .context("Failed to retrieve the archived newsletter issue")?
+        .ok_or(ApiError::NotFoundError)?;
+
+    let html_content = render_for_reader(issue.html_content, &base_url.0)
+        .context("Failed to render the archived newsletter issue")?;
+
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>{title}</title>
+        </head>
+        <body>
+            <h1>{title}</h1>
+            <p>Published on {published_at}</p>
+
+            <iframe sandbox srcdoc="{html_content}" title="{title}" width="800" height="600"></iframe>
+
+            <a href="/archive">&lt;- Archive</a>
+        </body>
+        </html>
+        "#,
+        title = escape_html(&issue.title),
+        published_at = issue.published_at.format("%Y-%m-%d"),
+        html_content = escape_html(&html_content),
+    );
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(body)
+    )
+}
+
+/// Issues that went out, or started to. Scheduled and cancelled issues are not archived,
+/// nor are scheduled issues that were paused before their send time.
+#[tracing::instrument(skip(pool))]
+async fn get_archived_issues(pool: &PgPool, page: u32) -> Result<Vec<ArchivedIssueSummary>, sqlx::Error> {
+    sqlx::query_as!(
+        ArchivedIssueSummary,
+        r#"
+        SELECT slug as "slug!", title, published_at as "published_at!"
+        FROM newsletter_issues
+        WHERE status IN ('sending', 'paused', 'sent')
+            AND slug IS NOT NULL
+            AND published_at <= now()
+            AND NOT hidden_from_archive
+        ORDER BY published_at DESC, id
+        LIMIT $1 OFFSET $2
+        "#,
+        ISSUES_PER_PAGE + 1,
+        (i64::from(page) - 1) * ISSUES_PER_PAGE,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_archived_issue(pool: &PgPool, slug: &str) -> Result<Option<ArchivedIssue>, sqlx::Error> {
+    sqlx::query_as!(
+        ArchivedIssue,
+        r#"
+        SELECT title, html_content, published_at as "published_at!"
+        FROM newsletter_issues
+        WHERE slug = $1
+            AND status IN ('sending', 'paused', 'sent')
+            AND published_at <= now()
+            AND NOT hidden_from_archive
+        "#,
+        slug,
+    )
+    .fetch_optional(pool)
+    .await
+}
@@ -0,0 +1,21 @@
+use crate::domain::{NewsletterTemplate, TemplateValues};
+
+/// Archive readers are anonymous, `{{ name }}` gets a stand-in.
+const ARCHIVE_READER_NAME: &str = "reader";
+
+/// Placeholders are filled in for an anonymous reader, the unsubscribe link leads to
+/// the unsubscribe form rather than carrying anyone's token.
+pub fn render_for_reader(html_content: String, base_url: &str) -> Result<String, anyhow::Error> {
+    let unsubscribe_url = format!("{}/subscriptions/unsubscribe", base_url);
+    let values = TemplateValues {
+        name: ARCHIVE_READER_NAME,
+        email: "",
+        unsubscribe_url: &unsubscribe_url,
+    };
+
+    let html_content = NewsletterTemplate::parse(html_content)
+        .map_err(anyhow::Error::msg)?
+        .render_html(&values);
+
+    Ok(html_content)
+}
@@ -0,0 +1,6 @@
+mod feed;
+mod get;
+mod helpers;
+
+pub use feed::*;
+pub use get::*;
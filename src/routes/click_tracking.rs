@@ -0,0 +1,72 @@
+use actix_web::http::header::{CacheControl, CacheDirective, LOCATION};
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+
+use crate::domain::{ClickTrackingToken, TrackedClick};
+use crate::routes::helpers::ApiError;
+use crate::startup::HmacSecret;
+
+/// Redirects to the link the token points at. Only links registered when the issue
+/// was published can be reached: anything else, including an invalid token, is a 404.
+/// Clicks of subscribers who opted out of tracking still get redirected, unrecorded.
+#[tracing::instrument(name = "Record a newsletter click", skip(token, pool, hmac_secret))]
+pub async fn track_click(
+    token: web::Path<String>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, ApiError> {
+    let click = ClickTrackingToken::parse(&token, &hmac_secret.0).map_err(|e| {
+        tracing::info!(error.message = %e, "Ignoring a click with an invalid token");
+        ApiError::NotFoundError
+    })?;
+    let position = i32::try_from(click.position).map_err(|_| ApiError::NotFoundError)?;
+
+    let url = sqlx::query_scalar!(
+        r#"
+        SELECT url FROM newsletter_links
+        WHERE newsletter_issue_id = $1 AND position = $2
+        "#,
+        click.newsletter_issue_id,
+        position,
+    )
+    .fetch_optional(pool.get_ref())
+    .await
+    .context("Failed to retrieve the clicked link")?
+    .ok_or(ApiError::NotFoundError)?;
+
+    if let Err(e) = record_click(&pool, &click, position).await {
+        tracing::warn!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to record a newsletter click",
+        );
+    }
+
+    // Every click has to reach us, not a cache.
+    Ok(
+        HttpResponse::Found()
+            .insert_header((LOCATION, url))
+            .insert_header(CacheControl(vec![CacheDirective::NoStore, CacheDirective::Private]))
+            .finish()
+    )
+}
+
+#[tracing::instrument(skip(pool))]
+async fn record_click(pool: &PgPool, click: &TrackedClick, position: i32) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_clicks (newsletter_issue_id, position, subscriber_id, clicked_at)
+        SELECT $1, $2, id, now()
+        FROM subscriptions
+        WHERE id = $3 AND NOT do_not_track
+        "#,
+        click.newsletter_issue_id,
+        position,
+        click.subscriber_id,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -3,7 +3,7 @@ use actix_web::http::StatusCode;
 use actix_web::{ResponseError, HttpResponse};
 use reqwest::header;
 
-use crate::routes::helpers::error_chain_fmt;
+use crate::utils::error_chain_fmt;
 
 impl ResponseError for ApiError {
     fn status_code(&self) -> StatusCode {
@@ -11,6 +11,7 @@ impl ResponseError for ApiError {
             ApiError::ValidationError(_) => StatusCode::BAD_REQUEST,
             ApiError::AuthorizationError | 
             ApiError::AuthBasicError => StatusCode::UNAUTHORIZED,
+            ApiError::NotFoundError => StatusCode::NOT_FOUND,
             ApiError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -43,6 +44,8 @@ pub enum ApiError {
     AuthorizationError, // in book we add here #[source] anyhow::Error check later if we really need it
     #[error("Unauthorized")]
     AuthBasicError,
+    #[error("Not found")]
+    NotFoundError,
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
 }
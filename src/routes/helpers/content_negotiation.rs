@@ -0,0 +1,24 @@
+use actix_web::guard::GuardContext;
+use actix_web::http::header::{Accept, ContentType, Header};
+use actix_web::HttpRequest;
+
+/// Browsers put `text/html` first in their `Accept` header. API clients send `*/*`,
+/// `application/json` or no `Accept` at all, and keep getting bare status codes.
+pub fn prefers_html(request: &HttpRequest) -> bool {
+    Accept::parse(request)
+        .map(|accept| accept.preference().essence_str() == "text/html")
+        .unwrap_or(false)
+}
+
+/// Unlike browsers, API clients only get JSON when they ask for it.
+pub fn prefers_json(request: &HttpRequest) -> bool {
+    Accept::parse(request)
+        .map(|accept| accept.preference().essence_str() == "application/json")
+        .unwrap_or(false)
+}
+
+/// Route guard for the handlers taking a JSON body, whatever its `charset`.
+pub fn has_json_body(ctx: &GuardContext) -> bool {
+    ctx.header::<ContentType>()
+        .is_some_and(|content_type| content_type.0.essence_str() == "application/json")
+}
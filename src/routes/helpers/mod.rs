@@ -1,5 +1,5 @@
 mod api_error;
-mod error_chain_fmt;
+mod content_negotiation;
 
 pub use api_error::ApiError;
-pub use error_chain_fmt::error_chain_fmt;
+pub use content_negotiation::{has_json_body, prefers_html, prefers_json};
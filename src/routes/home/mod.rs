@@ -1,8 +1,16 @@
 use actix_web::HttpResponse;
 use actix_web::http::header::ContentType;
+use actix_web_flash_messages::IncomingFlashMessages;
+use std::fmt::Write;
+
+pub async fn home(flash_messages: IncomingFlashMessages) -> HttpResponse {
+    let mut flash_html = String::new();
+
+    for message in flash_messages.iter() {
+        writeln!(flash_html, "<p><i>{}</i></p>", message.content()).unwrap();
+    }
 
-pub async fn home() -> HttpResponse {
     HttpResponse::Ok()
         .content_type(ContentType::html())
-        .body(include_str!("home.html"))
+        .body(format!(include_str!("home.html"), flash_html = flash_html))
 }
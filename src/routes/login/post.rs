@@ -29,14 +29,14 @@ pub async fn login(
     
     tracing::Span::current().record(
         "username",
-        &tracing::field::display(&creds.username),
+        tracing::field::display(&creds.username),
     );
 
     match validate_credentials(creds, &pool).await { 
         Ok(user_id) => {
             tracing::Span::current().record(
                 "user_id",
-                &tracing::field::display(&user_id),
+                tracing::field::display(&user_id),
             );
 
             session.renew();
@@ -1,13 +1,23 @@
+mod archive;
+mod click_tracking;
 mod health_check;
 mod home;
 mod login;
+mod open_tracking;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_resend;
+mod subscriptions_unsubscribe;
 pub mod admin;
 pub mod helpers;
 
+pub use archive::*;
+pub use click_tracking::*;
 pub use health_check::*;
 pub use home::*;
 pub use login::*;
+pub use open_tracking::*;
 pub use subscriptions::*;
 pub use subscriptions_confirm::*;
+pub use subscriptions_resend::*;
+pub use subscriptions_unsubscribe::*;
@@ -0,0 +1,67 @@
+use actix_web::http::header::{CacheControl, CacheDirective};
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::OpenTrackingToken;
+use crate::startup::HmacSecret;
+
+/// A transparent 1x1 GIF.
+const PIXEL: [u8; 43] = [
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+/// Always answers with the pixel, an email client has no use for an error.
+/// Opens are only recorded for valid tokens, for issues that still track opens and
+/// for subscribers who haven't opted out since the issue was sent.
+#[tracing::instrument(name = "Record a newsletter open", skip(token, pool, hmac_secret))]
+pub async fn track_open(
+    token: web::Path<String>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> HttpResponse {
+    match OpenTrackingToken::parse(&token, &hmac_secret.0) {
+        Ok((newsletter_issue_id, subscriber_id)) => {
+            if let Err(e) = record_open(&pool, newsletter_issue_id, subscriber_id).await {
+                tracing::warn!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    "Failed to record a newsletter open",
+                );
+            }
+        },
+        Err(e) => tracing::info!(error.message = %e, "Ignoring an open with an invalid token"),
+    }
+
+    // Every open has to reach us, not a cache.
+    HttpResponse::Ok()
+        .content_type("image/gif")
+        .insert_header(CacheControl(vec![CacheDirective::NoStore, CacheDirective::Private]))
+        .body(PIXEL.as_slice())
+}
+
+#[tracing::instrument(skip(pool))]
+async fn record_open(pool: &PgPool, newsletter_issue_id: Uuid, subscriber_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_opens (newsletter_issue_id, subscriber_id, n_opens, first_opened_at, last_opened_at)
+        SELECT newsletter_issues.id, subscriptions.id, 1, now(), now()
+        FROM newsletter_issues, subscriptions
+        WHERE newsletter_issues.id = $1
+            AND newsletter_issues.track_opens
+            AND subscriptions.id = $2
+            AND NOT subscriptions.do_not_track
+        ON CONFLICT (newsletter_issue_id, subscriber_id) DO UPDATE
+        SET n_opens = newsletter_opens.n_opens + 1,
+            last_opened_at = EXCLUDED.last_opened_at
+        "#,
+        newsletter_issue_id,
+        subscriber_id,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
@@ -1,4 +1,5 @@
-use actix_web::{web, HttpResponse};
+use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web_flash_messages::FlashMessage;
 use anyhow::Context;
 use chrono::Utc;
 use rand::distributions::Alphanumeric;
@@ -6,55 +7,207 @@ use rand::{thread_rng, Rng};
 use sqlx::{Transaction, Postgres, PgPool};
 use uuid::Uuid;
 
+use crate::configuration::SubscriptionSettings;
 use crate::domain::NewSubscriber;
 use crate::domain::SubscriberEmail;
 use crate::domain::SubscriberName;
-use crate::email_client::EmailClient;
-use crate::routes::helpers::{ApiError, error_chain_fmt};
+use crate::email_client::EmailTransport;
+use crate::routes::helpers::{ApiError, prefers_html, prefers_json};
 use crate::startup::ApplicationBaseUrl;
+use crate::utils::{error_chain_fmt, escape_html, see_other};
 
+/// The same fields whether they come from the signup form or as JSON. Missing fields
+/// are empty, so that they are reported along with the other invalid ones.
 #[derive(serde::Deserialize)]
 pub struct FormData {
+    #[serde(default)]
     email: String,
+    #[serde(default)]
     name: String,
 }
 
+/// Which field failed validation and why. Errors about the request as a whole have no field.
+#[derive(serde::Serialize, Debug)]
+pub struct FieldError {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    field: Option<&'static str>,
+    message: String,
+}
+
+impl FieldError {
+    fn new(field: &'static str, message: String) -> Self {
+        Self { field: Some(field), message }
+    }
+}
+
+/// Every invalid field is reported, not only the first one.
 impl TryFrom<FormData> for NewSubscriber {
-    type Error = String;
+    type Error = Vec<FieldError>;
 
     fn try_from(value: FormData) -> Result<Self, Self::Error> {
-        let name = SubscriberName::parse(value.name)?;
-        let email = SubscriberEmail::parse(value.email).map_err(|e| e.0)?;
+        let name = SubscriberName::parse(value.name).map_err(|e| FieldError::new("name", e));
+        let email = SubscriberEmail::parse(value.email).map_err(|e| FieldError::new("email", e.0));
+
+        match (name, email) {
+            (Ok(name), Ok(email)) => Ok(Self { email, name }),
+            (name, email) => Err(name.err().into_iter().chain(email.err()).collect()),
+        }
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum SubscribeError {
+    #[error("{}", .0.iter().map(|e| e.message.as_str()).collect::<Vec<_>>().join(" "))]
+    ValidationError(Vec<FieldError>),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for SubscribeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl From<SubscribeError> for ApiError {
+    fn from(e: SubscribeError) -> Self {
+        match e {
+            SubscribeError::ValidationError(_) => ApiError::ValidationError(e.to_string()),
+            SubscribeError::UnexpectedError(e) => ApiError::UnexpectedError(e),
+        }
+    }
+}
+
+/// Form submissions. Browsers get redirected to the signup form, clients asking for
+/// JSON get the same answers as `subscribe_json`, the others bare status codes.
+pub async fn subscribe(
+    request: HttpRequest,
+    form: Result<web::Form<FormData>, actix_web::Error>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<dyn EmailTransport>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    settings: web::Data<SubscriptionSettings>,
+) -> Result<HttpResponse, ApiError> {
+    let outcome = match form {
+        Ok(form) => register_subscriber(form.0, &pool, email_client.as_ref(), &base_url.0, &settings).await,
+        Err(_) => Err(request_error("Please fill in your name and your email address.")),
+    };
+
+    if prefers_html(&request) {
+        return subscribe_page(outcome);
+    }
+    if prefers_json(&request) {
+        return json_response(outcome);
+    }
 
-        Ok(Self { email, name })
+    match outcome? {
+        SubscribeOutcome::ConfirmationSent => Ok(HttpResponse::Ok().finish()),
+        SubscribeOutcome::AlreadyConfirmed => {
+            Ok(HttpResponse::UnprocessableEntity().body("Email is already confirmed."))
+        },
     }
 }
 
+/// `POST /subscriptions` with an `application/json` body, for the clients that aren't
+/// a browser on the home page. Validation errors come back field by field:
+/// `{"errors": [{"field": "email", "message": "..."}]}`.
+pub async fn subscribe_json(
+    body: Result<web::Json<FormData>, actix_web::Error>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<dyn EmailTransport>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    settings: web::Data<SubscriptionSettings>,
+) -> Result<HttpResponse, ApiError> {
+    let outcome = match body {
+        Ok(body) => register_subscriber(body.0, &pool, email_client.as_ref(), &base_url.0, &settings).await,
+        Err(e) => Err(request_error(&format!("The request body is not valid: {}", e))),
+    };
+
+    json_response(outcome)
+}
+
+fn request_error(message: &str) -> SubscribeError {
+    SubscribeError::ValidationError(vec![FieldError { field: None, message: message.into() }])
+}
+
+/// Browsers posting the form on the home page are sent back to it, with the outcome
+/// in a flash message. Only unexpected errors are left to `ApiError`.
+fn subscribe_page(outcome: Result<SubscribeOutcome, SubscribeError>) -> Result<HttpResponse, ApiError> {
+    match outcome {
+        Ok(SubscribeOutcome::ConfirmationSent) => {
+            FlashMessage::info("Thanks for subscribing! Click the link in the email we just sent you to confirm.").send();
+        },
+        Ok(SubscribeOutcome::AlreadyConfirmed) => {
+            FlashMessage::info("You are already subscribed.").send();
+        },
+        Err(e @ SubscribeError::ValidationError(_)) => {
+            FlashMessage::error(escape_html(&e.to_string())).send();
+        },
+        Err(e) => return Err(e.into()),
+    }
+
+    Ok(see_other("/"))
+}
+
+fn json_response(outcome: Result<SubscribeOutcome, SubscribeError>) -> Result<HttpResponse, ApiError> {
+    match outcome {
+        Ok(SubscribeOutcome::ConfirmationSent) => {
+            Ok(HttpResponse::Ok().json(serde_json::json!({ "status": "pending_confirmation" })))
+        },
+        Ok(SubscribeOutcome::AlreadyConfirmed) => {
+            let errors = [FieldError::new("email", "Email is already confirmed.".into())];
+            Ok(HttpResponse::UnprocessableEntity().json(serde_json::json!({ "errors": errors })))
+        },
+        Err(SubscribeError::ValidationError(errors)) => {
+            Ok(HttpResponse::BadRequest().json(serde_json::json!({ "errors": errors })))
+        },
+        Err(e) => Err(e.into()),
+    }
+}
+
+enum SubscribeOutcome {
+    /// Also when the last confirmation email went out less than `resend_cooldown_in_secs`
+    /// ago: subscribing again doesn't send another one until then.
+    ConfirmationSent,
+    AlreadyConfirmed,
+}
+
 #[tracing::instrument(
     name = "Saving new subscriber details in the database.",
-    skip(form, pool, email_client, base_url),
+    skip(form, pool, email_client, base_url, settings),
     fields(
         subscriber_email = %form.email,
         subscriber_name = %form.name
     )
 )]
-pub async fn subscribe(
-    form: web::Form<FormData>,
-    pool: web::Data<PgPool>,
-    email_client: web::Data<EmailClient>,
-    base_url: web::Data<ApplicationBaseUrl>,
-) -> Result<HttpResponse, ApiError> { 
-    let new_subscriber = form.0.try_into().map_err(ApiError::ValidationError)?;
+async fn register_subscriber(
+    form: FormData,
+    pool: &PgPool,
+    email_client: &dyn EmailTransport,
+    base_url: &str,
+    settings: &SubscriptionSettings,
+) -> Result<SubscribeOutcome, SubscribeError> {
+    let new_subscriber = form.try_into().map_err(SubscribeError::ValidationError)?;
     let mut transaction = pool.begin().await.context("Failed to acquire a Postgres connection from the pool")?;
 
-    let subscriber = find_subscriber_id(&mut transaction, &new_subscriber)
+    let subscriber = find_subscriber_id(&mut transaction, &new_subscriber, settings.resend_cooldown_in_secs)
         .await
         .context("Failed to find subscriber by id")?;
 
     let subscriber_id = match subscriber {
         Some(subscriber) => {
             if subscriber.is_confirmed() {
-                return Ok(HttpResponse::UnprocessableEntity().body("Email is already confirmed."));
+                return Ok(SubscribeOutcome::AlreadyConfirmed);
+            };
+
+            if subscriber.is_waiting_for_cooldown() {
+                return Ok(SubscribeOutcome::ConfirmationSent);
+            };
+
+            if subscriber.is_unsubscribed() {
+                mark_subscriber_as_pending_confirmation(&mut transaction, subscriber.id)
+                    .await
+                    .context("Failed to resubscribe subscriber")?;
             };
 
             subscriber.id
@@ -72,41 +225,65 @@ pub async fn subscribe(
     transaction.commit().await.context("Failed to commit transaction")?;
 
     send_confirmation_email(
-        &email_client,
+        email_client,
         new_subscriber,
-        &base_url.0,
+        base_url,
         &subscription_token,
     )
     .await
     .context("Failed to send confirmation email")?;
 
-    Ok(HttpResponse::Ok().finish())
+    Ok(SubscribeOutcome::ConfirmationSent)
 }
 
 // move this later to the domain
 struct Subscriber {
     id: Uuid,
     status: String,
+    cooldown_left_in_secs: Option<i32>,
 }
 
 impl Subscriber {
     fn is_confirmed(&self) -> bool {
         self.status == "confirmed"
     }
+
+    fn is_unsubscribed(&self) -> bool {
+        self.status == "unsubscribed"
+    }
+
+    fn is_waiting_for_cooldown(&self) -> bool {
+        self.status == "pending_confirmation" && self.cooldown_left_in_secs.is_some_and(|secs| secs > 0)
+    }
 }
 
+/// The subscriber is locked like in `resend_confirmation`, so that concurrent requests
+/// can't both get past the cooldown.
 #[tracing::instrument(
     skip(new_subscriber, transaction)
 )]
 async fn find_subscriber_id(
     transaction: &mut Transaction<'_, Postgres>,
     new_subscriber: &NewSubscriber,
+    resend_cooldown_in_secs: u32,
 )
 -> Result<Option<Subscriber>, sqlx::Error> {
     let outcome = sqlx::query_as!(
         Subscriber,
-        r#"SELECT id, status FROM subscriptions WHERE email = $1"#,
+        r#"
+        SELECT id,
+            status,
+            (
+                SELECT EXTRACT(EPOCH FROM MAX(created_at) + make_interval(secs => $2) - now())::integer
+                FROM subscription_tokens
+                WHERE subscriber_id = subscriptions.id
+            ) as cooldown_left_in_secs
+        FROM subscriptions
+        WHERE email = $1
+        FOR UPDATE
+        "#,
         new_subscriber.email.as_ref(),
+        resend_cooldown_in_secs as f64,
     )
     .fetch_optional(transaction)
     .await?;
@@ -139,16 +316,33 @@ async fn insert_subscriber(
     Ok(subscriber_id)
 }
 
+#[tracing::instrument(
+    skip(transaction)
+)]
+async fn mark_subscriber_as_pending_confirmation(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE subscriptions SET status = 'pending_confirmation', unsubscribed_at = NULL WHERE id = $1"#,
+        subscriber_id,
+    )
+    .execute(transaction)
+    .await?;
+
+    Ok(())
+}
+
 #[tracing::instrument(
     name = "Send a confirmation email to a new subscriber",
     skip(email_client, new_subscriber)
 )]
 pub async fn send_confirmation_email(
-    email_client: &EmailClient,
+    email_client: &dyn EmailTransport,
     new_subscriber: NewSubscriber,
     base_url: &str,
     subscriber_token: &str,
-) -> Result<(), reqwest::Error> {
+) -> Result<(), anyhow::Error> {
     let confirmation_link = format!(
         "{}/subscriptions/confirm?subscription_token={}",
         base_url,
@@ -168,6 +362,7 @@ pub async fn send_confirmation_email(
                 "Welcome to our newsletter!\n Visit {} to confirm your subscription.",
                 confirmation_link,
             ),
+            &[],
         )
         .await?;
 
@@ -183,6 +378,8 @@ pub fn generate_subscription_token() -> String {
         .collect()
 }
 
+/// A subscriber has a single confirmation link at a time: asking for another one
+/// replaces the previous link.
 pub async fn store_token(
     transaction: &mut Transaction<'_, Postgres>,
     subscriber_id: Uuid,
@@ -190,8 +387,11 @@ pub async fn store_token(
 ) -> Result<(), StoreTokenError> {
     sqlx::query!(
         r#"
-        INSERT INTO subscription_tokens (subscription_token, subscriber_id)
-            VALUES ($1, $2)
+        WITH replaced_tokens AS (
+            DELETE FROM subscription_tokens WHERE subscriber_id = $2
+        )
+        INSERT INTO subscription_tokens (subscription_token, subscriber_id, created_at)
+            VALUES ($1, $2, now())
         "#,
         subscription_token,
         subscriber_id,
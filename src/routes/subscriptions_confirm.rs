@@ -1,36 +1,92 @@
-use actix_web::{HttpResponse, web};
+use actix_web::http::header::ContentType;
+use actix_web::{HttpRequest, HttpResponse, web};
+use actix_web_flash_messages::FlashMessage;
 use anyhow::Context;
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::routes::helpers::ApiError;
+use crate::configuration::SubscriptionSettings;
+use crate::routes::helpers::{prefers_html, ApiError};
+use crate::utils::{escape_html, see_other};
 
 #[derive(serde::Deserialize)]
 pub struct Parameters {
     pub subscription_token: String,
 }
 
+pub enum ConfirmationToken {
+    Valid { subscriber_id: Uuid },
+    /// The email address is there to fill in the resend form.
+    Expired { email: String },
+}
+
 #[tracing::instrument(
     name = "Confirm a pending subscriber",
-    skip(parameters),
+    skip(request, parameters, pool, settings),
 )]
 pub async fn confirm(
+    request: HttpRequest,
     parameters: web::Query<Parameters>,
     pool: web::Data<PgPool>,
+    settings: web::Data<SubscriptionSettings>,
 ) -> Result<HttpResponse, ApiError> {
-    let subcriber_id_option = get_subscriber_id_from_token(&pool, &parameters.subscription_token)
+    let token = get_subscriber_id_from_token(&pool, &parameters.subscription_token, settings.confirmation_link_ttl_in_hours)
         .await
         .context("Failed to retrieve a subscriber")?;
+    let html = prefers_html(&request);
 
-    let subscriber_id = subcriber_id_option.ok_or(ApiError::AuthorizationError)?;
+    let subscriber_id = match token {
+        Some(ConfirmationToken::Valid { subscriber_id }) => subscriber_id,
+        Some(ConfirmationToken::Expired { email }) if html => return Ok(link_expired_page(&email)),
+        Some(ConfirmationToken::Expired { .. }) => return Ok(HttpResponse::Gone().finish()),
+        None if html => {
+            FlashMessage::error("This confirmation link is not valid anymore. Subscribe again to get a new one.").send();
+            return Ok(see_other("/"));
+        },
+        None => return Err(ApiError::AuthorizationError),
+    };
 
     confirm_subscriber(&pool, &subscriber_id)
         .await
         .context("Failed to confirm subscriber")?;
-        
+
+    if html {
+        FlashMessage::info("Your subscription is confirmed, welcome aboard!").send();
+        return Ok(see_other("/"));
+    }
+
     Ok(HttpResponse::Ok().finish())
 }
 
+fn link_expired_page(email: &str) -> HttpResponse {
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Link expired</title>
+        </head>
+        <body>
+            <p>This confirmation link has expired. Do you want us to send you a new one?</p>
+            <form action="/subscriptions/resend_confirmation" method="post">
+                <label>Email
+                    <input type="email" name="email" value="{email}">
+                </label>
+                <button type="submit">Resend the confirmation email</button>
+            </form>
+        </body>
+        </html>
+        "#,
+        email = escape_html(email),
+    );
+
+    HttpResponse::Gone()
+        .content_type(ContentType::html())
+        .body(body)
+}
+
+/// The link is used up once the subscriber is confirmed.
 #[tracing::instrument(
     name = "Mark subscriber as confirmed",
     skip(pool, subscriber_id),
@@ -40,7 +96,12 @@ pub async fn confirm_subscriber(
     subscriber_id: &Uuid,
 ) -> Result<(), sqlx::Error> {
     sqlx::query!(
-        r#"UPDATE subscriptions SET status = 'confirmed' WHERE id = $1"#,
+        r#"
+        WITH used_tokens AS (
+            DELETE FROM subscription_tokens WHERE subscriber_id = $1
+        )
+        UPDATE subscriptions SET status = 'confirmed' WHERE id = $1
+        "#,
         subscriber_id,
     )
     .execute(pool)
@@ -49,6 +110,7 @@ pub async fn confirm_subscriber(
     Ok(())
 }
 
+/// Expired tokens are still found until `subscription_token_worker` removes them.
 #[tracing::instrument(
     name = "Get subscriber_id from token",
     skip(subscription_token, pool),
@@ -56,19 +118,28 @@ pub async fn confirm_subscriber(
 pub async fn get_subscriber_id_from_token(
     pool: &PgPool,
     subscription_token: &str,
-) -> Result<Option<Uuid>, sqlx::Error> {
+    ttl_in_hours: u32,
+) -> Result<Option<ConfirmationToken>, sqlx::Error> {
     let result = sqlx::query!(
         r#"
-        SELECT subscriber_id FROM subscription_tokens
+        SELECT subscriber_id,
+            subscriptions.email,
+            subscription_tokens.created_at > now() - make_interval(hours => $2) as "is_valid!"
+        FROM subscription_tokens
             INNER JOIN subscriptions
                 ON subscription_tokens.subscriber_id = subscriptions.id
             WHERE subscriptions.status = 'pending_confirmation'
                 AND subscription_token = $1
         "#,
         subscription_token,
+        ttl_in_hours as i32,
     )
     .fetch_optional(pool)
     .await?;
 
-    Ok(result.map(|r| r.subscriber_id))
+    Ok(result.map(|r| if r.is_valid {
+        ConfirmationToken::Valid { subscriber_id: r.subscriber_id }
+    } else {
+        ConfirmationToken::Expired { email: r.email }
+    }))
 }
@@ -0,0 +1,107 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+
+use crate::configuration::SubscriptionSettings;
+use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName};
+use crate::email_client::EmailTransport;
+use crate::routes::helpers::ApiError;
+use crate::routes::{generate_subscription_token, send_confirmation_email, store_token};
+use crate::startup::ApplicationBaseUrl;
+use crate::utils::escape_html;
+
+#[derive(serde::Deserialize)]
+pub struct ResendConfirmationFormData {
+    email: String,
+}
+
+/// Unknown and already confirmed addresses get the same answer as pending ones, only
+/// nothing is sent. Pending subscribers get at most one email every `resend_cooldown_in_secs`,
+/// the answer doesn't tell either: it would give away which addresses are pending.
+#[tracing::instrument(
+    name = "Resend a confirmation email",
+    skip(form, pool, email_client, base_url, settings),
+    fields(subscriber_email = %form.email)
+)]
+pub async fn resend_confirmation(
+    form: web::Form<ResendConfirmationFormData>,
+    pool: web::Data<PgPool>,
+    email_client: web::Data<dyn EmailTransport>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    settings: web::Data<SubscriptionSettings>,
+) -> Result<HttpResponse, ApiError> {
+    let email = SubscriberEmail::parse(form.0.email).map_err(|e| ApiError::ValidationError(e.0))?;
+    let mut transaction = pool.begin().await.context("Failed to acquire a Postgres connection from the pool")?;
+
+    // Locking the subscriber keeps concurrent requests from both getting past the cooldown.
+    let subscriber = sqlx::query!(
+        r#"
+        SELECT id,
+            name,
+            (
+                SELECT EXTRACT(EPOCH FROM MAX(created_at) + make_interval(secs => $2) - now())::integer
+                FROM subscription_tokens
+                WHERE subscriber_id = subscriptions.id
+            ) as cooldown_left_in_secs
+        FROM subscriptions
+        WHERE email = $1 AND status = 'pending_confirmation'
+        FOR UPDATE
+        "#,
+        email.as_ref(),
+        settings.resend_cooldown_in_secs as f64,
+    )
+    .fetch_optional(&mut transaction)
+    .await
+    .context("Failed to find the subscriber")?;
+
+    let subscriber = match subscriber {
+        Some(subscriber) => subscriber,
+        None => return Ok(email_sent_page(&email)),
+    };
+
+    if subscriber.cooldown_left_in_secs.is_some_and(|secs| secs > 0) {
+        return Ok(email_sent_page(&email));
+    }
+
+    let name = SubscriberName::parse(subscriber.name).map_err(|e| anyhow::anyhow!(e))?;
+    let subscription_token = generate_subscription_token();
+    store_token(&mut transaction, subscriber.id, &subscription_token)
+        .await
+        .context("Failed to store token")?;
+    transaction.commit().await.context("Failed to commit transaction")?;
+
+    let response = email_sent_page(&email);
+    send_confirmation_email(
+        email_client.as_ref(),
+        NewSubscriber { email, name },
+        &base_url.0,
+        &subscription_token,
+    )
+    .await
+    .context("Failed to send confirmation email")?;
+
+    Ok(response)
+}
+
+fn email_sent_page(email: &SubscriberEmail) -> HttpResponse {
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Confirmation email</title>
+        </head>
+        <body>
+            <p>If {email} is waiting for confirmation, a new confirmation link is on its way.</p>
+        </body>
+        </html>
+        "#,
+        email = escape_html(email.as_ref()),
+    );
+
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(body)
+}
@@ -0,0 +1,169 @@
+use actix_web::http::header::ContentType;
+use actix_web::{HttpResponse, web};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::UnsubscribeToken;
+use crate::routes::helpers::ApiError;
+use crate::startup::HmacSecret;
+
+#[derive(serde::Deserialize)]
+pub struct UnsubscribeParameters {
+    pub unsubscribe_token: String,
+}
+
+#[tracing::instrument(
+    name = "Show the unsubscribe confirmation page",
+    skip(parameters, hmac_secret),
+)]
+pub async fn unsubscribe_form(
+    parameters: web::Query<UnsubscribeParameters>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, ApiError> {
+    UnsubscribeToken::parse(&parameters.unsubscribe_token, &hmac_secret.0)
+        .map_err(|_| ApiError::AuthorizationError)?;
+
+    let body = format!(
+        r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Unsubscribe</title>
+        </head>
+        <body>
+            <p>Do you want to stop receiving our newsletter?</p>
+            <form action="/subscriptions/unsubscribe?unsubscribe_token={unsubscribe_token}" method="post">
+                <button type="submit">Unsubscribe</button>
+            </form>
+
+            <p>Or keep receiving it, without us knowing when you open it or click its links.</p>
+            <form action="/subscriptions/do_not_track?unsubscribe_token={unsubscribe_token}" method="post">
+                <button type="submit">Stop tracking me</button>
+            </form>
+        </body>
+        </html>
+        "#,
+        unsubscribe_token = parameters.unsubscribe_token,
+    );
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(body)
+    )
+}
+
+// The token is always read from the query string: our own form posts an empty body,
+// while RFC 8058 one-click requests post `List-Unsubscribe=One-Click`.
+#[tracing::instrument(
+    name = "Unsubscribe a subscriber",
+    skip(parameters, pool, hmac_secret),
+    fields(subscriber_id=tracing::field::Empty)
+)]
+pub async fn unsubscribe(
+    parameters: web::Query<UnsubscribeParameters>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, ApiError> {
+    let subscriber_id = UnsubscribeToken::parse(&parameters.unsubscribe_token, &hmac_secret.0)
+        .map_err(|_| ApiError::AuthorizationError)?;
+
+    tracing::Span::current().record("subscriber_id", tracing::field::display(&subscriber_id));
+
+    mark_subscriber_as_unsubscribed(&pool, &subscriber_id)
+        .await
+        .context("Failed to unsubscribe subscriber")?;
+
+    let body = r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Unsubscribed</title>
+        </head>
+        <body>
+            <p>You have been unsubscribed. You will not receive any more newsletter issues.</p>
+            <a href="/">Home</a>
+        </body>
+        </html>
+        "#;
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(body)
+    )
+}
+
+/// Same token as for unsubscribing: it comes with every email, so subscribers can
+/// opt out of open and click tracking from any of them. What was recorded so far goes too.
+#[tracing::instrument(
+    name = "Opt a subscriber out of tracking",
+    skip(parameters, pool, hmac_secret),
+    fields(subscriber_id=tracing::field::Empty)
+)]
+pub async fn do_not_track(
+    parameters: web::Query<UnsubscribeParameters>,
+    pool: web::Data<PgPool>,
+    hmac_secret: web::Data<HmacSecret>,
+) -> Result<HttpResponse, ApiError> {
+    let subscriber_id = UnsubscribeToken::parse(&parameters.unsubscribe_token, &hmac_secret.0)
+        .map_err(|_| ApiError::AuthorizationError)?;
+
+    tracing::Span::current().record("subscriber_id", tracing::field::display(&subscriber_id));
+
+    sqlx::query!(
+        r#"
+        WITH opted_out AS (
+            UPDATE subscriptions SET do_not_track = true WHERE id = $1 RETURNING id
+        ), forgotten_opens AS (
+            DELETE FROM newsletter_opens WHERE subscriber_id IN (SELECT id FROM opted_out)
+        )
+        DELETE FROM newsletter_clicks WHERE subscriber_id IN (SELECT id FROM opted_out)
+        "#,
+        subscriber_id,
+    )
+    .execute(pool.get_ref())
+    .await
+    .context("Failed to opt the subscriber out of tracking")?;
+
+    let body = r#"
+        <!DOCTYPE html>
+        <html lang="en">
+        <head>
+            <meta http-equiv="content-type" content="text/html; charset=utf-8">
+            <title>Tracking</title>
+        </head>
+        <body>
+            <p>We won't track when you open our newsletter or click its links anymore.</p>
+            <a href="/">Home</a>
+        </body>
+        </html>
+        "#;
+
+    Ok(
+        HttpResponse::Ok()
+            .content_type(ContentType::html())
+            .body(body)
+    )
+}
+
+#[tracing::instrument(
+    name = "Mark subscriber as unsubscribed",
+    skip(pool, subscriber_id),
+)]
+pub async fn mark_subscriber_as_unsubscribed(
+    pool: &PgPool,
+    subscriber_id: &Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"UPDATE subscriptions SET status = 'unsubscribed', unsubscribed_at = now() WHERE id = $1"#,
+        subscriber_id,
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
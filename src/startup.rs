@@ -1,33 +1,64 @@
 use actix_session::{SessionMiddleware, storage::RedisSessionStore};
 use actix_web::cookie::Key;
 use actix_web::dev::Server;
-use actix_web::{web, App, HttpServer};
+use actix_web::{guard, web, App, HttpServer};
 use actix_web_flash_messages::{FlashMessagesFramework, storage::CookieMessageStore};
 use secrecy::{Secret, ExposeSecret};
 use sqlx::PgPool;
 use sqlx::postgres::PgPoolOptions;
 use std::net::TcpListener;
+use std::sync::Arc;
 use tracing_actix_web::TracingLogger;
 
 use crate::authentication::middleware::RejectAnonymousUsers;
+use crate::configuration::ApplicationSettings;
 use crate::configuration::DatabaseSettings;
 use crate::configuration::Settings;
-use crate::email_client::EmailClient;
+use crate::configuration::SubscriptionSettings;
+use crate::email_client::EmailTransport;
+use crate::markdown::NewsletterLayout;
+use crate::routes::helpers::has_json_body;
 use crate::routes::{
     admin::{
         admin_dashboard,
+        analytics,
+        cancel_newsletter,
         change_password,
         change_password_form,
+        create_newsletter_draft,
+        delivery_failures,
+        edit_newsletter,
+        edit_newsletter_form,
         log_out,
+        newsletter_history,
+        newsletter_issue_progress,
+        pause_newsletter,
+        preview_newsletter,
         publish_newsletter,
+        requeue_all_delivery_failures,
+        requeue_delivery_failure,
+        resume_newsletter,
+        send_test_newsletter,
+        set_archive_visibility,
         submit_newsletter_form,
     },
+    archive,
+    archived_issue,
+    atom_feed,
     confirm,
+    do_not_track,
     health_check,
     home,
     login,
     login_form,
+    resend_confirmation,
+    rss_feed,
     subscribe,
+    subscribe_json,
+    track_click,
+    track_open,
+    unsubscribe,
+    unsubscribe_form,
 };
 
 pub struct Application {
@@ -37,19 +68,27 @@ pub struct Application {
 
 pub struct ApplicationBaseUrl(pub String);
 
+#[derive(Clone)]
+pub struct HmacSecret(pub Secret<String>);
+
 pub async fn run(
     listener: TcpListener,
     db_pool: PgPool,
-    email_client: EmailClient,
-    base_url: String,
-    hmac_secret: Secret<String>,
+    email_client: Arc<dyn EmailTransport>,
+    application: ApplicationSettings,
     redis_uri: Secret<String>,
+    newsletter_layout: NewsletterLayout,
+    subscription_settings: SubscriptionSettings,
 ) -> Result<Server, anyhow::Error> {
     let db_pool = web::Data::new(db_pool);
-    let email_client = web::Data::new(email_client);
+    let email_client: web::Data<dyn EmailTransport> = web::Data::from(email_client);
+    let ApplicationSettings { base_url, hmac_secret, .. } = application;
     let base_url = web::Data::new(ApplicationBaseUrl(base_url));
+    let newsletter_layout = web::Data::new(newsletter_layout);
+    let subscription_settings = web::Data::new(subscription_settings);
 
     let secret_key = Key::from(hmac_secret.expose_secret().as_bytes());
+    let hmac_secret = web::Data::new(HmacSecret(hmac_secret));
 
     let message_store = CookieMessageStore::builder(secret_key.clone()).build();
     let message_framework = FlashMessagesFramework::builder(message_store).build();
@@ -65,21 +104,50 @@ pub async fn run(
             .service(
                 web::scope("/admin")
                     .wrap(RejectAnonymousUsers)
+                    .route("/analytics", web::get().to(analytics))
                     .route("/dashboard", web::get().to(admin_dashboard))
                     .route("/logout", web::post().to(log_out))
                     .route("/newsletters", web::get().to(submit_newsletter_form))
-                    .route("/newsletters", web::post().to(publish_newsletter))
+                    .route("/newsletters", web::post().to(create_newsletter_draft))
+                    .route("/newsletters/failures", web::get().to(delivery_failures))
+                    .route("/newsletters/failures/requeue", web::post().to(requeue_delivery_failure))
+                    .route("/newsletters/failures/requeue_all", web::post().to(requeue_all_delivery_failures))
+                    .route("/newsletters/history", web::get().to(newsletter_history))
+                    .route("/newsletters/{newsletter_issue_id}", web::get().to(newsletter_issue_progress))
+                    .route("/newsletters/{newsletter_issue_id}/edit", web::get().to(edit_newsletter_form))
+                    .route("/newsletters/{newsletter_issue_id}/edit", web::post().to(edit_newsletter))
+                    .route("/newsletters/{newsletter_issue_id}/preview", web::get().to(preview_newsletter))
+                    .route("/newsletters/{newsletter_issue_id}/publish", web::post().to(publish_newsletter))
+                    .route("/newsletters/{newsletter_issue_id}/test", web::post().to(send_test_newsletter))
+                    .route("/newsletters/{newsletter_issue_id}/pause", web::post().to(pause_newsletter))
+                    .route("/newsletters/{newsletter_issue_id}/resume", web::post().to(resume_newsletter))
+                    .route("/newsletters/{newsletter_issue_id}/cancel", web::post().to(cancel_newsletter))
+                    .route("/newsletters/{newsletter_issue_id}/archive", web::post().to(set_archive_visibility))
                     .route("/password", web::get().to(change_password_form))
                     .route("/password", web::post().to(change_password))
             )
+            .route("/archive", web::get().to(archive))
+            .route("/archive/{slug}", web::get().to(archived_issue))
+            .route("/feed.atom", web::get().to(atom_feed))
+            .route("/feed.rss", web::get().to(rss_feed))
             .route("/health_check", web::get().to(health_check))
             .route("/login", web::get().to(login_form))
             .route("/login", web::post().to(login))
+            .route("/subscriptions", web::post().guard(guard::fn_guard(has_json_body)).to(subscribe_json))
             .route("/subscriptions", web::post().to(subscribe))
             .route("/subscriptions/confirm", web::get().to(confirm))
+            .route("/subscriptions/do_not_track", web::post().to(do_not_track))
+            .route("/subscriptions/resend_confirmation", web::post().to(resend_confirmation))
+            .route("/subscriptions/unsubscribe", web::get().to(unsubscribe_form))
+            .route("/subscriptions/unsubscribe", web::post().to(unsubscribe))
+            .route("/t/c/{token}", web::get().to(track_click))
+            .route("/t/o/{token}.gif", web::get().to(track_open))
             .app_data(db_pool.clone())
             .app_data(email_client.clone())
             .app_data(base_url.clone())
+            .app_data(hmac_secret.clone())
+            .app_data(newsletter_layout.clone())
+            .app_data(subscription_settings.clone())
     })
     .listen(listener)?
     .run();
@@ -91,7 +159,7 @@ impl Application {
     pub async fn build(configuration: Settings) -> Result<Self, anyhow::Error> {
         let connection_pool = get_connection_pool(&configuration.database);
         let email_client = configuration.email_client.client();
-        let hmac_secret = configuration.application.hmac_secret;
+        let newsletter_layout = configuration.newsletter_layout.layout()?;
 
         let address = format!("{}:{}", configuration.application.host, configuration.application.port);
         let listener = TcpListener::bind(address)?;
@@ -101,9 +169,10 @@ impl Application {
             listener,
             connection_pool,
             email_client,
-            configuration.application.base_url,
-            hmac_secret,
+            configuration.application,
             configuration.redis_uri,
+            newsletter_layout,
+            configuration.subscriptions,
         ).await?;
 
         Ok(Self { port, server })
@@ -0,0 +1,94 @@
+use crate::{
+    configuration::Settings,
+    startup::get_connection_pool,
+};
+
+use sqlx::PgPool;
+use std::time::Duration;
+
+const TEN_MINUTES: u64 = 60 * 10;
+/// Expired tokens stay around for a while, so that late clicks on a confirmation link
+/// get the "link expired" page and its resend form rather than an error.
+const DAYS_KEPT_AFTER_EXPIRY: i32 = 7;
+
+pub async fn run_worker_until_stopped(
+    configuration: Settings
+) -> Result<(), anyhow::Error> {
+    let connection_pool = get_connection_pool(&configuration.database);
+
+    worker_loop(connection_pool, configuration.subscriptions.confirmation_link_ttl_in_hours).await
+}
+
+async fn worker_loop(
+    pool: PgPool,
+    ttl_in_hours: u32,
+) -> Result<(), anyhow::Error> {
+    loop {
+        remove_expired_subscription_tokens(&pool, ttl_in_hours).await?;
+        tokio::time::sleep(Duration::from_secs(TEN_MINUTES)).await;
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+pub async fn remove_expired_subscription_tokens(pool: &PgPool, ttl_in_hours: u32) -> Result<u64, anyhow::Error> {
+    let rows_affected = sqlx::query!(
+        r#"
+        DELETE FROM subscription_tokens
+        WHERE created_at < now() - make_interval(hours => $1, days => $2)
+        "#,
+        ttl_in_hours as i32,
+        DAYS_KEPT_AFTER_EXPIRY,
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::subscription_token_worker::remove_expired_subscription_tokens;
+
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    #[sqlx::test]
+    async fn removes_tokens_a_week_past_their_expiry(pool: PgPool) {
+        let subscriber_id = Uuid::new_v4();
+        sqlx::query!(
+            r#"
+            INSERT INTO subscriptions (id, email, name, subscribed_at, status)
+            VALUES ($1, 'ursula_le_guin@gmail.com', 'le guin', now(), 'pending_confirmation')
+            "#,
+            subscriber_id,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query!(
+            r#"
+            INSERT INTO subscription_tokens (subscription_token, subscriber_id, created_at)
+            VALUES ('fresh', $1, now()),
+                ('expired', $1, now() - interval '2 days'),
+                ('forgotten', $1, now() - interval '10 days')
+            "#,
+            subscriber_id,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let rows_affected = remove_expired_subscription_tokens(&pool, 24).await.unwrap();
+
+        assert_eq!(rows_affected, 1);
+        let tokens: Vec<String> = sqlx::query!(r#"SELECT subscription_token FROM subscription_tokens ORDER BY subscription_token"#)
+            .fetch_all(&pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| r.subscription_token)
+            .collect();
+        assert_eq!(tokens, ["expired", "fresh"]);
+    }
+}
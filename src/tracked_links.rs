@@ -0,0 +1,169 @@
+//! Finds the links of an issue that click tracking applies to, and rewrites them.
+//! Issue contents are sanitized or generated from Markdown, so a small scanner over
+//! `<a href="...">` is enough, no need for a full HTML parser.
+
+/// A link in an issue's HTML, in document order.
+struct Href {
+    /// Where the attribute value starts and ends in the HTML.
+    start: usize,
+    end: usize,
+    /// The attribute value with its entities decoded.
+    url: String,
+}
+
+/// Absolute http(s) links only: `mailto:` and relative links are left alone, and so are
+/// personalized links such as `{{ unsubscribe_url }}`, which differ for every subscriber.
+fn is_trackable(url: &str) -> bool {
+    let lowercase = url.to_ascii_lowercase();
+
+    (lowercase.starts_with("http://") || lowercase.starts_with("https://")) && !url.contains("{{")
+}
+
+/// The URLs that get tracked, their index is their position in `newsletter_links`.
+pub fn trackable_links(html: &str) -> Vec<String> {
+    hrefs(html)
+        .into_iter()
+        .map(|href| href.url)
+        .filter(|url| is_trackable(url))
+        .collect()
+}
+
+/// Replaces the `position`-th trackable link with what `rewrite` returns for it.
+/// `links` are the URLs registered when the issue was published: a link that doesn't
+/// match, e.g. because the content changed since, is left alone.
+pub fn rewrite_links(html: &str, links: &[String], mut rewrite: impl FnMut(usize) -> String) -> String {
+    let mut rewritten = String::with_capacity(html.len());
+    let mut copied_up_to = 0;
+    let mut position = 0;
+
+    for href in hrefs(html) {
+        if !is_trackable(&href.url) {
+            continue;
+        }
+
+        if links.get(position) == Some(&href.url) {
+            rewritten.push_str(&html[copied_up_to..href.start]);
+            rewritten.push_str(&rewrite(position));
+            copied_up_to = href.end;
+        }
+        position += 1;
+    }
+
+    rewritten.push_str(&html[copied_up_to..]);
+    rewritten
+}
+
+fn hrefs(html: &str) -> Vec<Href> {
+    let lowercase = html.to_ascii_lowercase();
+    let mut hrefs = Vec::new();
+    let mut offset = 0;
+
+    while let Some(tag_start) = lowercase[offset..].find("<a").map(|i| offset + i) {
+        offset = tag_start + 2;
+        if !lowercase[offset..].starts_with(|c: char| c.is_ascii_whitespace()) {
+            continue;
+        }
+
+        let tag_end = lowercase[offset..].find('>').map_or(html.len(), |i| offset + i);
+        if let Some(href) = href_in_tag(html, &lowercase, offset, tag_end) {
+            hrefs.push(href);
+        }
+        offset = tag_end;
+    }
+
+    hrefs
+}
+
+fn href_in_tag(html: &str, lowercase: &str, tag_start: usize, tag_end: usize) -> Option<Href> {
+    let mut offset = tag_start;
+
+    loop {
+        let attribute_start = offset + lowercase[offset..tag_end].find("href")?;
+        offset = attribute_start + "href".len();
+
+        // `data-href` or `hreflang` are other attributes.
+        let preceded_by_space = lowercase[..attribute_start].ends_with(|c: char| c.is_ascii_whitespace());
+        let value = lowercase[offset..tag_end].trim_start();
+        if !preceded_by_space || !value.starts_with('=') {
+            continue;
+        }
+
+        let value_start = tag_end - value[1..].trim_start().len();
+        let quote = html[value_start..].chars().next()?;
+        let (start, end) = if quote == '"' || quote == '\'' {
+            let start = value_start + 1;
+            (start, start + html[start..tag_end].find(quote)?)
+        } else {
+            let end = html[value_start..tag_end]
+                .find(|c: char| c.is_ascii_whitespace())
+                .map_or(tag_end, |i| value_start + i);
+            (value_start, end)
+        };
+
+        return Some(Href { start, end, url: decode_entities(&html[start..end]) });
+    }
+}
+
+/// The entities our own escaping, ammonia and the Markdown renderer produce.
+fn decode_entities(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&#x27;", "'")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tracked_links::{rewrite_links, trackable_links};
+
+    const HTML: &str = r#"<p><a href="https://example.com/a?x=1&amp;y=2">A</a>
+<a class="button" href='http://example.com/b'>B</a>
+<a href="mailto:editor@example.com">Mail</a>
+<a href="{{ unsubscribe_url }}">Unsubscribe</a>
+<a data-href="https://example.com/ignored" href="/relative">Relative</a>
+<abbr title="https://example.com/not-a-link">abbr</abbr>
+<A HREF="https://example.com/c">C</A></p>"#;
+
+    #[test]
+    fn only_absolute_http_links_are_tracked() {
+        assert_eq!(
+            trackable_links(HTML),
+            ["https://example.com/a?x=1&y=2", "http://example.com/b", "https://example.com/c"],
+        );
+    }
+
+    #[test]
+    fn tracked_links_are_rewritten_in_place() {
+        let links = trackable_links(HTML);
+
+        let rewritten = rewrite_links(HTML, &links, |position| format!("https://t.example.com/{}", position));
+
+        assert!(rewritten.contains(r#"<a href="https://t.example.com/0">A</a>"#));
+        assert!(rewritten.contains(r#"<a class="button" href='https://t.example.com/1'>B</a>"#));
+        assert!(rewritten.contains(r#"<A HREF="https://t.example.com/2">C</A>"#));
+        assert!(rewritten.contains(r#"<a href="mailto:editor@example.com">Mail</a>"#));
+        assert!(rewritten.contains(r#"<a href="{{ unsubscribe_url }}">Unsubscribe</a>"#));
+        assert!(rewritten.contains(r#"<a data-href="https://example.com/ignored" href="/relative">"#));
+    }
+
+    #[test]
+    fn links_that_were_not_registered_are_left_alone() {
+        let links = vec!["https://example.com/other".to_string(), "http://example.com/b".to_string()];
+
+        let rewritten = rewrite_links(HTML, &links, |position| format!("https://t.example.com/{}", position));
+
+        assert!(rewritten.contains(r#"href="https://example.com/a?x=1&amp;y=2""#));
+        assert!(rewritten.contains(r#"href='https://t.example.com/1'"#));
+        assert!(rewritten.contains(r#"HREF="https://example.com/c""#));
+    }
+
+    #[test]
+    fn html_without_links_is_unchanged() {
+        let html = "<p>No links <b>here</b>, a < b</p>";
+
+        assert_eq!(rewrite_links(html, &[], |_| unreachable!()), html);
+    }
+}
@@ -0,0 +1,131 @@
+use crate::helpers::{spawn_app, assert_is_redirect_to, create_confirmed_subscriber, create_unconfirmed_subscriber, TestApp};
+
+use zero2prod::domain::UnsubscribeToken;
+
+fn draft_body(title: &str) -> serde_json::Value {
+    serde_json::json!({
+        "title": title,
+        "markdown_content": "Read [the article](https://example.com/article).",
+        "track_opens": "true",
+    })
+}
+
+async fn unsubscribe_first_subscriber(app: &TestApp) {
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions ORDER BY email LIMIT 1")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+    let unsubscribe_token = UnsubscribeToken::generate(subscriber_id, &app.hmac_secret);
+
+    app.post_unsubscribe(unsubscribe_token.as_ref()).await;
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_analytics() {
+    let app = spawn_app().await;
+
+    let response = app.get_analytics().await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn the_dashboard_links_to_the_analytics() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let html_page = app.get_admin_dashboard_html().await;
+
+    assert!(html_page.contains(r#"<a href="/admin/analytics">Analytics</a>"#));
+}
+
+#[tokio::test]
+async fn issues_show_their_deliveries_opens_clicks_and_unsubscribes() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    app.publish_and_deliver(&draft_body("First issue")).await;
+    unsubscribe_first_subscriber(&app).await;
+    app.publish_and_deliver(&draft_body("Second issue")).await;
+
+    let first_issue_id = sqlx::query!("SELECT id FROM newsletter_issues WHERE title = 'First issue'")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions WHERE status = 'confirmed'")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+    sqlx::query!(
+        "INSERT INTO newsletter_opens VALUES ($1, $2, 3, now(), now())",
+        first_issue_id,
+        subscriber_id,
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    sqlx::query!(
+        "INSERT INTO newsletter_clicks VALUES ($1, 0, $2, now()), ($1, 0, $2, now())",
+        first_issue_id,
+        subscriber_id,
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let html_page = app.get_analytics_html().await;
+
+    // Newest first.
+    let second_issue = html_page.find("Second issue</a>").unwrap();
+    let first_issue = html_page.find("First issue</a>").unwrap();
+    assert!(second_issue < first_issue);
+
+    let first_row = &html_page[first_issue..];
+    let first_row = &first_row[..first_row.find("</tr>").unwrap()];
+    assert!(first_row.contains("<td>2</td>\n                        <td>2</td>\n                        <td>0</td>"));
+    assert!(first_row.contains("<td>1 (50%)</td>"));
+    assert!(first_row.contains("<td>1 (50%), 2 clicks</td>"));
+    // The subscriber who left was last sent the first issue.
+    assert!(first_row.contains("<td>1 (50%)</td>\n                    "));
+
+    let second_row = &html_page[second_issue..first_issue];
+    assert!(second_row.contains("<td>1</td>\n                        <td>1</td>\n                        <td>0</td>"));
+    assert!(second_row.contains("<td>0 (0%)</td>\n                    "));
+}
+
+#[tokio::test]
+async fn drafts_are_left_out() {
+    let app = spawn_app().await;
+    app.user_login().await;
+    app.create_newsletter_draft(&serde_json::json!({
+        "title": "Draft title",
+        "markdown_content": "Hello",
+    }))
+    .await;
+
+    let html_page = app.get_analytics_html().await;
+
+    assert!(!html_page.contains("Draft title"));
+    assert!(html_page.contains("No newsletter issues have been published yet."));
+}
+
+#[tokio::test]
+async fn the_growth_chart_counts_confirmed_subscribers_only() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    create_unconfirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    let html_page = app.get_analytics_html().await;
+
+    let today = chrono::Utc::now().date_naive();
+    assert!(html_page.contains("<svg "));
+    assert_eq!(html_page.matches("<rect ").count(), 90);
+    assert!(html_page.contains(&format!("<title>{}: 2 confirmations</title>", today)));
+}
@@ -0,0 +1,184 @@
+use crate::helpers::{spawn_app, assert_is_redirect_to, create_confirmed_subscriber, TestApp, titled_draft_body, publish_body};
+
+use uuid::Uuid;
+
+async fn issue_slug(app: &TestApp, newsletter_issue_id: &str) -> Option<String> {
+    sqlx::query!(
+        "SELECT slug FROM newsletter_issues WHERE id = $1",
+        Uuid::parse_str(newsletter_issue_id).unwrap(),
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .slug
+}
+
+#[tokio::test]
+async fn published_issues_get_a_slug_and_show_up_in_the_archive() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let mut draft_body = titled_draft_body("Big News: July's Edition!");
+    draft_body["html_content"] = "<p>Hi {{ name }}, newsletter body as HTML</p>".into();
+    // Nobody is subscribed, the issue is sent as soon as it's published.
+    let newsletter_issue_id = app.publish_draft(&draft_body).await;
+
+    assert_eq!(issue_slug(&app, &newsletter_issue_id).await.as_deref(), Some("big-news-july-s-edition"));
+
+    let html_page = app.get_archive_html().await;
+    assert!(html_page.contains(r#"<a href="/archive/big-news-july-s-edition">Big News: July&#x27;s Edition!</a>"#));
+
+    let response = app.get_archived_issue("big-news-july-s-edition").await;
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("<h1>Big News: July&#x27;s Edition!</h1>"));
+    assert!(html_page.contains("Published on "));
+    // Rendered for an anonymous reader, inside the sandboxed frame.
+    assert!(html_page.contains("&lt;p&gt;Hi reader, newsletter body as HTML&lt;/p&gt;"));
+}
+
+#[tokio::test]
+async fn the_archive_is_public() {
+    let app = spawn_app().await;
+    app.user_login().await;
+    app.publish_draft(&titled_draft_body("Newsletter title")).await;
+    app.post_logout().await;
+
+    let html_page = app.get_archive_html().await;
+    assert!(html_page.contains(r#"<a href="/archive/newsletter-title">Newsletter title</a>"#));
+
+    let response = app.get_archived_issue("newsletter-title").await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn issues_with_the_same_title_get_different_slugs() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let first_issue_id = app.publish_draft(&titled_draft_body("Weekly update")).await;
+    let second_issue_id = app.publish_draft(&titled_draft_body("Weekly update")).await;
+
+    let first_slug = issue_slug(&app, &first_issue_id).await.unwrap();
+    let second_slug = issue_slug(&app, &second_issue_id).await.unwrap();
+    assert_eq!(first_slug, "weekly-update");
+    assert_eq!(second_slug, format!("weekly-update-{}", &second_issue_id[..8]));
+
+    assert_eq!(app.get_archived_issue(&second_slug).await.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn issues_with_the_same_title_published_concurrently_get_different_slugs() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let draft_body = titled_draft_body("Weekly update");
+    let first_issue_id = app.create_newsletter_draft(&draft_body).await;
+    let second_issue_id = app.create_newsletter_draft(&draft_body).await;
+    let (first_publish_body, second_publish_body) = (publish_body(), publish_body());
+
+    let (first_response, second_response) = tokio::join!(
+        app.post_publish_newsletter(&first_issue_id, &first_publish_body),
+        app.post_publish_newsletter(&second_issue_id, &second_publish_body),
+    );
+
+    assert_eq!(first_response.status().as_u16(), 303);
+    assert_eq!(second_response.status().as_u16(), 303);
+    let first_slug = issue_slug(&app, &first_issue_id).await.unwrap();
+    let second_slug = issue_slug(&app, &second_issue_id).await.unwrap();
+    assert_ne!(first_slug, second_slug);
+}
+
+#[tokio::test]
+async fn drafts_are_not_in_the_archive() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let newsletter_issue_id = app
+        .create_newsletter_draft(&titled_draft_body("Secret draft"))
+        .await;
+
+    assert_eq!(issue_slug(&app, &newsletter_issue_id).await, None);
+    let html_page = app.get_archive_html().await;
+    assert!(!html_page.contains("Secret draft"));
+    assert!(html_page.contains("No issues have been published yet."));
+}
+
+#[tokio::test]
+async fn paused_issues_are_not_archived_before_their_send_time() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    let newsletter_issue_id = app
+        .create_newsletter_draft(&titled_draft_body("Future issue"))
+        .await;
+    app.post_publish_newsletter(&newsletter_issue_id, &serde_json::json!({
+        "idempotency_key": Uuid::new_v4().to_string(),
+        "send_at": "2099-01-05T09:00",
+        "timezone": "UTC",
+    }))
+    .await;
+    app.post_newsletter_action(&newsletter_issue_id, "pause").await;
+    app.post_logout().await;
+
+    let slug = issue_slug(&app, &newsletter_issue_id).await.unwrap();
+    assert_eq!(app.get_archived_issue(&slug).await.status().as_u16(), 404);
+    assert!(!app.get_archive_html().await.contains("Future issue"));
+    for feed in ["feed.rss", "feed.atom"] {
+        let feed = app.get_feed(feed, &[]).await.text().await.unwrap();
+        assert!(!feed.contains("Future issue"));
+    }
+}
+
+#[tokio::test]
+async fn issues_can_be_hidden_from_the_archive_and_shown_again() {
+    let app = spawn_app().await;
+    app.user_login().await;
+    let newsletter_issue_id = app.publish_draft(&titled_draft_body("Newsletter title")).await;
+
+    let response = app.post_archive_visibility(&newsletter_issue_id, true).await;
+    assert_is_redirect_to(&response, &format!("/admin/newsletters/{}", newsletter_issue_id));
+
+    let html_page = app.get_newsletter_issue_html(&newsletter_issue_id, None).await;
+    assert!(html_page.contains("The newsletter issue is hidden from the archive."));
+    assert!(html_page.contains("Show in the archive"));
+    assert!(!app.get_archive_html().await.contains("Newsletter title"));
+    assert_eq!(app.get_archived_issue("newsletter-title").await.status().as_u16(), 404);
+
+    app.post_archive_visibility(&newsletter_issue_id, false).await;
+
+    assert!(app.get_archive_html().await.contains("Newsletter title"));
+    assert_eq!(app.get_archived_issue("newsletter-title").await.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_hide_an_issue_from_the_archive() {
+    let app = spawn_app().await;
+    app.user_login().await;
+    let newsletter_issue_id = app.publish_draft(&titled_draft_body("Newsletter title")).await;
+    app.post_logout().await;
+
+    let response = app.post_archive_visibility(&newsletter_issue_id, true).await;
+
+    assert_is_redirect_to(&response, "/login");
+    assert!(app.get_archive_html().await.contains("Newsletter title"));
+}
+
+#[tokio::test]
+async fn unknown_slugs_are_not_found() {
+    let app = spawn_app().await;
+
+    let response = app.get_archived_issue("nothing-here").await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn the_home_page_links_to_the_archive() {
+    let app = spawn_app().await;
+
+    let html_page = app.api_client.get(&app.address).send().await.unwrap().text().await.unwrap();
+
+    assert!(html_page.contains(r#"<a href="/archive">"#));
+}
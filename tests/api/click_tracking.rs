@@ -0,0 +1,173 @@
+use crate::helpers::{spawn_app, create_confirmed_subscriber, TestApp};
+
+use uuid::Uuid;
+use zero2prod::domain::{ClickTrackingToken, TrackedClick, UnsubscribeToken};
+
+const MARKDOWN_CONTENT: &str = "\
+Read [the article](https://example.com/article?a=1&b=2), \
+write to [the editor](mailto:editor@example.com) \
+or [unsubscribe]({{ unsubscribe_url }}).";
+
+fn draft_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "markdown_content": MARKDOWN_CONTENT,
+    })
+}
+
+fn tracked_links(app: &TestApp, html_content: &str) -> Vec<String> {
+    let prefix = format!(r#"href="{}/t/c/"#, app.address);
+
+    html_content
+        .match_indices(&prefix)
+        .map(|(start, _)| {
+            let start = start + r#"href=""#.len();
+            let end = start + html_content[start..].find('"').unwrap();
+            html_content[start..end].to_owned()
+        })
+        .collect()
+}
+
+async fn n_clicks(app: &TestApp) -> i64 {
+    sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM newsletter_clicks"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count
+}
+
+#[tokio::test]
+async fn links_are_rewritten_to_a_redirect_that_records_clicks() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    let (newsletter_issue_id, html_contents) = app.publish_and_deliver(&draft_body()).await;
+
+    let html_content = &html_contents[0];
+    let links = tracked_links(&app, html_content);
+    assert_eq!(links.len(), 1);
+    assert!(!html_content.contains("https://example.com/article"));
+    // Other schemes and personalized links are left alone.
+    assert!(html_content.contains(r#"href="mailto:editor@example.com""#));
+    assert!(html_content.contains(&format!(r#"href="{}/subscriptions/unsubscribe?unsubscribe_token="#, app.address)));
+
+    let response = app.api_client.get(&links[0]).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 302);
+    assert_eq!(response.headers()["Location"], "https://example.com/article?a=1&b=2");
+    assert_eq!(response.headers()["Cache-Control"], "no-store, private");
+    app.api_client.get(&links[0]).send().await.unwrap();
+    assert_eq!(n_clicks(&app).await, 2);
+
+    let html_page = app.get_newsletter_issue_html(&newsletter_issue_id, None).await;
+    assert!(html_page.contains("<td>https://example.com/article?a=1&amp;b=2</td>"));
+    assert!(html_page.contains("<td>1</td>\n                        <td>2</td>"));
+}
+
+#[tokio::test]
+async fn the_stored_issue_keeps_its_original_links() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    let (newsletter_issue_id, _) = app.publish_and_deliver(&draft_body()).await;
+
+    let html_content = sqlx::query!(
+        "SELECT html_content FROM newsletter_issues WHERE id = $1",
+        Uuid::parse_str(&newsletter_issue_id).unwrap(),
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .html_content;
+    assert!(html_content.contains(r#"href="https://example.com/article?a=1&amp;b=2""#));
+    assert!(!html_content.contains("/t/c/"));
+}
+
+#[tokio::test]
+async fn subscribers_who_opted_out_get_the_original_links() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    sqlx::query!("UPDATE subscriptions SET do_not_track = true")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.user_login().await;
+
+    let (_, html_contents) = app.publish_and_deliver(&draft_body()).await;
+
+    assert_eq!(tracked_links(&app, &html_contents[0]), Vec::<String>::new());
+    assert!(html_contents[0].contains(r#"href="https://example.com/article?a=1&amp;b=2""#));
+}
+
+#[tokio::test]
+async fn opting_out_forgets_past_clicks_and_stops_recording() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+    let (_, html_contents) = app.publish_and_deliver(&draft_body()).await;
+    let link = tracked_links(&app, &html_contents[0]).remove(0);
+    app.api_client.get(&link).send().await.unwrap();
+    assert_eq!(n_clicks(&app).await, 1);
+
+    let unsubscribe_token = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .map(|r| UnsubscribeToken::generate(r.id, &app.hmac_secret))
+        .unwrap();
+    app.api_client
+        .post(format!("{}/subscriptions/do_not_track?unsubscribe_token={}", app.address, unsubscribe_token.as_ref()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(n_clicks(&app).await, 0);
+
+    // Links in emails sent before opting out keep working.
+    let response = app.api_client.get(&link).send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 302);
+    assert_eq!(n_clicks(&app).await, 0);
+}
+
+#[tokio::test]
+async fn invalid_tokens_are_not_redirected() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/t/c/forged", app.address))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 404);
+    assert!(response.headers().get("Location").is_none());
+}
+
+#[tokio::test]
+async fn tokens_for_unregistered_links_are_not_redirected() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+    let (newsletter_issue_id, _) = app.publish_and_deliver(&draft_body()).await;
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+
+    let click = TrackedClick {
+        newsletter_issue_id: Uuid::parse_str(&newsletter_issue_id).unwrap(),
+        subscriber_id,
+        position: 1,
+    };
+    let token = ClickTrackingToken::generate(&click, &app.hmac_secret);
+    let response = app
+        .api_client
+        .get(format!("{}/t/c/{}", app.address, token.as_ref()))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 404);
+    assert_eq!(n_clicks(&app).await, 0);
+}
@@ -0,0 +1,248 @@
+use crate::helpers::{spawn_app, assert_is_redirect_to, create_confirmed_subscriber, TestApp};
+
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn publish_newsletter_with_retries(app: &TestApp, n_retries: u8) {
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+        "n_retries": n_retries,
+        "retry_base_delay_in_secs": 0,
+        "retry_max_delay_in_secs": 0,
+    });
+
+    app.publish_newsletter(&newsletter_request_body).await;
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_delivery_failures() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/admin/newsletters/failures", &app.address))
+        .send()
+        .await
+        .unwrap();
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn exhausted_deliveries_are_moved_to_failures() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter_with_retries(&app, 2).await;
+    app.dispatch_all_pending_emails().await;
+
+    let n_queued = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_queued, 0);
+
+    let failure = sqlx::query!("SELECT subscriber_email, n_attempts, last_error FROM issue_delivery_failures")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    assert_eq!(failure.n_attempts, 2);
+    assert!(failure.last_error.contains("(500): Internal Server Error"));
+
+    let delivery = sqlx::query!("SELECT status, n_attempts FROM newsletter_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(delivery.status, "failed");
+    assert_eq!(delivery.n_attempts, 2);
+
+    let html_page = app.get_delivery_failures_html().await;
+    assert!(html_page.contains(&failure.subscriber_email));
+}
+
+#[tokio::test]
+async fn deliveries_to_invalid_emails_are_moved_to_failures() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO subscriptions
+        (id, email, name, subscribed_at, status)
+        VALUES
+        (gen_random_uuid(), 'me.mail.com', 'me', now(), 'confirmed')
+        "#
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    publish_newsletter_with_retries(&app, 3).await;
+    app.dispatch_all_pending_emails().await;
+
+    let failure = sqlx::query!("SELECT subscriber_email, n_attempts FROM issue_delivery_failures")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    assert_eq!(failure.subscriber_email, "me.mail.com");
+    assert_eq!(failure.n_attempts, 1);
+}
+
+#[tokio::test]
+async fn a_failed_delivery_can_be_requeued() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    let guard = Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    publish_newsletter_with_retries(&app, 1).await;
+    app.dispatch_all_pending_emails().await;
+    drop(guard);
+
+    let failure = sqlx::query!("SELECT newsletter_issue_id, subscriber_email FROM issue_delivery_failures")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = app
+        .post_requeue_delivery_failure(&serde_json::json!({
+            "newsletter_issue_id": failure.newsletter_issue_id,
+            "subscriber_email": failure.subscriber_email,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters/failures");
+
+    let html_page = app.get_delivery_failures_html().await;
+    assert!(html_page.contains("<p><i>The delivery has been requeued.</i></p>"));
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.dispatch_all_pending_emails().await;
+
+    let n_failures = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM issue_delivery_failures"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_failures, 0);
+
+    let delivery = sqlx::query!("SELECT status FROM newsletter_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(delivery.status, "sent");
+}
+
+#[tokio::test]
+async fn all_failed_deliveries_can_be_requeued_at_once() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter_with_retries(&app, 1).await;
+    app.dispatch_all_pending_emails().await;
+
+    let response = app.post_requeue_all_delivery_failures().await;
+    assert_is_redirect_to(&response, "/admin/newsletters/failures");
+
+    let html_page = app.get_delivery_failures_html().await;
+    assert!(html_page.contains("<p><i>2 deliveries have been requeued.</i></p>"));
+
+    let task = sqlx::query!(r#"SELECT COUNT(*) as "count!", MIN(n_retries) as "n_retries!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(task.count, 2);
+    assert_eq!(task.n_retries, 1);
+}
+
+#[tokio::test]
+async fn a_failure_that_is_queued_already_is_kept_when_requeueing() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter_with_retries(&app, 1).await;
+    app.dispatch_all_pending_emails().await;
+
+    let failure = sqlx::query!("SELECT newsletter_issue_id, subscriber_email FROM issue_delivery_failures")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (newsletter_issue_id, subscriber_email, n_retries, execute_after)
+        VALUES ($1, $2, 1, now() + interval '1 hour')
+        "#,
+        failure.newsletter_issue_id,
+        failure.subscriber_email,
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let response = app
+        .post_requeue_delivery_failure(&serde_json::json!({
+            "newsletter_issue_id": failure.newsletter_issue_id,
+            "subscriber_email": failure.subscriber_email,
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/newsletters/failures");
+
+    let html_page = app.get_delivery_failures_html().await;
+    assert!(html_page.contains("<p><i>The delivery could not be requeued.</i></p>"));
+
+    let n_failures = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM issue_delivery_failures"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_failures, 1);
+
+    let delivery = sqlx::query!("SELECT status FROM newsletter_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(delivery.status, "failed");
+}
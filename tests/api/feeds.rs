@@ -0,0 +1,150 @@
+use crate::helpers::{spawn_app, titled_draft_body};
+
+use uuid::Uuid;
+
+/// The feeds escape the HTML of the issues once more.
+fn fish_and_chips() -> serde_json::Value {
+    let mut draft_body = titled_draft_body("Fish & chips");
+    draft_body["html_content"] = "<p>Hi {{ name }} & co</p>".into();
+    draft_body
+}
+
+#[tokio::test]
+async fn the_rss_feed_lists_archived_issues() {
+    let app = spawn_app().await;
+    app.user_login().await;
+    app.publish_draft(&fish_and_chips()).await;
+
+    let response = app.get_feed("feed.rss", &[]).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["Content-Type"], "application/rss+xml; charset=utf-8");
+    let feed = response.text().await.unwrap();
+    assert!(feed.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+    assert!(feed.contains("<title>Fish &amp; chips</title>"));
+    assert!(feed.contains("/archive/fish-chips</link>"));
+    assert!(feed.contains("<pubDate>"));
+    assert!(feed.contains(" +0000</pubDate>"));
+    // The HTML is escaped once for the feed, its own entities twice.
+    assert!(feed.contains("<description>&lt;p&gt;Hi reader &amp;amp; co&lt;/p&gt;</description>"));
+}
+
+#[tokio::test]
+async fn the_atom_feed_lists_archived_issues() {
+    let app = spawn_app().await;
+    app.user_login().await;
+    app.publish_draft(&fish_and_chips()).await;
+
+    let response = app.get_feed("feed.atom", &[]).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["Content-Type"], "application/atom+xml; charset=utf-8");
+    let feed = response.text().await.unwrap();
+    assert!(feed.contains(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#));
+    assert!(feed.contains("<title>Fish &amp; chips</title>"));
+    assert!(feed.contains("/archive/fish-chips</id>"));
+    assert!(feed.contains("<published>"));
+    assert!(feed.contains(r#"<content type="html">&lt;p&gt;Hi reader &amp;amp; co&lt;/p&gt;</content>"#));
+}
+
+#[tokio::test]
+async fn drafts_and_hidden_issues_are_not_in_the_feeds() {
+    let app = spawn_app().await;
+    app.user_login().await;
+    app.create_newsletter_draft(&titled_draft_body("Secret draft")).await;
+    let hidden_issue_id = app.publish_draft(&titled_draft_body("Hidden issue")).await;
+    app.post_archive_visibility(&hidden_issue_id, true).await;
+
+    for feed in ["feed.rss", "feed.atom"] {
+        let feed = app.get_feed(feed, &[]).await.text().await.unwrap();
+
+        assert!(!feed.contains("Secret draft"));
+        assert!(!feed.contains("Hidden issue"));
+    }
+}
+
+#[tokio::test]
+async fn an_empty_feed_is_still_valid() {
+    let app = spawn_app().await;
+
+    let response = app.get_feed("feed.atom", &[]).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.headers().get("Last-Modified").is_none());
+    let feed = response.text().await.unwrap();
+    assert!(feed.contains("<updated>1970-01-01T00:00:00+00:00</updated>"));
+    assert!(!feed.contains("<entry>"));
+}
+
+#[tokio::test]
+async fn unchanged_feeds_are_not_sent_again_for_a_matching_etag() {
+    let app = spawn_app().await;
+    app.user_login().await;
+    app.publish_draft(&titled_draft_body("Newsletter title")).await;
+
+    for feed in ["feed.rss", "feed.atom"] {
+        let response = app.get_feed(feed, &[]).await;
+        let etag = response.headers()["ETag"].to_str().unwrap().to_owned();
+
+        let response = app.get_feed(feed, &[("If-None-Match", &etag)]).await;
+
+        assert_eq!(response.status().as_u16(), 304);
+        assert_eq!(response.headers()["ETag"].to_str().unwrap(), etag);
+        assert!(response.text().await.unwrap().is_empty());
+    }
+}
+
+#[tokio::test]
+async fn a_new_issue_changes_the_etag() {
+    let app = spawn_app().await;
+    app.user_login().await;
+    app.publish_draft(&titled_draft_body("First issue")).await;
+    let response = app.get_feed("feed.rss", &[]).await;
+    let etag = response.headers()["ETag"].to_str().unwrap().to_owned();
+
+    app.publish_draft(&titled_draft_body("Second issue")).await;
+    let response = app.get_feed("feed.rss", &[("If-None-Match", &etag)]).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_ne!(response.headers()["ETag"].to_str().unwrap(), etag);
+    assert!(response.text().await.unwrap().contains("Second issue"));
+}
+
+#[tokio::test]
+async fn unchanged_feeds_are_not_sent_again_since_their_last_modification() {
+    let app = spawn_app().await;
+    app.user_login().await;
+    app.publish_draft(&titled_draft_body("Newsletter title")).await;
+
+    let response = app.get_feed("feed.atom", &[]).await;
+    let last_modified = response.headers()["Last-Modified"].to_str().unwrap().to_owned();
+
+    let response = app.get_feed("feed.atom", &[("If-Modified-Since", &last_modified)]).await;
+    assert_eq!(response.status().as_u16(), 304);
+
+    let response = app.get_feed("feed.atom", &[("If-Modified-Since", "Thu, 01 Jan 2015 00:00:00 GMT")]).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn hiding_an_issue_counts_as_a_modification() {
+    let app = spawn_app().await;
+    app.user_login().await;
+    let newsletter_issue_id = app.publish_draft(&titled_draft_body("Newsletter title")).await;
+    // Backdate the publication, HTTP dates only have whole seconds.
+    sqlx::query!(
+        "UPDATE newsletter_issues SET published_at = now() - interval '1 hour' WHERE id = $1",
+        Uuid::parse_str(&newsletter_issue_id).unwrap(),
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    let response = app.get_feed("feed.rss", &[]).await;
+    let last_modified = response.headers()["Last-Modified"].to_str().unwrap().to_owned();
+
+    app.post_archive_visibility(&newsletter_issue_id, true).await;
+    let response = app.get_feed("feed.rss", &[("If-Modified-Since", &last_modified)]).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(!response.text().await.unwrap().contains("Newsletter title"));
+}
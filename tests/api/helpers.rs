@@ -1,13 +1,17 @@
+use fake::Fake;
+use fake::faker::{internet::en::SafeEmail, name::en::Name};
 use once_cell::sync::Lazy;
 use secrecy::{Secret, ExposeSecret};
 use sqlx::{Connection, Executor, PgConnection, PgPool};
+use std::sync::Arc;
 use uuid::Uuid;
-use wiremock::MockServer;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
 
 use zero2prod::authentication::compute_password_hash;
-use zero2prod::configuration::{get_configuration, DatabaseSettings};
-use zero2prod::email_client::EmailClient;
-use zero2prod::issue_delivery_worker::{try_execute_task, ExecutionOutcome, handle_worker_error};
+use zero2prod::configuration::{get_configuration, DatabaseSettings, EmailTransportKind, Settings};
+use zero2prod::email_client::EmailTransport;
+use zero2prod::issue_delivery_worker::{enqueue_due_issues, try_execute_task, ExecutionOutcome, handle_worker_error};
 use zero2prod::startup::{Application, get_connection_pool};
 use zero2prod::telemetry::{get_subscriber, init_subscriber};
 
@@ -18,9 +22,14 @@ pub struct TestApp {
     pub inbox_id: String,
     pub port: u16,
     pub api_client: reqwest::Client,
-    pub email_client: EmailClient,
+    pub email_client: Arc<dyn EmailTransport>,
+    pub hmac_secret: Secret<String>,
+    pub configuration: Settings,
 }
 
+/// What Firefox sends when following a link.
+pub const BROWSER_ACCEPT: &str = "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8";
+
 pub struct ConfirmationLinks {
     pub html: reqwest::Url,
     pub plain_text: reqwest::Url,
@@ -29,7 +38,7 @@ pub struct ConfirmationLinks {
 impl TestApp {
     pub async fn post_subscriptions(&self, body: String) -> reqwest::Response {
         self.api_client
-            .post(&format!("{}/subscriptions", &self.address))
+            .post(format!("{}/subscriptions", &self.address))
             .header("Content-Type", "application/x-www-form-urlencoded")
             .body(body)
             .send()
@@ -37,6 +46,57 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_subscriptions_json(&self, body: &serde_json::Value) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/subscriptions", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Posts the signup form like a browser does, asking for HTML.
+    pub async fn post_subscriptions_from_browser(&self, body: String) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/subscriptions", &self.address))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .header("Accept", BROWSER_ACCEPT)
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Follows a confirmation link like a browser does, asking for HTML.
+    pub async fn get_confirmation_from_browser(&self, confirmation_link: reqwest::Url) -> reqwest::Response {
+        self.api_client
+            .get(confirmation_link)
+            .header("Accept", BROWSER_ACCEPT)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_home_html(&self) -> String {
+        self.api_client
+            .get(&self.address)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_resend_confirmation(&self, email: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/subscriptions/resend_confirmation", &self.address))
+            .form(&[("email", email)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub fn get_confirmation_links(
         &self,
         email_request: &wiremock::Request
@@ -61,8 +121,8 @@ impl TestApp {
             confirmation_link.set_port(Some(self.port)).unwrap();
             confirmation_link
         };
-        let html = get_link(&body["html"].as_str().unwrap());
-        let plain_text = get_link(&body["text"].as_str().unwrap());
+        let html = get_link(body["html"].as_str().unwrap());
+        let plain_text = get_link(body["text"].as_str().unwrap());
 
         ConfirmationLinks {
             html,
@@ -75,7 +135,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.api_client
-            .post(&format!("{}/admin/newsletters", &self.address))
+            .post(format!("{}/admin/newsletters", &self.address))
             .form(body)
             .send()
             .await
@@ -107,7 +167,7 @@ impl TestApp {
     {
 
         self.api_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
             .form(body)
             .send()
             .await
@@ -127,7 +187,7 @@ impl TestApp {
 
     pub async fn get_login_html(&self) -> String {
         self.api_client
-            .get(&format!("{}/login", &self.address))
+            .get(format!("{}/login", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -138,7 +198,7 @@ impl TestApp {
 
     pub async fn get_admin_dashboard(&self) -> reqwest::Response { 
         self.api_client
-            .get(&format!("{}/admin/dashboard", &self.address))
+            .get(format!("{}/admin/dashboard", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -148,9 +208,21 @@ impl TestApp {
         self.get_admin_dashboard().await.text().await.unwrap()
     }
 
+    pub async fn get_analytics(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/analytics", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_analytics_html(&self) -> String {
+        self.get_analytics().await.text().await.unwrap()
+    }
+
     pub async fn get_change_password(&self) -> reqwest::Response {
         self.api_client
-            .get(&format!("{}/admin/password", &self.address))
+            .get(format!("{}/admin/password", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -161,7 +233,7 @@ impl TestApp {
         T: serde::Serialize,
     {
         self.api_client
-            .post(&format!("{}/admin/password", &self.address))
+            .post(format!("{}/admin/password", &self.address))
             .form(body)
             .send()
             .await
@@ -174,27 +246,179 @@ impl TestApp {
 
     pub async fn post_logout(&self) -> reqwest::Response {
         self.api_client
-            .post(&format!("{}/admin/logout", &self.address))
+            .post(format!("{}/admin/logout", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
-    pub async fn post_publish_newsletter<T>(&self, body: &T) -> reqwest::Response
+    pub async fn post_publish_newsletter<T>(&self, newsletter_issue_id: &str, body: &T) -> reqwest::Response
     where
         T: serde::Serialize,
     {
         self.api_client
-            .post(&format!("{}/admin/newsletters", &self.address))
+            .post(format!("{}/admin/newsletters/{}/publish", &self.address, newsletter_issue_id))
             .form(body)
             .send()
             .await
             .expect("faild to execute request.")
     }
 
+    /// Saves a draft and returns its id.
+    pub async fn create_newsletter_draft<T>(&self, body: &T) -> String
+    where
+        T: serde::Serialize,
+    {
+        let response = self.post_newsletters(body).await;
+
+        newsletter_draft_id(&response).expect("Failed to create a newsletter draft.")
+    }
+
+    /// Saves `draft_body` as a draft, publishes it right away and returns its id.
+    pub async fn publish_draft<T>(&self, draft_body: &T) -> String
+    where
+        T: serde::Serialize,
+    {
+        let newsletter_issue_id = self.create_newsletter_draft(draft_body).await;
+        self.post_publish_newsletter(&newsletter_issue_id, &publish_body()).await;
+
+        newsletter_issue_id
+    }
+
+    /// Publishes `draft_body` to every confirmed subscriber and returns its id along with
+    /// the HTML of every email that was sent.
+    pub async fn publish_and_deliver<T>(&self, draft_body: &T) -> (String, Vec<String>)
+    where
+        T: serde::Serialize,
+    {
+        let n_sent_before = self.email_server.received_requests().await.unwrap().len();
+        Mock::given(path(format!("/api/send/{}", self.inbox_id)))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&self.email_server)
+            .await;
+
+        let newsletter_issue_id = self.publish_draft(draft_body).await;
+        self.dispatch_all_pending_emails().await;
+
+        let html_contents = self.email_server.received_requests().await.unwrap()[n_sent_before..]
+            .iter()
+            .map(|request| {
+                let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+                body["html"].as_str().unwrap().to_owned()
+            })
+            .collect();
+
+        (newsletter_issue_id, html_contents)
+    }
+
+    /// Saves a draft with the content in `body` and publishes it with the rest of `body`.
+    /// The response of the draft creation is returned if it fails.
+    pub async fn publish_newsletter<T>(&self, body: &T) -> reqwest::Response
+    where
+        T: serde::Serialize,
+    {
+        let response = self.post_newsletters(body).await;
+
+        match newsletter_draft_id(&response) {
+            Some(newsletter_issue_id) => self.post_publish_newsletter(&newsletter_issue_id, body).await,
+            None => response,
+        }
+    }
+
+    pub async fn get_edit_newsletter(&self, newsletter_issue_id: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/newsletters/{}/edit", &self.address, newsletter_issue_id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_edit_newsletter<T>(&self, newsletter_issue_id: &str, body: &T) -> reqwest::Response
+    where
+        T: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/newsletters/{}/edit", &self.address, newsletter_issue_id))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_preview_newsletter_html(&self, newsletter_issue_id: &str) -> String {
+        self.api_client
+            .get(format!("{}/admin/newsletters/{}/preview", &self.address, newsletter_issue_id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_send_test_newsletter<T>(&self, newsletter_issue_id: &str, body: &T) -> reqwest::Response
+    where
+        T: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/newsletters/{}/test", &self.address, newsletter_issue_id))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// `action` is one of `pause`, `resume` or `cancel`.
+    pub async fn post_newsletter_action(&self, newsletter_issue_id: &str, action: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/newsletters/{}/{}", &self.address, newsletter_issue_id, action))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_archive_visibility(&self, newsletter_issue_id: &str, hidden_from_archive: bool) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/newsletters/{}/archive", &self.address, newsletter_issue_id))
+            .form(&serde_json::json!({ "hidden_from_archive": hidden_from_archive }))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_archive_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/archive", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_archived_issue(&self, slug: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/archive/{}", &self.address, slug))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// `feed` is `feed.rss` or `feed.atom`.
+    pub async fn get_feed(&self, feed: &str, headers: &[(&str, &str)]) -> reqwest::Response {
+        let mut request = self.api_client.get(format!("{}/{}", &self.address, feed));
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
     pub async fn get_publish_newsletter_html(&self) -> String {
         self.api_client
-            .get(&format!("{}/admin/newsletters", &self.address))
+            .get(format!("{}/admin/newsletters", &self.address))
             .send()
             .await
             .expect("failed to exeucte request.")
@@ -203,17 +427,98 @@ impl TestApp {
             .expect("Failed to get html page")
     }
 
+    pub async fn get_delivery_failures_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/newsletters/failures", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_requeue_delivery_failure<T>(&self, body: &T) -> reqwest::Response
+    where
+        T: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/newsletters/failures/requeue", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_requeue_all_delivery_failures(&self) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/newsletters/failures/requeue_all", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_newsletter_history(&self, page: Option<u32>) -> reqwest::Response {
+        let mut request = self.api_client
+            .get(format!("{}/admin/newsletters/history", &self.address));
+
+        if let Some(page) = page {
+            request = request.query(&[("page", page)]);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn get_newsletter_history_html(&self, page: Option<u32>) -> String {
+        self.get_newsletter_history(page).await.text().await.unwrap()
+    }
+
+    pub async fn get_newsletter_issue(&self, newsletter_issue_id: &str, search: Option<&str>) -> reqwest::Response {
+        let mut request = self.api_client
+            .get(format!("{}/admin/newsletters/{}", &self.address, newsletter_issue_id));
+
+        if let Some(search) = search {
+            request = request.query(&[("search", search)]);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn get_newsletter_issue_html(&self, newsletter_issue_id: &str, search: Option<&str>) -> String {
+        self.get_newsletter_issue(newsletter_issue_id, search).await.text().await.unwrap()
+    }
+
+    pub async fn get_unsubscribe(&self, unsubscribe_token: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/subscriptions/unsubscribe", &self.address))
+            .query(&[("unsubscribe_token", unsubscribe_token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_unsubscribe(&self, unsubscribe_token: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/subscriptions/unsubscribe", &self.address))
+            .query(&[("unsubscribe_token", unsubscribe_token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn dispatch_all_pending_emails(&self) {
+        enqueue_due_issues(&self.db_pool)
+            .await
+            .expect("Failed to enqueue the deliveries of due issues.");
+
         loop {
-            let outcome = try_execute_task(&self.db_pool, &self.email_client)
+            let outcome = try_execute_task(&self.db_pool, self.email_client.as_ref(), &self.address, &self.hmac_secret)
                 .await;
 
             match outcome { 
                 Ok(ExecutionOutcome::EmptyQueue) => break,
                 Ok(ExecutionOutcome::TaskCompleted) => {},
-                Err(error_type) => handle_worker_error(&self.db_pool, error_type)
-                    .await
-                    .expect("Failed to handle worker error"),
+                Err(error_type) => handle_worker_error(error_type).await,
             }
         }
     }
@@ -261,9 +566,10 @@ pub async fn spawn_app() -> TestApp {
     configuration.database.database_name = format!(
         "{}_test_{}",
         configuration.database.database_name,
-        Uuid::new_v4().to_string()
+        Uuid::new_v4()
     );
     configuration.application.port = 0;
+    configuration.email_client.transport = EmailTransportKind::Mailtrap;
     configuration.email_client.base_url = email_server.uri();
     configuration.email_client.inbox_id = Secret::new(Uuid::new_v4().to_string());
 
@@ -273,7 +579,7 @@ pub async fn spawn_app() -> TestApp {
 
     let address = format!("http://127.0.0.1:{}", application.port());
     let application_port = application.port();
-    let _ = tokio::spawn(application.run_until_stopped());
+    tokio::spawn(application.run_until_stopped());
 
     let api_client = reqwest::Client::builder()
         .redirect(reqwest::redirect::Policy::none())
@@ -286,13 +592,97 @@ pub async fn spawn_app() -> TestApp {
         api_client,
         db_pool: get_connection_pool(&configuration.database),
         inbox_id: configuration.email_client.inbox_id.expose_secret().clone(),
-        email_client: configuration.email_client.client(),
+        email_client: configuration.email_client.clone().client(),
+        hmac_secret: configuration.application.hmac_secret.clone(),
         email_server,
         port: application_port,
+        configuration,
     }
 }
 
+/// Drafts are saved with a redirect to their preview page.
+fn newsletter_draft_id(response: &reqwest::Response) -> Option<String> {
+    response
+        .headers()
+        .get("Location")
+        .and_then(|l| l.to_str().ok())
+        .and_then(|l| l.strip_prefix("/admin/newsletters/"))
+        .and_then(|l| l.strip_suffix("/preview"))
+        .map(str::to_owned)
+}
+
 pub fn assert_is_redirect_to(response: &reqwest::Response, location: &str) {
     assert_eq!(response.status().as_u16(), 303);
     assert_eq!(response.headers().get("Location").unwrap(), location)
 }
+
+pub fn draft_body() -> serde_json::Value {
+    titled_draft_body("Newsletter title")
+}
+
+pub fn titled_draft_body(title: &str) -> serde_json::Value {
+    serde_json::json!({
+        "title": title,
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+    })
+}
+
+/// Publishes a draft right away, with a fresh idempotency key.
+pub fn publish_body() -> serde_json::Value {
+    serde_json::json!({
+        "idempotency_key": Uuid::new_v4().to_string(),
+    })
+}
+
+pub async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
+    let name: String = Name().fake();
+    let email: String = SafeEmail().fake();
+
+    let body = serde_urlencoded::to_string(serde_json::json!({
+        "name": name,
+        "email": email,
+    }))
+    .unwrap();
+
+    let _mock_guard = Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .named("Create unconfirmed subscriber")
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    app.post_subscriptions(body)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let email_request = &app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+
+    app.get_confirmation_links(email_request)
+}
+
+pub async fn create_confirmed_subscriber(app: &TestApp) {
+    let confirmation_link = create_unconfirmed_subscriber(app).await;
+
+    reqwest::get(confirmation_link.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+/// Backdates the confirmation links, so that a new one can be sent.
+pub async fn wait_out_the_cooldown(app: &TestApp) {
+    sqlx::query!("UPDATE subscription_tokens SET created_at = now() - interval '10 minutes'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+}
@@ -1,8 +1,26 @@
 mod admin_dashboard;
+mod analytics;
+mod archive;
 mod change_password;
+mod click_tracking;
+mod delivery_failures;
+mod feeds;
 mod health_check;
 mod helpers;
 mod login;
 mod newsletter;
+mod newsletter_controls;
+mod newsletter_drafts;
+mod newsletter_history;
+mod newsletter_issue;
+mod newsletter_markdown;
+mod newsletter_personalization;
+mod newsletter_sanitization;
+mod open_tracking;
+mod newsletter_send_test;
+mod scheduled_newsletters;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_json;
+mod subscriptions_resend;
+mod subscriptions_unsubscribe;
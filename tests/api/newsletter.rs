@@ -1,7 +1,7 @@
-use crate::helpers::{spawn_app, TestApp, ConfirmationLinks, assert_is_redirect_to};
+use crate::helpers::{spawn_app, assert_is_redirect_to, create_confirmed_subscriber, create_unconfirmed_subscriber};
+
+use zero2prod::issue_delivery_worker::run_worker_until_stopped;
 
-use fake::Fake;
-use fake::faker::{internet::en::SafeEmail, name::en::Name};
 use std::time::Duration;
 use uuid::Uuid;
 use wiremock::matchers::{any, method, path};
@@ -33,9 +33,11 @@ async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() {
 
     app.post_login(&body).await;
 
-    let response = app.post_newsletters(&newsletter_request_body).await;
+    let response = app.publish_newsletter(&newsletter_request_body).await;
 
-    assert_is_redirect_to(&response, "/admin/newsletters");
+    assert_eq!(response.status().as_u16(), 303);
+    let location = response.headers().get("Location").unwrap().to_str().unwrap();
+    assert!(location.starts_with("/admin/newsletters/"));
 }
 
 #[tokio::test]
@@ -57,16 +59,16 @@ async fn retries_are_exhausted_for_newsletter() {
         "html_content": "<p>Newsletter body as HTML</p>",
         "idempotency_key": Uuid::new_v4().to_string(),
         "n_retries": 3,
-        "execute_after_in_secs": 0,
+        "retry_base_delay_in_secs": 0,
     });
 
     app.user_login().await;
 
-    let response = app.post_newsletters(&newsletter_request_body).await;
+    let response = app.publish_newsletter(&newsletter_request_body).await;
 
     app.dispatch_all_pending_emails().await;
 
-    assert_is_redirect_to(&response, "/admin/newsletters");
+    assert_eq!(response.status().as_u16(), 303);
 }
 
 #[tokio::test]
@@ -88,16 +90,16 @@ async fn retry_is_not_triggered_immediately() {
         "html_content": "<p>Newsletter body as HTML</p>",
         "idempotency_key": Uuid::new_v4().to_string(),
         "n_retries": 3,
-        "execute_after_in_secs": 60,
+        "retry_base_delay_in_secs": 60,
     });
 
     app.user_login().await;
 
-    let response = app.post_newsletters(&newsletter_request_body).await;
+    let response = app.publish_newsletter(&newsletter_request_body).await;
 
     app.dispatch_all_pending_emails().await;
 
-    assert_is_redirect_to(&response, "/admin/newsletters");
+    assert_eq!(response.status().as_u16(), 303);
 }
 
 #[tokio::test]
@@ -129,16 +131,16 @@ async fn retry_is_not_triggered_for_invalid_emails() {
         "html_content": "<p>Newsletter body as HTML</p>",
         "idempotency_key": Uuid::new_v4().to_string(),
         "n_retries": 3,
-        "execute_after_in_secs": 0,
+        "retry_base_delay_in_secs": 0,
     });
 
     app.user_login().await;
 
-    let response = app.post_newsletters(&newsletter_request_body).await;
+    let response = app.publish_newsletter(&newsletter_request_body).await;
 
     app.dispatch_all_pending_emails().await;
 
-    assert_is_redirect_to(&response, "/admin/newsletters");
+    assert_eq!(response.status().as_u16(), 303);
 }
 
 #[tokio::test]
@@ -161,11 +163,11 @@ async fn newsletters_are_delivered_to_confirmed_subscribers() {
     });
 
     app.user_login().await;
-    let response = app.post_newsletters(&newsletter_request_body).await;
+    let response = app.publish_newsletter(&newsletter_request_body).await;
 
     app.dispatch_all_pending_emails().await;
 
-    assert_is_redirect_to(&response, "/admin/newsletters");
+    assert_eq!(response.status().as_u16(), 303);
 }
 
 #[tokio::test]
@@ -189,11 +191,11 @@ async fn newsletters_returns_400_for_invalid_data() {
         ),
         (
             serde_json::json!({
-                "title": "Newsletter!",
+                "title": " ",
                 "text_content": "Newsletter body as plain text",
                 "html_content": "<p>Newsletter body as HTML</p>",
             }),
-            "The idempotency key cannot be empty",
+            "empty title",
         ),
     ];
 
@@ -216,6 +218,21 @@ async fn newsletters_returns_400_for_invalid_data() {
     };
 }
 
+#[tokio::test]
+async fn publishing_without_an_idempotency_key_returns_400() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let response = app.publish_newsletter(&serde_json::json!({
+        "title": "Newsletter!",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+    }))
+    .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
 #[tokio::test]
 async fn unauthorized_user_is_rejected() {
     let app = spawn_app().await;
@@ -253,20 +270,23 @@ async fn newsletter_creation_is_idempotent() {
         "idempotency_key": Uuid::new_v4().to_string(),
     });
 
-    let response = app.post_publish_newsletter(&newsletter_request_body).await;
-    assert_is_redirect_to(&response, "/admin/newsletters");
+    let newsletter_issue_id = app.create_newsletter_draft(&newsletter_request_body).await;
+    let issue_page = format!("/admin/newsletters/{}", newsletter_issue_id);
+
+    let response = app.post_publish_newsletter(&newsletter_issue_id, &newsletter_request_body).await;
+    assert_is_redirect_to(&response, &issue_page);
 
 
-    let html_page = app.get_publish_newsletter_html().await;
+    let html_page = app.get_newsletter_issue_html(&newsletter_issue_id, None).await;
 
     assert!(
         html_page.contains("<p><i>The newsletter issue has been accepted - emails will go out shortly.</i></p>")
     );
 
-    let response = app.post_publish_newsletter(&newsletter_request_body).await;
-    assert_is_redirect_to(&response, "/admin/newsletters");
+    let response = app.post_publish_newsletter(&newsletter_issue_id, &newsletter_request_body).await;
+    assert_is_redirect_to(&response, &issue_page);
 
-    let html_page = app.get_publish_newsletter_html().await;
+    let html_page = app.get_newsletter_issue_html(&newsletter_issue_id, None).await;
 
     assert!(
         html_page.contains("<p><i>The newsletter issue has been accepted - emails will go out shortly.</i></p>")
@@ -295,8 +315,10 @@ async fn concurrent_form_submission_is_handled_gracefully() {
         "idempotency_key": Uuid::new_v4().to_string(),
     });
 
-    let response1 = app.post_publish_newsletter(&newsletter_request_body);
-    let response2 = app.post_publish_newsletter(&newsletter_request_body);
+    let newsletter_issue_id = app.create_newsletter_draft(&newsletter_request_body).await;
+
+    let response1 = app.post_publish_newsletter(&newsletter_issue_id, &newsletter_request_body);
+    let response2 = app.post_publish_newsletter(&newsletter_issue_id, &newsletter_request_body);
 
     let (response1, response2) = tokio::join!(response1, response2);
 
@@ -306,46 +328,366 @@ async fn concurrent_form_submission_is_handled_gracefully() {
     app.dispatch_all_pending_emails().await;
 }
 
-async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
-    let name: String = Name().fake();
-    let email: String = SafeEmail().fake();
+#[tokio::test]
+async fn newsletters_carry_one_click_unsubscribe_headers() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
 
-    let body = serde_urlencoded::to_string(&serde_json::json!({
-        "name": name,
-        "email": email,
-    }))
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    });
+
+    app.user_login().await;
+    app.publish_newsletter(&newsletter_request_body).await;
+    app.dispatch_all_pending_emails().await;
+
+    let email_request = app.email_server.received_requests().await.unwrap().pop().unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+
+    assert_eq!(body["headers"]["List-Unsubscribe-Post"], "List-Unsubscribe=One-Click");
+
+    let list_unsubscribe = body["headers"]["List-Unsubscribe"].as_str().unwrap();
+    assert!(!list_unsubscribe.contains("mailto:"));
+
+    let unsubscribe_link = list_unsubscribe
+        .strip_prefix(&format!("<{}", app.address))
+        .and_then(|link| link.strip_suffix('>'))
+        .expect("No https unsubscribe link in List-Unsubscribe");
+
+    let response = reqwest::Client::new()
+        .post(format!("{}{}", app.address, unsubscribe_link))
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .body("List-Unsubscribe=One-Click")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    assert_eq!(saved.status, "unsubscribed");
+}
+
+#[tokio::test]
+async fn failed_delivery_is_rescheduled_with_backoff() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+        "n_retries": 3,
+        "retry_base_delay_in_secs": 600,
+        "retry_max_delay_in_secs": 3600,
+    });
+
+    app.user_login().await;
+    app.publish_newsletter(&newsletter_request_body).await;
+
+    let published_at = chrono::Utc::now();
+    app.dispatch_all_pending_emails().await;
+
+    let task = sqlx::query!(
+        r#"SELECT n_retries, n_attempts, execute_after as "execute_after!" FROM issue_delivery_queue"#
+    )
+    .fetch_one(&app.db_pool)
+    .await
     .unwrap();
 
-    let _mock_guard = Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+    assert_eq!(task.n_retries, 2);
+    assert_eq!(task.n_attempts, 1);
+    assert!(task.execute_after >= published_at + chrono::Duration::seconds(300));
+    assert!(task.execute_after <= chrono::Utc::now() + chrono::Duration::seconds(600));
+}
+
+#[tokio::test]
+async fn delivery_rejected_by_the_provider_is_not_retried() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
         .and(method("POST"))
-        .respond_with(ResponseTemplate::new(200))
-        .named("Create unconfirmed subscriber")
+        .respond_with(ResponseTemplate::new(400).set_body_string("Invalid recipient"))
         .expect(1)
-        .mount_as_scoped(&app.email_server)
+        .mount(&app.email_server)
         .await;
 
-    app.post_subscriptions(body.into())
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+        "n_retries": 5,
+        "retry_base_delay_in_secs": 0,
+        "retry_max_delay_in_secs": 0,
+    });
+
+    app.user_login().await;
+    app.publish_newsletter(&newsletter_request_body).await;
+    app.dispatch_all_pending_emails().await;
+
+    let failure = sqlx::query!("SELECT n_attempts, last_error FROM issue_delivery_failures")
+        .fetch_one(&app.db_pool)
         .await
-        .error_for_status()
         .unwrap();
 
-    let email_request = &app
-        .email_server
-        .received_requests()
+    assert_eq!(failure.n_attempts, 1);
+    assert!(failure.last_error.contains("Invalid recipient"));
+}
+
+#[tokio::test]
+async fn rate_limited_delivery_is_rescheduled_after_the_retry_after_delay() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "120"))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+        "n_retries": 3,
+        "retry_base_delay_in_secs": 1,
+        "retry_max_delay_in_secs": 3600,
+    });
+
+    app.user_login().await;
+    app.publish_newsletter(&newsletter_request_body).await;
+
+    let published_at = chrono::Utc::now();
+    app.dispatch_all_pending_emails().await;
+
+    let task = sqlx::query!(
+        r#"SELECT n_retries, execute_after as "execute_after!" FROM issue_delivery_queue"#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+
+    assert_eq!(task.n_retries, 2);
+    assert!(task.execute_after >= published_at + chrono::Duration::seconds(120));
+    assert!(task.execute_after <= chrono::Utc::now() + chrono::Duration::seconds(120));
+}
+
+#[tokio::test]
+async fn concurrent_delivery_workers_send_each_email_once_and_stop_on_shutdown() {
+    let app = spawn_app().await;
+    for _ in 0..6 {
+        create_confirmed_subscriber(&app).await;
+    }
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_millis(200)))
+        .expect(6)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    });
+
+    app.user_login().await;
+    app.publish_newsletter(&newsletter_request_body).await;
+
+    let mut configuration = app.configuration.clone();
+    configuration.delivery_worker.n_workers = 2;
+    configuration.delivery_worker.max_in_flight = 3;
+    let (shutdown_sender, shutdown) = tokio::sync::watch::channel(false);
+    let workers = tokio::spawn(run_worker_until_stopped(configuration, shutdown));
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+    loop {
+        let n_queued = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM issue_delivery_queue"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap()
+            .count;
+
+        if n_queued == 0 {
+            break;
+        }
+        assert!(tokio::time::Instant::now() < deadline, "The queue was not drained in time.");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    shutdown_sender.send(true).unwrap();
+    tokio::time::timeout(Duration::from_secs(5), workers)
         .await
+        .expect("The workers did not stop on shutdown.")
         .unwrap()
-        .pop()
         .unwrap();
+}
+
+#[tokio::test]
+async fn delivery_workers_refuse_to_start_with_too_few_database_connections() {
+    let app = spawn_app().await;
 
-    app.get_confirmation_links(&email_request)
+    let mut configuration = app.configuration.clone();
+    configuration.delivery_worker.n_workers = 2;
+    configuration.delivery_worker.max_in_flight = 4;
+    configuration.delivery_worker.max_connections = 8;
+    let (_shutdown_sender, shutdown) = tokio::sync::watch::channel(false);
+
+    let outcome = tokio::time::timeout(Duration::from_secs(5), run_worker_until_stopped(configuration, shutdown))
+        .await
+        .expect("The workers started anyway.");
+
+    assert!(outcome.is_err());
 }
 
-async fn create_confirmed_subscriber(app: &TestApp) {
-    let confirmation_link = create_unconfirmed_subscriber(app).await;
+#[tokio::test]
+async fn idle_delivery_workers_are_woken_up_when_a_newsletter_is_published() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
 
-    reqwest::get(confirmation_link.html)
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let (shutdown_sender, shutdown) = tokio::sync::watch::channel(false);
+    let workers = tokio::spawn(run_worker_until_stopped(app.configuration.clone(), shutdown));
+
+    // Give the workers the time to find the queue empty and go idle.
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    app.user_login().await;
+    app.publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    }))
+    .await;
+
+    // Way shorter than the fallback poll interval.
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+    while app.email_server.received_requests().await.unwrap().is_empty() {
+        assert!(tokio::time::Instant::now() < deadline, "The workers were not woken up.");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    shutdown_sender.send(true).unwrap();
+    workers.await.unwrap().unwrap();
+}
+
+#[tokio::test]
+async fn newsletters_returns_400_when_max_retry_delay_is_shorter_than_initial_one() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let response = app.publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+        "retry_base_delay_in_secs": 60,
+        "retry_max_delay_in_secs": 10,
+    }))
+    .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn newsletters_returns_400_when_a_retry_delay_is_too_long() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let test_cases = vec![(30, 4_000_000_000u32), (4_000_000_000u32, 4_000_000_000u32)];
+    for (base_delay, max_delay) in test_cases {
+        let response = app.publish_newsletter(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string(),
+            "retry_base_delay_in_secs": base_delay,
+            "retry_max_delay_in_secs": max_delay,
+        }))
+        .await;
+
+        assert_eq!(response.status().as_u16(), 400);
+    }
+
+    let n_enqueued = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
         .await
         .unwrap()
-        .error_for_status()
-        .unwrap();
+        .count;
+    assert_eq!(n_enqueued, 0);
+}
+
+#[tokio::test]
+async fn retry_after_delays_are_capped_by_the_maximum_retry_delay() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", u64::MAX.to_string().as_str()))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+        "n_retries": 3,
+        "retry_base_delay_in_secs": 1,
+        "retry_max_delay_in_secs": 600,
+    });
+
+    app.user_login().await;
+    app.publish_newsletter(&newsletter_request_body).await;
+
+    let published_at = chrono::Utc::now();
+    app.dispatch_all_pending_emails().await;
+
+    let task = sqlx::query!(
+        r#"SELECT n_retries, execute_after as "execute_after!" FROM issue_delivery_queue"#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+
+    assert_eq!(task.n_retries, 2);
+    assert!(task.execute_after >= published_at + chrono::Duration::seconds(600));
+    assert!(task.execute_after <= chrono::Utc::now() + chrono::Duration::seconds(600));
 }
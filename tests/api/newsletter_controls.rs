@@ -0,0 +1,172 @@
+use crate::helpers::{spawn_app, assert_is_redirect_to, create_confirmed_subscriber, TestApp, draft_body, publish_body};
+
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+struct IssueState {
+    status: String,
+    n_sent_before_cancel: Option<i32>,
+    n_queued: i64,
+}
+
+async fn get_issue_state(app: &TestApp, newsletter_issue_id: &str) -> IssueState {
+    sqlx::query_as!(
+        IssueState,
+        r#"
+        SELECT status,
+            n_sent_before_cancel,
+            (SELECT COUNT(*) FROM issue_delivery_queue WHERE newsletter_issue_id = $1) as "n_queued!"
+        FROM newsletter_issues
+        WHERE id = $1
+        "#,
+        Uuid::parse_str(newsletter_issue_id).unwrap(),
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+}
+
+async fn publish_new_issue(app: &TestApp) -> String {
+    let newsletter_issue_id = app.create_newsletter_draft(&draft_body()).await;
+    app.post_publish_newsletter(&newsletter_issue_id, &publish_body()).await;
+    newsletter_issue_id
+}
+
+#[tokio::test]
+async fn paused_issues_are_not_delivered_until_they_are_resumed() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+    let newsletter_issue_id = publish_new_issue(&app).await;
+
+    let response = app.post_newsletter_action(&newsletter_issue_id, "pause").await;
+    assert_is_redirect_to(&response, &format!("/admin/newsletters/{}", newsletter_issue_id));
+    let html_page = app.get_newsletter_issue_html(&newsletter_issue_id, None).await;
+    assert!(html_page.contains("<p><i>The newsletter issue has been paused.</i></p>"));
+
+    let paused = Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+    drop(paused);
+
+    let issue = get_issue_state(&app, &newsletter_issue_id).await;
+    assert_eq!(issue.status, "paused");
+    assert_eq!(issue.n_queued, 2);
+
+    app.post_newsletter_action(&newsletter_issue_id, "resume").await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(get_issue_state(&app, &newsletter_issue_id).await.status, "sent");
+}
+
+#[tokio::test]
+async fn resumed_scheduled_issues_wait_for_their_send_time() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    let newsletter_issue_id = app.create_newsletter_draft(&draft_body()).await;
+    app.post_publish_newsletter(&newsletter_issue_id, &serde_json::json!({
+        "idempotency_key": Uuid::new_v4().to_string(),
+        "send_at": "2099-01-05T09:00",
+    }))
+    .await;
+
+    app.post_newsletter_action(&newsletter_issue_id, "pause").await;
+    app.post_newsletter_action(&newsletter_issue_id, "resume").await;
+
+    assert_eq!(get_issue_state(&app, &newsletter_issue_id).await.status, "scheduled");
+}
+
+#[tokio::test]
+async fn cancelling_an_issue_drops_the_remaining_deliveries() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_issue_id = publish_new_issue(&app).await;
+    // Only one of the three emails goes out before the mistake is noticed.
+    sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue
+        SET execute_after = now() + interval '1 hour'
+        WHERE subscriber_email <> (SELECT MIN(subscriber_email) FROM issue_delivery_queue)
+        "#,
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    let response = app.post_newsletter_action(&newsletter_issue_id, "cancel").await;
+    assert_is_redirect_to(&response, &format!("/admin/newsletters/{}", newsletter_issue_id));
+
+    let issue = get_issue_state(&app, &newsletter_issue_id).await;
+    assert_eq!(issue.status, "cancelled");
+    assert_eq!(issue.n_sent_before_cancel, Some(1));
+    assert_eq!(issue.n_queued, 0);
+
+    let html_page = app.get_newsletter_issue_html(&newsletter_issue_id, None).await;
+    assert!(html_page.contains(
+        "<p><i>The newsletter issue has been cancelled, 1 recipients had already been sent it.</i></p>"
+    ));
+    assert!(html_page.contains("<p>Cancelled after 1 recipients had been sent the issue.</p>"));
+}
+
+#[tokio::test]
+async fn only_issues_in_flight_can_be_paused_or_cancelled() {
+    let app = spawn_app().await;
+    app.user_login().await;
+    let draft_id = app.create_newsletter_draft(&draft_body()).await;
+    // Nobody to send it to, so it is sent right away.
+    let sent_id = publish_new_issue(&app).await;
+
+    for (newsletter_issue_id, action, error_message) in [
+        (&draft_id, "pause", "Only issues that are scheduled or being sent can be paused."),
+        (&draft_id, "resume", "Only paused issues can be resumed."),
+        (&sent_id, "cancel", "Only issues that are scheduled or being sent can be cancelled."),
+    ] {
+        let response = app.post_newsletter_action(newsletter_issue_id, action).await;
+        assert_is_redirect_to(&response, &format!("/admin/newsletters/{}", newsletter_issue_id));
+
+        let html_page = app.get_newsletter_issue_html(newsletter_issue_id, None).await;
+        assert!(html_page.contains(&format!("<p><i>{}</i></p>", error_message)));
+    }
+
+    assert_eq!(get_issue_state(&app, &draft_id).await.status, "draft");
+    assert_eq!(get_issue_state(&app, &sent_id).await.status, "sent");
+}
+
+#[tokio::test]
+async fn controlling_an_unknown_issue_returns_404() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    for action in ["pause", "resume", "cancel"] {
+        let response = app.post_newsletter_action(&Uuid::new_v4().to_string(), action).await;
+
+        assert_eq!(response.status().as_u16(), 404, "{} did not return 404", action);
+    }
+}
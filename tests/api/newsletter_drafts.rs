@@ -0,0 +1,198 @@
+use crate::helpers::{spawn_app, assert_is_redirect_to, create_confirmed_subscriber, TestApp, draft_body, publish_body};
+
+use uuid::Uuid;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn issue_status(app: &TestApp, newsletter_issue_id: &str) -> String {
+    sqlx::query!(
+        "SELECT status FROM newsletter_issues WHERE id = $1",
+        Uuid::parse_str(newsletter_issue_id).unwrap(),
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .status
+}
+
+#[tokio::test]
+async fn the_new_issue_form_saves_drafts() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let html_page = app.get_publish_newsletter_html().await;
+
+    assert!(html_page.contains(r#"<form action="/admin/newsletters" method="post">"#));
+    assert!(html_page.contains("Save draft"));
+}
+
+#[tokio::test]
+async fn saving_a_draft_does_not_send_anything() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_issue_id = app.create_newsletter_draft(&draft_body()).await;
+
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(issue_status(&app, &newsletter_issue_id).await, "draft");
+    let n_queued = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_queued, 0);
+
+    let html_page = app.get_preview_newsletter_html(&newsletter_issue_id).await;
+    assert!(html_page.contains("<p><i>The draft has been saved.</i></p>"));
+    assert!(html_page.contains("Newsletter body as plain text"));
+    assert!(html_page.contains("&lt;p&gt;Newsletter body as HTML&lt;/p&gt;"));
+    assert!(html_page.contains(&format!(r#"action="/admin/newsletters/{}/publish""#, newsletter_issue_id)));
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_preview_a_draft() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/admin/newsletters/{}/preview", &app.address, Uuid::new_v4()))
+        .send()
+        .await
+        .unwrap();
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn drafts_can_be_edited() {
+    let app = spawn_app().await;
+    app.user_login().await;
+    let newsletter_issue_id = app.create_newsletter_draft(&draft_body()).await;
+
+    let html_page = app.get_edit_newsletter(&newsletter_issue_id).await.text().await.unwrap();
+    assert!(html_page.contains(r#"value="Newsletter title""#));
+
+    let response = app
+        .post_edit_newsletter(&newsletter_issue_id, &serde_json::json!({
+            "title": "Fixed title",
+            "text_content": "Fixed body",
+            "html_content": "<p>Fixed body</p>",
+        }))
+        .await;
+    assert_is_redirect_to(&response, &format!("/admin/newsletters/{}/preview", newsletter_issue_id));
+
+    let html_page = app.get_preview_newsletter_html(&newsletter_issue_id).await;
+    assert!(html_page.contains("Fixed title"));
+    assert!(!html_page.contains("Newsletter title"));
+}
+
+#[tokio::test]
+async fn editing_an_unknown_issue_returns_404() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let response = app.post_edit_newsletter(&Uuid::new_v4().to_string(), &draft_body()).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn published_issues_cannot_be_edited() {
+    let app = spawn_app().await;
+    app.user_login().await;
+    let newsletter_issue_id = app.create_newsletter_draft(&draft_body()).await;
+    app.post_publish_newsletter(&newsletter_issue_id, &publish_body()).await;
+    let issue_page = format!("/admin/newsletters/{}", newsletter_issue_id);
+
+    let response = app.get_edit_newsletter(&newsletter_issue_id).await;
+    assert_is_redirect_to(&response, &issue_page);
+
+    let response = app
+        .post_edit_newsletter(&newsletter_issue_id, &serde_json::json!({
+            "title": "Fixed title",
+            "text_content": "Fixed body",
+            "html_content": "<p>Fixed body</p>",
+        }))
+        .await;
+    assert_is_redirect_to(&response, &issue_page);
+
+    let html_page = app.get_newsletter_issue_html(&newsletter_issue_id, None).await;
+    assert!(html_page.contains("<p><i>Only drafts can be edited.</i></p>"));
+    assert!(html_page.contains("Newsletter title"));
+}
+
+#[tokio::test]
+async fn an_issue_is_published_only_once() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_issue_id = app.create_newsletter_draft(&draft_body()).await;
+    app.post_publish_newsletter(&newsletter_issue_id, &publish_body()).await;
+    let response = app.post_publish_newsletter(&newsletter_issue_id, &publish_body()).await;
+    assert_is_redirect_to(&response, &format!("/admin/newsletters/{}", newsletter_issue_id));
+
+    let html_page = app.get_newsletter_issue_html(&newsletter_issue_id, None).await;
+    assert!(html_page.contains("<p><i>The newsletter issue has already been published.</i></p>"));
+
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn publishing_an_unknown_issue_returns_404() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let response = app.post_publish_newsletter(&Uuid::new_v4().to_string(), &publish_body()).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn published_issues_go_from_sending_to_sent() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_issue_id = app.create_newsletter_draft(&draft_body()).await;
+    app.post_publish_newsletter(&newsletter_issue_id, &publish_body()).await;
+    assert_eq!(issue_status(&app, &newsletter_issue_id).await, "sending");
+
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(issue_status(&app, &newsletter_issue_id).await, "sent");
+}
+
+#[tokio::test]
+async fn issues_without_recipients_are_sent_right_away() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let newsletter_issue_id = app.create_newsletter_draft(&draft_body()).await;
+    app.post_publish_newsletter(&newsletter_issue_id, &publish_body()).await;
+
+    assert_eq!(issue_status(&app, &newsletter_issue_id).await, "sent");
+}
@@ -0,0 +1,78 @@
+use crate::helpers::{spawn_app, assert_is_redirect_to, create_confirmed_subscriber, TestApp};
+
+use uuid::Uuid;
+
+async fn insert_issue(app: &TestApp, title: &str, published_at: chrono::DateTime<chrono::Utc>) -> Uuid {
+    let id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issues (id, title, text_content, html_content, status, published_at)
+        VALUES ($1, $2, 'Text', '<p>HTML</p>', 'sent', $3)
+        "#,
+        id,
+        title,
+        published_at,
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    id
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_newsletter_history() {
+    let app = spawn_app().await;
+
+    let response = app.get_newsletter_history(None).await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn published_issues_are_listed_with_their_delivery_counts() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    app.publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    }))
+    .await;
+    let issue = sqlx::query!("SELECT id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    let html_page = app.get_newsletter_history_html(None).await;
+
+    assert!(html_page.contains(&format!(r#"<a href="/admin/newsletters/{}">Newsletter title</a>"#, issue.id)));
+    assert!(html_page.contains("<td>2</td>"));
+}
+
+#[tokio::test]
+async fn newsletter_history_is_paginated_newest_first() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let now = chrono::Utc::now();
+    for i in 0..21 {
+        insert_issue(&app, &format!("Issue #{}", i), now - chrono::Duration::days(i)).await;
+    }
+
+    let first_page = app.get_newsletter_history_html(None).await;
+    assert!(first_page.contains("Issue #0<"));
+    assert!(first_page.contains("Issue #19<"));
+    assert!(!first_page.contains("Issue #20<"));
+    assert!(first_page.contains(r#"href="/admin/newsletters/history?page=2""#));
+
+    let second_page = app.get_newsletter_history_html(Some(2)).await;
+    assert!(second_page.contains("Issue #20<"));
+    assert!(!second_page.contains("Issue #0<"));
+    assert!(!second_page.contains("page=3"));
+    assert!(second_page.contains(r#"href="/admin/newsletters/history?page=1""#));
+}
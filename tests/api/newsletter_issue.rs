@@ -0,0 +1,147 @@
+use crate::helpers::{spawn_app, assert_is_redirect_to, create_confirmed_subscriber, TestApp};
+
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn publish_newsletter(app: &TestApp) -> String {
+    app.publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    }))
+    .await;
+
+    sqlx::query!("SELECT id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id
+        .to_string()
+}
+
+async fn subscriber_emails(app: &TestApp) -> Vec<String> {
+    sqlx::query!("SELECT email FROM subscriptions ORDER BY email")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|r| r.email)
+        .collect()
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_a_newsletter_issue() {
+    let app = spawn_app().await;
+
+    let response = app.get_newsletter_issue(&Uuid::new_v4().to_string(), None).await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn unknown_newsletter_issue_returns_404() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let response = app.get_newsletter_issue(&Uuid::new_v4().to_string(), None).await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn sent_deliveries_are_recorded_with_the_provider_message_id() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "success": true,
+            "message_ids": ["provider-message-id"],
+        })))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_issue_id = publish_newsletter(&app).await;
+
+    let html_page = app.get_newsletter_issue_html(&newsletter_issue_id, None).await;
+    assert!(html_page.contains("Queued: 2"));
+    assert!(html_page.contains("Sent: 0"));
+
+    app.dispatch_all_pending_emails().await;
+
+    let deliveries = sqlx::query!(
+        "SELECT status, provider_message_id, n_attempts FROM newsletter_deliveries"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+
+    assert_eq!(deliveries.len(), 2);
+    for delivery in deliveries {
+        assert_eq!(delivery.status, "sent");
+        assert_eq!(delivery.provider_message_id.as_deref(), Some("provider-message-id"));
+        assert_eq!(delivery.n_attempts, 1);
+    }
+
+    let html_page = app.get_newsletter_issue_html(&newsletter_issue_id, None).await;
+    assert!(html_page.contains("Newsletter title"));
+    assert!(html_page.contains("Queued: 0"));
+    assert!(html_page.contains("Sent: 2"));
+    assert!(html_page.contains("provider-message-id"));
+    for email in subscriber_emails(&app).await {
+        assert!(html_page.contains(&email));
+    }
+}
+
+#[tokio::test]
+async fn subscribers_who_left_after_publication_are_skipped() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_issue_id = publish_newsletter(&app).await;
+    sqlx::query!("UPDATE subscriptions SET status = 'unsubscribed'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    app.dispatch_all_pending_emails().await;
+
+    let delivery = sqlx::query!("SELECT status FROM newsletter_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(delivery.status, "skipped");
+
+    let html_page = app.get_newsletter_issue_html(&newsletter_issue_id, None).await;
+    assert!(html_page.contains("Skipped: 1"));
+}
+
+#[tokio::test]
+async fn recipients_can_be_searched_by_email() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    let newsletter_issue_id = publish_newsletter(&app).await;
+    let emails = subscriber_emails(&app).await;
+
+    let html_page = app.get_newsletter_issue_html(&newsletter_issue_id, Some(&emails[0])).await;
+
+    assert!(html_page.contains(&emails[0]));
+    assert!(!html_page.contains(&emails[1]));
+}
@@ -0,0 +1,97 @@
+use crate::helpers::{spawn_app, create_confirmed_subscriber};
+
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+const MARKDOWN: &str = "# Big news\n\nHi {{ name }}, we shipped **Markdown**.\n\n- one\n- two\n\n[Read more](https://example.com/news)";
+
+#[tokio::test]
+async fn markdown_drafts_store_both_generated_versions_and_the_source() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let newsletter_issue_id = app
+        .create_newsletter_draft(&serde_json::json!({
+            "title": "Newsletter title",
+            "markdown_content": MARKDOWN,
+        }))
+        .await;
+
+    let issue = sqlx::query!(
+        "SELECT text_content, html_content, markdown_content FROM newsletter_issues WHERE id = $1",
+        Uuid::parse_str(&newsletter_issue_id).unwrap(),
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+
+    assert_eq!(issue.markdown_content.as_deref(), Some(MARKDOWN));
+    assert_eq!(
+        issue.text_content,
+        "Big news\n\nHi {{ name }}, we shipped Markdown.\n\n- one\n- two\n\nRead more (https://example.com/news)",
+    );
+    // Wrapped in the layout, with inlined styles.
+    assert!(issue.html_content.starts_with("<!DOCTYPE html>"));
+    assert!(issue.html_content.contains("Hi {{ name }}, we shipped <strong>Markdown</strong>."));
+    assert!(issue.html_content.contains(r#"<h1 style=""#));
+    assert!(issue.html_content.contains(r#"<a style="color: #1a73e8;" href="https://example.com/news">Read more</a>"#));
+
+    let html_page = app.get_edit_newsletter(&newsletter_issue_id).await.text().await.unwrap();
+    assert!(html_page.contains("# Big news"));
+}
+
+#[tokio::test]
+async fn raw_html_in_markdown_is_not_interpreted() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let newsletter_issue_id = app
+        .create_newsletter_draft(&serde_json::json!({
+            "title": "Newsletter title",
+            "markdown_content": "Hello\n\n<script>alert('hi')</script>\n\n[click](javascript:alert(1))",
+        }))
+        .await;
+
+    let issue = sqlx::query!(
+        "SELECT html_content FROM newsletter_issues WHERE id = $1",
+        Uuid::parse_str(&newsletter_issue_id).unwrap(),
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+
+    assert!(!issue.html_content.contains("<script>"));
+    assert!(!issue.html_content.contains("javascript:"));
+}
+
+#[tokio::test]
+async fn markdown_issues_are_delivered_rendered() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+    let n_sent_before = app.email_server.received_requests().await.unwrap().len();
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "markdown_content": MARKDOWN,
+        "idempotency_key": Uuid::new_v4().to_string(),
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    let request = &app.email_server.received_requests().await.unwrap()[n_sent_before];
+    let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    let html = body["html"].as_str().unwrap();
+    assert!(html.contains("<strong>Markdown</strong>"));
+    // The layout's footer links to the subscriber's own unsubscribe page.
+    assert!(html.contains(&format!(r#"<a href="{}/subscriptions/unsubscribe?unsubscribe_token="#, app.address)));
+    assert!(body["text"].as_str().unwrap().starts_with("Big news\n\nHi "));
+}
@@ -0,0 +1,114 @@
+use crate::helpers::{spawn_app, create_confirmed_subscriber};
+
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn placeholders_are_filled_in_for_every_subscriber() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+    let n_sent_before = app.email_server.received_requests().await.unwrap().len();
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    app.publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Hi {{ name }} ({{ email }}), unsubscribe at {{ unsubscribe_url }}",
+        "html_content": "<p>Hi {{name}}</p><a href=\"{{ unsubscribe_url }}\">Unsubscribe</a>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    }))
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    let requests = app.email_server.received_requests().await.unwrap();
+    for request in &requests[n_sent_before..] {
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        let email = body["to"][0]["email"].as_str().unwrap();
+        let subscriber = sqlx::query!("SELECT name FROM subscriptions WHERE email = $1", email)
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+
+        let text = body["text"].as_str().unwrap();
+        assert!(text.starts_with(&format!("Hi {} ({}), unsubscribe at ", subscriber.name, email)));
+        assert!(text.contains(&format!("{}/subscriptions/unsubscribe?unsubscribe_token=", app.address)));
+        assert!(!text.contains("{{"));
+
+        let html = body["html"].as_str().unwrap();
+        assert!(html.contains(&format!("<p>Hi {}</p>", subscriber.name)));
+        assert!(!html.contains("{{"));
+
+        // The link in the body is the same as the one-click unsubscribe link.
+        let list_unsubscribe = body["headers"]["List-Unsubscribe"].as_str().unwrap();
+        let unsubscribe_url = text.rsplit(' ').next().unwrap();
+        assert!(list_unsubscribe.contains(&format!("<{}>", unsubscribe_url)));
+    }
+}
+
+#[tokio::test]
+async fn unknown_placeholders_are_rejected_when_saving() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let response = app
+        .post_newsletters(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Hi {{ first_name }}",
+            "html_content": "<p>Hi</p>",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let newsletter_issue_id = app
+        .create_newsletter_draft(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Hi {{ name }}",
+            "html_content": "<p>Hi {{ name }}</p>",
+        }))
+        .await;
+    let response = app
+        .post_edit_newsletter(&newsletter_issue_id, &serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Hi {{ name }}",
+            "html_content": "<p>Hi {{ nmae }}</p>",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn test_emails_are_rendered_for_a_stand_in_subscriber() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_issue_id = app
+        .create_newsletter_draft(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Hi {{ name }} ({{ email }})",
+            "html_content": "<p>Hi {{ name }}</p>",
+        }))
+        .await;
+    app.post_send_test_newsletter(&newsletter_issue_id, &serde_json::json!({
+        "recipients": "editor@example.com",
+    }))
+    .await;
+
+    let request = &app.email_server.received_requests().await.unwrap()[0];
+    let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    assert_eq!(body["text"], "Hi Test Subscriber (editor@example.com)");
+}
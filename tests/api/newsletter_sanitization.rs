@@ -0,0 +1,124 @@
+use crate::helpers::spawn_app;
+
+use uuid::Uuid;
+
+#[tokio::test]
+async fn unsafe_html_is_removed_from_drafts_and_the_editor_is_told() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let newsletter_issue_id = app
+        .create_newsletter_draft(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": r#"<p onclick="steal()">Hello</p><script>steal()</script><a href="javascript:steal()">click</a>"#,
+        }))
+        .await;
+
+    let issue = sqlx::query!(
+        "SELECT html_content FROM newsletter_issues WHERE id = $1",
+        Uuid::parse_str(&newsletter_issue_id).unwrap(),
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(issue.html_content, "<p>Hello</p><a>click</a>");
+
+    let html_page = app.get_preview_newsletter_html(&newsletter_issue_id).await;
+    assert!(html_page.contains("The draft has been saved."));
+    assert!(html_page.contains(
+        "Unsafe HTML was removed from the content: the href attribute on a, the onclick attribute on p, the script element."
+    ));
+}
+
+#[tokio::test]
+async fn edits_are_sanitized_too() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let newsletter_issue_id = app
+        .create_newsletter_draft(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+        }))
+        .await;
+    app.get_preview_newsletter_html(&newsletter_issue_id).await;
+
+    app.post_edit_newsletter(&newsletter_issue_id, &serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": r#"<p>Edited</p><iframe src="https://tracker.example.com"></iframe>"#,
+    }))
+    .await;
+
+    let issue = sqlx::query!(
+        "SELECT html_content FROM newsletter_issues WHERE id = $1",
+        Uuid::parse_str(&newsletter_issue_id).unwrap(),
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(issue.html_content, "<p>Edited</p>");
+
+    let html_page = app.get_preview_newsletter_html(&newsletter_issue_id).await;
+    assert!(html_page.contains("Unsafe HTML was removed from the content: the iframe element, the src attribute on iframe."));
+}
+
+#[tokio::test]
+async fn safe_html_is_saved_as_is_without_a_warning() {
+    let app = spawn_app().await;
+    app.user_login().await;
+    let html_content = r#"<table width="600" cellpadding="0"><tbody><tr><td style="padding: 8px;">Hi {{ name }}</td></tr></tbody></table><a href="{{ unsubscribe_url }}">Unsubscribe</a>"#;
+
+    let newsletter_issue_id = app
+        .create_newsletter_draft(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": html_content,
+        }))
+        .await;
+
+    let issue = sqlx::query!(
+        "SELECT html_content FROM newsletter_issues WHERE id = $1",
+        Uuid::parse_str(&newsletter_issue_id).unwrap(),
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(issue.html_content, html_content);
+
+    let html_page = app.get_preview_newsletter_html(&newsletter_issue_id).await;
+    assert!(!html_page.contains("Unsafe HTML was removed"));
+}
+
+#[tokio::test]
+async fn drafts_saved_before_sanitization_are_cleaned_when_published() {
+    let app = spawn_app().await;
+    app.user_login().await;
+    let newsletter_issue_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issues (id, title, text_content, html_content, status)
+        VALUES ($1, 'Newsletter title', 'Newsletter body', '<p>Hello</p><script>steal()</script>', 'draft')
+        "#,
+        newsletter_issue_id,
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    app.post_publish_newsletter(&newsletter_issue_id.to_string(), &serde_json::json!({
+        "idempotency_key": Uuid::new_v4().to_string(),
+    }))
+    .await;
+
+    let issue = sqlx::query!("SELECT html_content FROM newsletter_issues WHERE id = $1", newsletter_issue_id)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issue.html_content, "<p>Hello</p>");
+
+    let html_page = app.get_newsletter_issue_html(&newsletter_issue_id.to_string(), None).await;
+    assert!(html_page.contains("Unsafe HTML was removed from the content: the script element."));
+}
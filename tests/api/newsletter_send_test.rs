@@ -0,0 +1,142 @@
+use crate::helpers::{spawn_app, assert_is_redirect_to, create_confirmed_subscriber, draft_body};
+
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn test_emails_go_only_to_the_given_addresses() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+    let newsletter_issue_id = app.create_newsletter_draft(&draft_body()).await;
+    let n_sent_before = app.email_server.received_requests().await.unwrap().len();
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_send_test_newsletter(&newsletter_issue_id, &serde_json::json!({
+            "recipients": "editor@example.com,\r\nproofreader@example.com",
+        }))
+        .await;
+    assert_is_redirect_to(&response, &format!("/admin/newsletters/{}/preview", newsletter_issue_id));
+
+    let requests = app.email_server.received_requests().await.unwrap();
+    let mut recipients = Vec::new();
+    for request in &requests[n_sent_before..] {
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(body["subject"], "[TEST] Newsletter title");
+        recipients.push(body["to"][0]["email"].as_str().unwrap().to_owned());
+    }
+    recipients.sort();
+    assert_eq!(recipients, ["editor@example.com", "proofreader@example.com"]);
+
+    let html_page = app.get_preview_newsletter_html(&newsletter_issue_id).await;
+    assert!(html_page.contains("<p><i>The test email has been sent.</i></p>"));
+
+    // The issue itself is untouched.
+    let issue = sqlx::query!(
+        r#"
+        SELECT status,
+            (SELECT COUNT(*) FROM issue_delivery_queue) as "n_queued!",
+            (SELECT COUNT(*) FROM newsletter_deliveries) as "n_delivered!"
+        FROM newsletter_issues
+        WHERE id = $1
+        "#,
+        Uuid::parse_str(&newsletter_issue_id).unwrap(),
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(issue.status, "draft");
+    assert_eq!(issue.n_queued, 0);
+    assert_eq!(issue.n_delivered, 0);
+}
+
+#[tokio::test]
+async fn failed_test_emails_are_reported() {
+    let app = spawn_app().await;
+    app.user_login().await;
+    let newsletter_issue_id = app.create_newsletter_draft(&draft_body()).await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_send_test_newsletter(&newsletter_issue_id, &serde_json::json!({
+        "recipients": "editor@example.com",
+    }))
+    .await;
+
+    let html_page = app.get_preview_newsletter_html(&newsletter_issue_id).await;
+    assert!(html_page.contains("<p><i>The test email could not be sent to editor@example.com.</i></p>"));
+}
+
+#[tokio::test]
+async fn invalid_test_recipients_are_rejected() {
+    let app = spawn_app().await;
+    app.user_login().await;
+    let newsletter_issue_id = app.create_newsletter_draft(&draft_body()).await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let too_many: Vec<String> = (0..11).map(|i| format!("editor{}@example.com", i)).collect();
+    let too_many = too_many.join(",");
+    let test_cases = vec![
+        ("", "no recipient"),
+        ("editor@example.com, not-an-email", "invalid address"),
+        (too_many.as_str(), "too many recipients"),
+    ];
+
+    for (recipients, error_message) in test_cases {
+        let response = app
+            .post_send_test_newsletter(&newsletter_issue_id, &serde_json::json!({ "recipients": recipients }))
+            .await;
+
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request when the payload had {}.",
+            error_message
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_emails_for_an_unknown_issue_return_404() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let response = app
+        .post_send_test_newsletter(&Uuid::new_v4().to_string(), &serde_json::json!({
+            "recipients": "editor@example.com",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 404);
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_send_test_emails() {
+    let app = spawn_app().await;
+
+    let response = app
+        .post_send_test_newsletter(&Uuid::new_v4().to_string(), &serde_json::json!({
+            "recipients": "editor@example.com",
+        }))
+        .await;
+
+    assert_is_redirect_to(&response, "/login");
+}
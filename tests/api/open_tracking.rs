@@ -0,0 +1,190 @@
+use crate::helpers::{spawn_app, create_confirmed_subscriber, TestApp};
+
+use uuid::Uuid;
+use zero2prod::domain::UnsubscribeToken;
+
+fn draft_body(track_opens: bool) -> serde_json::Value {
+    let mut draft_body = serde_json::json!({
+        "title": "Newsletter title",
+        "markdown_content": "Newsletter body",
+    });
+    if track_opens {
+        draft_body["track_opens"] = "true".into();
+    }
+    draft_body
+}
+
+fn pixel_url(html_content: &str) -> Option<String> {
+    let start = html_content.find(r#"<img src=""#)? + r#"<img src=""#.len();
+    let end = start + html_content[start..].find('"')?;
+
+    Some(html_content[start..end].to_owned())
+}
+
+async fn opens(app: &TestApp, newsletter_issue_id: &str) -> Vec<(i32, bool)> {
+    sqlx::query!(
+        "SELECT n_opens, first_opened_at < last_opened_at as later_open FROM newsletter_opens WHERE newsletter_issue_id = $1",
+        Uuid::parse_str(newsletter_issue_id).unwrap(),
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|r| (r.n_opens, r.later_open.unwrap()))
+    .collect()
+}
+
+#[tokio::test]
+async fn tracked_issues_carry_a_pixel_that_records_opens() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    let (newsletter_issue_id, html_contents) = app.publish_and_deliver(&draft_body(true)).await;
+
+    let html_content = &html_contents[0];
+    let pixel_url = pixel_url(html_content).expect("No tracking pixel in the email");
+    assert!(pixel_url.starts_with(&format!("{}/t/o/", app.address)));
+    assert!(pixel_url.ends_with(".gif"));
+    // The pixel goes last inside the body of the layout.
+    assert!(html_content.contains(r#"style="display: block; border: 0;"></body>"#));
+
+    let response = reqwest::get(&pixel_url).await.unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["Content-Type"], "image/gif");
+    assert_eq!(response.headers()["Cache-Control"], "no-store, private");
+    assert!(response.bytes().await.unwrap().starts_with(b"GIF89a"));
+    assert_eq!(opens(&app, &newsletter_issue_id).await, [(1, false)]);
+
+    reqwest::get(&pixel_url).await.unwrap();
+    assert_eq!(opens(&app, &newsletter_issue_id).await, [(2, true)]);
+
+    let html_page = app.get_newsletter_issue_html(&newsletter_issue_id, None).await;
+    assert!(html_page.contains("Opened by: 1 subscribers (2 opens)"));
+}
+
+#[tokio::test]
+async fn issues_are_not_tracked_by_default() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    let (newsletter_issue_id, html_contents) = app.publish_and_deliver(&draft_body(false)).await;
+
+    assert_eq!(pixel_url(&html_contents[0]), None);
+    let html_page = app.get_newsletter_issue_html(&newsletter_issue_id, None).await;
+    assert!(html_page.contains("Opens are not tracked."));
+}
+
+#[tokio::test]
+async fn subscribers_who_opted_out_get_no_pixel() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    sqlx::query!("UPDATE subscriptions SET do_not_track = true WHERE email = (SELECT MIN(email) FROM subscriptions)")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.user_login().await;
+
+    let (_, html_contents) = app.publish_and_deliver(&draft_body(true)).await;
+
+    let n_pixels = html_contents.iter().filter_map(|html| pixel_url(html)).count();
+    assert_eq!(html_contents.len(), 2);
+    assert_eq!(n_pixels, 1);
+}
+
+#[tokio::test]
+async fn opting_out_stops_recording_and_forgets_past_opens() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+    let (newsletter_issue_id, html_contents) = app.publish_and_deliver(&draft_body(true)).await;
+    let pixel_url = pixel_url(&html_contents[0]).unwrap();
+    reqwest::get(&pixel_url).await.unwrap();
+
+    let unsubscribe_token = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .map(|r| UnsubscribeToken::generate(r.id, &app.hmac_secret))
+        .unwrap();
+    let unsubscribe_page = reqwest::get(format!(
+        "{}/subscriptions/unsubscribe?unsubscribe_token={}",
+        app.address,
+        unsubscribe_token.as_ref(),
+    ))
+    .await
+    .unwrap()
+    .text()
+    .await
+    .unwrap();
+    assert!(unsubscribe_page.contains("Stop tracking me"));
+
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions/do_not_track?unsubscribe_token={}", app.address, unsubscribe_token.as_ref()))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(opens(&app, &newsletter_issue_id).await, []);
+
+    reqwest::get(&pixel_url).await.unwrap();
+    assert_eq!(opens(&app, &newsletter_issue_id).await, []);
+}
+
+#[tokio::test]
+async fn opting_out_requires_a_valid_token() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions/do_not_track?unsubscribe_token=forged", app.address))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 401);
+}
+
+#[tokio::test]
+async fn invalid_tokens_get_the_pixel_but_record_nothing() {
+    let app = spawn_app().await;
+
+    let response = reqwest::get(format!("{}/t/o/forged.gif", app.address)).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["Content-Type"], "image/gif");
+    let n_opens = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM newsletter_opens"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_opens, 0);
+}
+
+#[tokio::test]
+async fn the_draft_editor_remembers_the_tracking_choice() {
+    let app = spawn_app().await;
+    app.user_login().await;
+
+    let newsletter_issue_id = app
+        .create_newsletter_draft(&serde_json::json!({
+            "title": "Newsletter title",
+            "markdown_content": "Hello",
+            "track_opens": "true",
+        }))
+        .await;
+
+    let html_page = app.get_edit_newsletter(&newsletter_issue_id).await.text().await.unwrap();
+    assert!(html_page.contains(r#"<input type="checkbox" name="track_opens" value="true" checked>"#));
+
+    app.post_edit_newsletter(&newsletter_issue_id, &serde_json::json!({
+        "title": "Newsletter title",
+        "markdown_content": "Hello",
+    }))
+    .await;
+
+    let html_page = app.get_edit_newsletter(&newsletter_issue_id).await.text().await.unwrap();
+    assert!(html_page.contains(r#"<input type="checkbox" name="track_opens" value="true">"#));
+}
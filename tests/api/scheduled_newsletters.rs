@@ -0,0 +1,192 @@
+use crate::helpers::{spawn_app, assert_is_redirect_to, create_confirmed_subscriber, TestApp, draft_body};
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+fn schedule_body(send_at: &str, timezone: &str) -> serde_json::Value {
+    serde_json::json!({
+        "idempotency_key": Uuid::new_v4().to_string(),
+        "send_at": send_at,
+        "timezone": timezone,
+    })
+}
+
+struct ScheduledIssue {
+    status: String,
+    published_at: Option<DateTime<Utc>>,
+}
+
+async fn get_scheduled_issue(app: &TestApp, newsletter_issue_id: &str) -> ScheduledIssue {
+    sqlx::query_as!(
+        ScheduledIssue,
+        "SELECT status, published_at FROM newsletter_issues WHERE id = $1",
+        Uuid::parse_str(newsletter_issue_id).unwrap(),
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+}
+
+async fn fast_forward_to_the_send_time(app: &TestApp) {
+    sqlx::query!("UPDATE newsletter_issues SET published_at = now() - interval '1 second' WHERE status = 'scheduled'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn scheduled_issues_are_not_delivered_before_their_send_time() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    let send_at = Utc::now() + chrono::Duration::days(3);
+    let newsletter_issue_id = app.create_newsletter_draft(&draft_body()).await;
+    let response = app
+        .post_publish_newsletter(
+            &newsletter_issue_id,
+            &schedule_body(&send_at.format("%Y-%m-%dT%H:%M").to_string(), "UTC"),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let html_page = app.get_newsletter_issue_html(&newsletter_issue_id, None).await;
+    assert!(html_page.contains("The newsletter issue has been scheduled for"));
+    assert!(html_page.contains("Scheduled for"));
+
+    let when_the_time_comes = Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+    drop(when_the_time_comes);
+
+    assert_eq!(get_scheduled_issue(&app, &newsletter_issue_id).await.status, "scheduled");
+
+    fast_forward_to_the_send_time(&app).await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(get_scheduled_issue(&app, &newsletter_issue_id).await.status, "sent");
+}
+
+#[tokio::test]
+async fn the_send_time_is_read_in_the_chosen_time_zone() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    let newsletter_issue_id = app.create_newsletter_draft(&draft_body()).await;
+    app.post_publish_newsletter(&newsletter_issue_id, &schedule_body("2099-01-05T09:00", "America/New_York"))
+        .await;
+
+    let expected = DateTime::parse_from_rfc3339("2099-01-05T14:00:00Z").unwrap();
+    let issue = get_scheduled_issue(&app, &newsletter_issue_id).await;
+    assert_eq!(issue.status, "scheduled");
+    assert_eq!(issue.published_at.unwrap(), expected);
+}
+
+#[tokio::test]
+async fn subscribers_who_confirm_after_an_issue_is_scheduled_get_it_too() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    let newsletter_issue_id = app.create_newsletter_draft(&draft_body()).await;
+    app.post_publish_newsletter(&newsletter_issue_id, &schedule_body("2099-01-05T09:00", "UTC"))
+        .await;
+    create_confirmed_subscriber(&app).await;
+    fast_forward_to_the_send_time(&app).await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(get_scheduled_issue(&app, &newsletter_issue_id).await.status, "sent");
+}
+
+#[tokio::test]
+async fn send_times_in_the_past_are_rejected() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    let newsletter_issue_id = app.create_newsletter_draft(&draft_body()).await;
+    let response = app
+        .post_publish_newsletter(&newsletter_issue_id, &schedule_body("2020-01-01T09:00", "UTC"))
+        .await;
+    assert_is_redirect_to(&response, &format!("/admin/newsletters/{}", newsletter_issue_id));
+
+    let html_page = app.get_newsletter_issue_html(&newsletter_issue_id, None).await;
+    assert!(html_page.contains("<p><i>The send time 2020-01-01T09:00:00+00:00 is in the past.</i></p>"));
+
+    assert_eq!(get_scheduled_issue(&app, &newsletter_issue_id).await.status, "draft");
+    let n_enqueued = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_enqueued, 0);
+}
+
+#[tokio::test]
+async fn send_times_that_just_passed_are_sent_right_away() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.user_login().await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let send_at = Utc::now() - chrono::Duration::minutes(1);
+    let newsletter_issue_id = app.create_newsletter_draft(&draft_body()).await;
+    app.post_publish_newsletter(&newsletter_issue_id, &schedule_body(&send_at.format("%Y-%m-%dT%H:%M").to_string(), "UTC"))
+        .await;
+    assert_eq!(get_scheduled_issue(&app, &newsletter_issue_id).await.status, "sending");
+
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn invalid_send_times_are_rejected() {
+    let app = spawn_app().await;
+    app.user_login().await;
+    let newsletter_issue_id = app.create_newsletter_draft(&draft_body()).await;
+
+    let test_cases = vec![
+        (schedule_body("next monday", "UTC"), "unparsable time"),
+        (schedule_body("2099-01-05T09:00", "Mars/Olympus_Mons"), "unknown time zone"),
+        (schedule_body("2030-03-31T02:30", "Europe/Berlin"), "time skipped by daylight saving"),
+    ];
+
+    for (body, error_message) in test_cases {
+        let response = app.post_publish_newsletter(&newsletter_issue_id, &body).await;
+
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request when the payload had an {}.",
+            error_message
+        );
+    }
+
+    assert_eq!(get_scheduled_issue(&app, &newsletter_issue_id).await.status, "draft");
+}
@@ -1,4 +1,4 @@
-use crate::helpers::spawn_app;
+use crate::helpers::{spawn_app, assert_is_redirect_to, create_confirmed_subscriber, wait_out_the_cooldown};
 
 use wiremock::matchers::{method, path};
 use wiremock::{Mock, ResponseTemplate};
@@ -117,7 +117,7 @@ async fn subscribe_sends_a_confirmation_email_with_a_link() {
 
     let email_request = &app.email_server.received_requests().await.unwrap()[0];
 
-    let links = app.get_confirmation_links(&email_request);
+    let links = app.get_confirmation_links(email_request);
 
     assert_eq!(links.html, links.plain_text);
 }
@@ -135,15 +135,39 @@ async fn subscribe_sends_a_confirmation_email_when_user_subscribe_twice() {
         .await;
 
     app.post_subscriptions(body.into()).await;
+    wait_out_the_cooldown(&app).await;
     app.post_subscriptions(body.into()).await;
 
     let email_request = &app.email_server.received_requests().await.unwrap()[1];
 
-    let links = app.get_confirmation_links(&email_request);
+    let links = app.get_confirmation_links(email_request);
 
     assert_eq!(links.html, links.plain_text);
 }
 
+#[tokio::test]
+async fn subscribing_again_within_the_cooldown_sends_no_email() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions(body.into()).await;
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let links = app.get_confirmation_links(email_request);
+
+    let response = app.post_subscriptions(body.into()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    // The link that was sent keeps working.
+    reqwest::get(links.html).await.unwrap().error_for_status().unwrap();
+}
+
 #[tokio::test]
 async fn subscribe_returns_a_422_when_email_already_confirmed() {
     let app = spawn_app().await;
@@ -159,7 +183,7 @@ async fn subscribe_returns_a_422_when_email_already_confirmed() {
     app.post_subscriptions(body.into()).await;
 
     let email_request = &app.email_server.received_requests().await.unwrap()[0];
-    let links = app.get_confirmation_links(&email_request);
+    let links = app.get_confirmation_links(email_request);
 
     reqwest::get(links.html).await.unwrap().error_for_status().unwrap();
 
@@ -183,3 +207,76 @@ async fn subscribe_fails_if_there_is_a_fatal_database_error() {
 
     assert_eq!(response.status().as_u16(), 500);
 }
+
+#[tokio::test]
+async fn the_home_page_has_a_signup_form() {
+    let app = spawn_app().await;
+
+    let html_page = app.get_home_html().await;
+
+    assert!(html_page.contains(r#"<form id="subscribe" action="/subscriptions" method="post">"#));
+    assert!(html_page.contains(r#"name="name""#));
+    assert!(html_page.contains(r#"name="email""#));
+}
+
+#[tokio::test]
+async fn browsers_are_sent_back_home_with_a_message_once_subscribed() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_subscriptions_from_browser(body.into()).await;
+
+    assert_is_redirect_to(&response, "/");
+    let html_page = app.get_home_html().await;
+    assert!(html_page.contains("<p><i>Thanks for subscribing! Click the link in the email we just sent you to confirm.</i></p>"));
+}
+
+#[tokio::test]
+async fn browsers_are_sent_back_home_with_the_validation_error() {
+    let app = spawn_app().await;
+    let test_cases = vec![
+        ("name=&email=ursula_le_guin%40gmail.com", "The name must not be empty."),
+        ("name=Ursula&email=definitely-not-an-email", "definitely-not-an-email is not a valid email address."),
+        ("name=%3Cb%3EUrsula%3C%2Fb%3E&email=ursula_le_guin%40gmail.com", "&lt;b&gt;Ursula&lt;/b&gt; is not a valid subscriber name."),
+        ("name=le%20guin", "The email address must not be empty."),
+        ("", "The name must not be empty. The email address must not be empty."),
+    ];
+
+    for (body, message) in test_cases {
+        let response = app.post_subscriptions_from_browser(body.into()).await;
+
+        assert_is_redirect_to(&response, "/");
+        let html_page = app.get_home_html().await;
+        assert!(
+            html_page.contains(&format!("<p><i>{}</i></p>", message)),
+            "The home page did not show `{}` for `{}`.",
+            message,
+            body,
+        );
+    }
+}
+
+#[tokio::test]
+async fn browsers_are_told_when_they_are_already_subscribed() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let email = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .email;
+    let body = serde_urlencoded::to_string([("name", "le guin"), ("email", email.as_str())]).unwrap();
+
+    let response = app.post_subscriptions_from_browser(body).await;
+
+    assert_is_redirect_to(&response, "/");
+    let html_page = app.get_home_html().await;
+    assert!(html_page.contains("<p><i>You are already subscribed.</i></p>"));
+}
@@ -1,4 +1,4 @@
-use crate::helpers::spawn_app;
+use crate::helpers::{spawn_app, assert_is_redirect_to, create_unconfirmed_subscriber, wait_out_the_cooldown};
 
 use wiremock::matchers::{method, path};
 use wiremock::{Mock, ResponseTemplate};
@@ -28,7 +28,7 @@ async fn the_link_returned_by_subscribe_returns_a_200_if_called() {
     app.post_subscriptions(body.into()).await;
 
     let email_request = &app.email_server.received_requests().await.unwrap()[0];
-    let links = app.get_confirmation_links(&email_request);
+    let links = app.get_confirmation_links(email_request);
 
     let response = reqwest::get(links.html)
         .await
@@ -51,7 +51,7 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
     app.post_subscriptions(body.into()).await;
     let email_request = &app.email_server.received_requests().await.unwrap()[0];
 
-    let confirmation_links = app.get_confirmation_links(&email_request);
+    let confirmation_links = app.get_confirmation_links(email_request);
 
     reqwest::get(confirmation_links.html).await.unwrap().error_for_status().unwrap();
 
@@ -80,10 +80,107 @@ async fn clicking_on_the_confirmation_link_returns_401_when_user_is_already_conf
     app.post_subscriptions(body.into()).await;
     let email_request = &app.email_server.received_requests().await.unwrap()[0];
 
-    let confirmation_links = app.get_confirmation_links(&email_request);
+    let confirmation_links = app.get_confirmation_links(email_request);
 
     reqwest::get(confirmation_links.html.clone()).await.unwrap().error_for_status().unwrap();
     let second_response = reqwest::get(confirmation_links.html).await.unwrap().error_for_status().err().unwrap();
 
     assert_eq!(second_response.status().unwrap(), reqwest::StatusCode::UNAUTHORIZED);
 }
+
+#[tokio::test]
+async fn an_expired_link_offers_to_resend_the_confirmation_email() {
+    let app = spawn_app().await;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await;
+    sqlx::query!("UPDATE subscription_tokens SET created_at = now() - interval '49 hours'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = app.get_confirmation_from_browser(confirmation_links.html).await;
+
+    assert_eq!(response.status().as_u16(), 410);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("This confirmation link has expired."));
+    assert!(html_page.contains(r#"<form action="/subscriptions/resend_confirmation" method="post">"#));
+    let email = sqlx::query!("SELECT email, status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(html_page.contains(&format!(r#"value="{}""#, email.email)));
+    assert_eq!(email.status, "pending_confirmation");
+}
+
+#[tokio::test]
+async fn subscribing_again_replaces_the_confirmation_link() {
+    let app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions(body.into()).await;
+    wait_out_the_cooldown(&app).await;
+    app.post_subscriptions(body.into()).await;
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let first_link = app.get_confirmation_links(&email_requests[0]).html;
+    let second_link = app.get_confirmation_links(&email_requests[1]).html;
+
+    let n_tokens = sqlx::query!(r#"SELECT COUNT(*) as "count!" FROM subscription_tokens"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .count;
+    assert_eq!(n_tokens, 1);
+    assert_eq!(reqwest::get(first_link).await.unwrap().status().as_u16(), 401);
+    assert_eq!(reqwest::get(second_link).await.unwrap().status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn api_clients_get_a_bare_410_for_an_expired_link() {
+    let app = spawn_app().await;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await;
+    sqlx::query!("UPDATE subscription_tokens SET created_at = now() - interval '49 hours'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = reqwest::get(confirmation_links.html).await.unwrap();
+
+    assert_eq!(response.status().as_u16(), 410);
+    assert_eq!(response.text().await.unwrap(), "");
+}
+
+#[tokio::test]
+async fn browsers_are_welcomed_on_the_home_page_once_confirmed() {
+    let app = spawn_app().await;
+    let confirmation_links = create_unconfirmed_subscriber(&app).await;
+
+    let response = app.get_confirmation_from_browser(confirmation_links.html).await;
+
+    assert_is_redirect_to(&response, "/");
+    let html_page = app.get_home_html().await;
+    assert!(html_page.contains("<p><i>Your subscription is confirmed, welcome aboard!</i></p>"));
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[tokio::test]
+async fn browsers_following_an_invalid_link_are_sent_back_to_the_signup_form() {
+    let app = spawn_app().await;
+    let mut confirmation_link = reqwest::Url::parse(&format!("{}/subscriptions/confirm", app.address)).unwrap();
+    confirmation_link.set_query(Some("subscription_token=forged"));
+
+    let response = app.get_confirmation_from_browser(confirmation_link).await;
+
+    assert_is_redirect_to(&response, "/");
+    let html_page = app.get_home_html().await;
+    assert!(html_page.contains("<p><i>This confirmation link is not valid anymore. Subscribe again to get a new one.</i></p>"));
+    assert!(html_page.contains(r#"<form id="subscribe" action="/subscriptions" method="post">"#));
+}
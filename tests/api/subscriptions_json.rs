@@ -0,0 +1,120 @@
+use crate::helpers::{create_confirmed_subscriber, spawn_app};
+
+use serde_json::json;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+#[tokio::test]
+async fn a_json_subscription_is_saved_and_sent_a_confirmation_email() {
+    let app = spawn_app().await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_subscriptions_json(&json!({ "name": "le guin", "email": "ursula_le_guin@gmail.com" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers()["Content-Type"], "application/json");
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body, json!({ "status": "pending_confirmation" }));
+
+    let saved = sqlx::query!("SELECT email, name, status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.email, "ursula_le_guin@gmail.com");
+    assert_eq!(saved.name, "le guin");
+    assert_eq!(saved.status, "pending_confirmation");
+}
+
+#[tokio::test]
+async fn every_invalid_field_is_reported() {
+    let app = spawn_app().await;
+    let test_cases = vec![
+        (
+            json!({ "name": "", "email": "ursula_le_guin@gmail.com" }),
+            json!([{ "field": "name", "message": "The name must not be empty." }]),
+        ),
+        (
+            json!({ "name": "Ursula", "email": "definitely-not-an-email" }),
+            json!([{ "field": "email", "message": "definitely-not-an-email is not a valid email address." }]),
+        ),
+        (
+            json!({ "name": "<Ursula>" }),
+            json!([
+                { "field": "name", "message": "<Ursula> is not a valid subscriber name." },
+                { "field": "email", "message": "The email address must not be empty." },
+            ]),
+        ),
+    ];
+
+    for (body, errors) in test_cases {
+        let response = app.post_subscriptions_json(&body).await;
+
+        assert_eq!(response.status().as_u16(), 400, "The API did not reject {}.", body);
+        let response_body: serde_json::Value = response.json().await.unwrap();
+        assert_eq!(response_body, json!({ "errors": errors }), "Unexpected errors for {}.", body);
+    }
+}
+
+#[tokio::test]
+async fn a_malformed_body_is_reported_without_a_field() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions", app.address))
+        .header("Content-Type", "application/json; charset=utf-8")
+        .body(r#"{"name": "le guin","#)
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    let errors = body["errors"].as_array().unwrap();
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].get("field").is_none());
+    assert!(errors[0]["message"].as_str().unwrap().starts_with("The request body is not valid"));
+}
+
+#[tokio::test]
+async fn an_already_confirmed_email_is_reported_on_the_email_field() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let email = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .email;
+
+    let response = app.post_subscriptions_json(&json!({ "name": "le guin", "email": email })).await;
+
+    assert_eq!(response.status().as_u16(), 422);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body, json!({ "errors": [{ "field": "email", "message": "Email is already confirmed." }] }));
+}
+
+#[tokio::test]
+async fn form_submissions_asking_for_json_get_json_back() {
+    let app = spawn_app().await;
+
+    let response = app
+        .api_client
+        .post(format!("{}/subscriptions", app.address))
+        .header("Accept", "application/json")
+        .form(&[("name", "Ursula"), ("email", "")])
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 400);
+    let body: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(body, json!({ "errors": [{ "field": "email", "message": "The email address must not be empty." }] }));
+}
@@ -0,0 +1,89 @@
+use crate::helpers::{spawn_app, create_confirmed_subscriber, create_unconfirmed_subscriber, wait_out_the_cooldown, TestApp};
+
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+
+async fn subscriber_email(app: &TestApp) -> String {
+    sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .email
+}
+
+#[tokio::test]
+async fn a_new_link_is_sent_once_the_cooldown_is_over() {
+    let app = spawn_app().await;
+    let old_links = create_unconfirmed_subscriber(&app).await;
+    let email = subscriber_email(&app).await;
+    wait_out_the_cooldown(&app).await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_resend_confirmation(&email).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.text().await.unwrap().contains("a new confirmation link is on its way"));
+    let email_request = app.email_server.received_requests().await.unwrap().pop().unwrap();
+    let new_links = app.get_confirmation_links(&email_request);
+    assert_ne!(old_links.html, new_links.html);
+    assert_eq!(reqwest::get(old_links.html).await.unwrap().status().as_u16(), 401);
+    reqwest::get(new_links.html).await.unwrap().error_for_status().unwrap();
+}
+
+#[tokio::test]
+async fn resends_are_rate_limited() {
+    let app = spawn_app().await;
+    create_unconfirmed_subscriber(&app).await;
+    let email = subscriber_email(&app).await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app.post_resend_confirmation(&email).await;
+
+    // Same answer as for any other address, nothing tells that this one is pending.
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response.headers().get("Retry-After").is_none());
+    assert!(response.text().await.unwrap().contains("a new confirmation link is on its way"));
+}
+
+#[tokio::test]
+async fn nothing_is_sent_to_unknown_or_confirmed_addresses() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let email = subscriber_email(&app).await;
+    wait_out_the_cooldown(&app).await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    for email in [email.as_str(), "nobody@example.com"] {
+        let response = app.post_resend_confirmation(email).await;
+
+        assert_eq!(response.status().as_u16(), 200);
+        assert!(response.text().await.unwrap().contains("a new confirmation link is on its way"));
+    }
+}
+
+#[tokio::test]
+async fn an_invalid_email_is_rejected_with_a_400() {
+    let app = spawn_app().await;
+
+    let response = app.post_resend_confirmation("not-an-email").await;
+
+    assert_eq!(response.status().as_u16(), 400);
+}
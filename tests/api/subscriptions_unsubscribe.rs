@@ -0,0 +1,163 @@
+use crate::helpers::{spawn_app, create_confirmed_subscriber, TestApp};
+
+use uuid::Uuid;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::domain::UnsubscribeToken;
+
+async fn unsubscribe_token_for_the_only_subscriber(app: &TestApp) -> String {
+    let subscriber = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription");
+
+    UnsubscribeToken::generate(subscriber.id, &app.hmac_secret).as_ref().to_owned()
+}
+
+#[tokio::test]
+async fn unsubscribe_without_token_is_rejected_with_a_400() {
+    let app = spawn_app().await;
+
+    let response = reqwest::get(&format!("{}/subscriptions/unsubscribe", app.address))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status().as_u16(), 400);
+}
+
+#[tokio::test]
+async fn unsubscribe_with_a_forged_token_is_rejected_with_a_401() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+
+    let subscriber = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    let forged_token = base64::encode_config(subscriber.id.as_bytes(), base64::URL_SAFE_NO_PAD);
+
+    assert_eq!(app.get_unsubscribe(&forged_token).await.status().as_u16(), 401);
+    assert_eq!(app.post_unsubscribe(&forged_token).await.status().as_u16(), 401);
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[tokio::test]
+async fn unsubscribe_page_asks_for_a_confirmation() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let token = unsubscribe_token_for_the_only_subscriber(&app).await;
+
+    let response = app.get_unsubscribe(&token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains(&format!(
+        r#"<form action="/subscriptions/unsubscribe?unsubscribe_token={}" method="post">"#,
+        token
+    )));
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    assert_eq!(saved.status, "confirmed");
+}
+
+#[tokio::test]
+async fn posting_to_the_unsubscribe_link_unsubscribes_a_subscriber() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let token = unsubscribe_token_for_the_only_subscriber(&app).await;
+
+    let response = app.post_unsubscribe(&token).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    assert_eq!(saved.status, "unsubscribed");
+}
+
+#[tokio::test]
+async fn unsubscribe_token_for_an_unknown_subscriber_does_not_fail() {
+    let app = spawn_app().await;
+    let token = UnsubscribeToken::generate(Uuid::new_v4(), &app.hmac_secret);
+
+    let response = app.post_unsubscribe(token.as_ref()).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn newsletters_are_not_delivered_to_unsubscribed_subscribers() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let token = unsubscribe_token_for_the_only_subscriber(&app).await;
+    app.post_unsubscribe(&token).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    app.user_login().await;
+    app.publish_newsletter(&serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string(),
+    }))
+    .await;
+
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn unsubscribed_subscriber_can_subscribe_again() {
+    let app = spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let token = unsubscribe_token_for_the_only_subscriber(&app).await;
+    app.post_unsubscribe(&token).await;
+
+    Mock::given(path(format!("/api/send/{}", app.inbox_id)))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let email = sqlx::query!("SELECT email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .email;
+    let body = serde_urlencoded::to_string(serde_json::json!({
+        "name": "le guin",
+        "email": email,
+    }))
+    .unwrap();
+
+    app.post_subscriptions(body).await.error_for_status().unwrap();
+
+    let email_request = &app.email_server.received_requests().await.unwrap().pop().unwrap();
+    let confirmation_links = app.get_confirmation_links(email_request);
+    reqwest::get(confirmation_links.html).await.unwrap().error_for_status().unwrap();
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    assert_eq!(saved.status, "confirmed");
+}